@@ -29,6 +29,8 @@ struct Header<'ar> {
     replication_sequence_number: i64,
     #[allow(unused)]
     replication_base_url: &'ar str,
+    #[allow(unused)]
+    tag_policy: Option<&'ar str>,
 }
 
 #[derive(Debug)]
@@ -88,7 +90,7 @@ impl<'ar> Member<'ar> {
         let strings = archive.stringtable();
         archive
             .relation_members()
-            .at(relation_idx as usize)
+            .at(relation_idx)
             .map(move |member| {
                 let res = match member {
                     RelationMembersRef::NodeMember(m) => Member {
@@ -148,6 +150,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         replication_timestamp: header.replication_timestamp(),
         replication_sequence_number: header.replication_sequence_number(),
         replication_base_url: strings.substring(header.replication_base_url_idx() as usize)?,
+        tag_policy: header
+            .tag_policy_idx()
+            .map(|idx| strings.substring(idx as usize))
+            .transpose()?,
     };
     println!("{:#?}", header);
 
@@ -18,8 +18,7 @@
 //! The code in this example file is released into the Public Domain.
 
 use clap::Parser;
-use osmflat::{iter_tags, FileResourceStorage, Node, Osm, Relation, RelationMembersRef, Way};
-use smallvec::{smallvec, SmallVec};
+use osmflat::{iter_tags, relation_polygons, FileResourceStorage, Osm, Relation, Way, WayRef};
 use svg::{
     node::{self, element},
     Document,
@@ -28,7 +27,6 @@ use svg::{
 use std::f64;
 use std::fmt::Write;
 use std::io;
-use std::ops::Range;
 use std::path::PathBuf;
 use std::str;
 
@@ -55,49 +53,14 @@ impl GeoCoord {
     }
 }
 
-/// Convert osmflat Node into GeoCoord.
-impl GeoCoord {
-    fn from_node(node: &Node, coord_scale: i32) -> Self {
-        Self {
-            lat: node.lat() as f64 / coord_scale as f64,
-            lon: node.lon() as f64 / coord_scale as f64,
-        }
-    }
-}
-
-/// Polyline which can be transformed into an iterator over `GeoCoord`'s.
-struct Polyline {
-    inner: SmallVec<[Range<u64>; 4]>,
-}
-
-impl From<Range<u64>> for Polyline {
-    fn from(range: Range<u64>) -> Self {
-        Self {
-            inner: smallvec![range],
-        }
+impl From<(f64, f64)> for GeoCoord {
+    fn from((lat, lon): (f64, f64)) -> Self {
+        Self { lat, lon }
     }
 }
 
-impl Polyline {
-    #[allow(clippy::iter_overeager_cloned)]
-    fn into_iter(self, archive: &Osm) -> Option<impl Iterator<Item = GeoCoord> + '_> {
-        let nodes_index = archive.nodes_index();
-        let nodes = archive.nodes();
-        let mut indices = self.inner.iter().cloned().flatten();
-        let scale = archive.header().coord_scale();
-        if indices.any(|idx| nodes_index[idx as usize].value().is_none()) {
-            None
-        } else {
-            let indices = self.inner.into_iter().flatten();
-            Some(indices.map(move |idx| {
-                GeoCoord::from_node(
-                    &nodes[nodes_index[idx as usize].value().unwrap() as usize],
-                    scale,
-                )
-            }))
-        }
-    }
-}
+/// Polyline of coordinates.
+type Polyline = Vec<GeoCoord>;
 
 /// Categories of features we support in this renderer.
 #[derive(Debug, Clone, Copy)]
@@ -117,38 +80,31 @@ struct Feature {
 }
 
 impl Feature {
-    fn into_polyline(self, archive: &Osm) -> Option<Polyline> {
+    /// Returns the polylines of the feature; for areas these are the rings of
+    /// its polygons. Features with missing nodes or broken rings are skipped.
+    fn into_polylines(self, archive: &Osm) -> Vec<Polyline> {
         match self.cat {
-            Category::Road | Category::River(_) => {
-                Some(way_into_polyline(&archive.ways()[self.idx]))
-            }
-            Category::Park | Category::Water => multipolygon_into_polyline(archive, self.idx),
+            Category::Road | Category::River(_) => archive
+                .way(self.idx)
+                .and_then(way_into_polyline)
+                .into_iter()
+                .collect(),
+            Category::Park | Category::Water => archive
+                .relation(self.idx)
+                .and_then(|relation| relation_polygons(relation).ok())
+                .into_iter()
+                .flatten()
+                .flat_map(|polygon| std::iter::once(polygon.outer).chain(polygon.inners))
+                .map(|ring| ring.into_iter().map(GeoCoord::from).collect())
+                .collect(),
         }
     }
 }
 
-fn way_into_polyline(way: &Way) -> Polyline {
-    Polyline {
-        inner: smallvec![way.refs()],
-    }
-}
-
-fn multipolygon_into_polyline(archive: &Osm, idx: usize) -> Option<Polyline> {
-    let members = archive.relation_members().at(idx);
-    let strings = archive.stringtable();
-    let ways = archive.ways();
-
-    let inner: Option<SmallVec<[Range<u64>; 4]>> = members
-        .filter_map(|m| match m {
-            RelationMembersRef::WayMember(way_member)
-                if strings.substring(way_member.role_idx() as usize) == Ok("outer") =>
-            {
-                Some(way_member.way_idx().map(|idx| ways[idx as usize].refs()))
-            }
-            _ => None,
-        })
-        .collect();
-    inner.map(|inner| Polyline { inner })
+fn way_into_polyline(way: WayRef) -> Option<Polyline> {
+    way.nodes()
+        .map(|node| node.map(|node| GeoCoord::from(node.lat_lon())))
+        .collect()
 }
 
 /// Classifies all features from osmflat we want to render.
@@ -237,7 +193,6 @@ fn classify_relation(archive: &Osm, relation: &Relation) -> Option<Category> {
 
 /// Renders svg from classified polylines.
 fn render_svg<P>(
-    archive: &Osm,
     classified_polylines: P,
     output: PathBuf,
     width: u32,
@@ -276,11 +231,7 @@ where
     let mut points = String::new(); // reuse string buffer inside the for-loop
     for (poly, cat) in classified_polylines {
         points.clear();
-        let poly_iter = match poly.into_iter(archive) {
-            Some(x) => x,
-            None => continue,
-        };
-        for coord in poly_iter {
+        for coord in poly {
             // collect extent
             min_coord = min_coord.min(coord);
             max_coord = max_coord.max(coord);
@@ -377,17 +328,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let archive = Osm::open(storage)?;
 
     let features = classify(&archive);
-    let archive_inner = archive.clone();
-    let classified_polylines = features.filter_map(move |f| {
+    let classified_polylines = features.flat_map(|f| {
         let cat = f.cat;
-        f.into_polyline(&archive_inner).map(|p| (p, cat))
+        f.into_polylines(&archive)
+            .into_iter()
+            .map(move |p| (p, cat))
     });
-    render_svg(
-        &archive,
-        classified_polylines,
-        args.output,
-        args.width,
-        args.height,
-    )?;
+    render_svg(classified_polylines, args.output, args.width, args.height)?;
     Ok(())
 }
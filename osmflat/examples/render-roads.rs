@@ -1,10 +1,13 @@
 //! Renders all roads by using a simple Bresenham line algorithm.
 //!
+//! If a bounding box is given, only roads intersecting it are rendered. The
+//! roads are looked up in the spatial index of the archive.
+//!
 //! LICENSE
 //!
 //! The code in this example file is released into the Public Domain.
 
-use osmflat::{find_tag_by, FileResourceStorage, Node, Osm, Way};
+use osmflat::{find_tag_by, ways_in_bbox, BoundingBox, FileResourceStorage, Node, Osm, Way};
 
 use clap::Parser;
 use itertools::Itertools;
@@ -119,11 +122,16 @@ fn way_filter(way: &Way, archive: &Osm) -> bool {
     .is_some()
 }
 
-fn roads(archive: &Osm) -> impl Iterator<Item = &Way> {
-    archive
-        .ways()
-        .iter()
-        .filter(move |&way| way_filter(way, archive))
+fn roads(archive: &Osm, bbox: Option<BoundingBox>) -> Box<dyn Iterator<Item = &Way> + '_> {
+    let ways: Box<dyn Iterator<Item = &Way>> = match bbox {
+        Some(bbox) => {
+            let ways = archive.ways();
+            let indexes = ways_in_bbox(archive, &bbox).expect("archive has no spatial index");
+            Box::new(indexes.map(move |idx| &ways[idx]))
+        }
+        None => Box::new(archive.ways().iter()),
+    };
+    Box::new(ways.filter(move |&way| way_filter(way, archive)))
 }
 
 /// Bresenham's line algorithm
@@ -154,12 +162,26 @@ fn bresenham(mut x0: i32, mut y0: i32, x1: i32, y1: i32) -> impl Iterator<Item =
     })
 }
 
-fn render(archive: &Osm, width: u32) -> Image {
+fn render(archive: &Osm, width: u32, bbox: Option<BoundingBox>) -> Image {
     // compute extent
-    let coords = roads(archive)
-        .filter_map(|way| way_coords(archive, way))
-        .flatten();
-    let (min, max) = compute_bounds(coords);
+    let (min, max) = match bbox {
+        Some(bbox) => (
+            GeoCoord {
+                lat: bbox.bottom,
+                lon: bbox.left,
+            },
+            GeoCoord {
+                lat: bbox.top,
+                lon: bbox.right,
+            },
+        ),
+        None => {
+            let coords = roads(archive, None)
+                .filter_map(|way| way_coords(archive, way))
+                .flatten();
+            compute_bounds(coords)
+        }
+    };
 
     // compute ratio and height
     let ratio = (max.lat - min.lat) / (max.lon - min.lon) / (max.lat / 180. * PI).cos();
@@ -171,13 +193,16 @@ fn render(archive: &Osm, width: u32) -> Image {
     // draw
     let mut image = Image::new(width, height);
 
-    let line_segments = roads(archive)
+    let line_segments = roads(archive, bbox)
         .filter_map(|way| Some(way_coords(archive, way)?.map(t).tuple_windows()))
         .flatten();
 
     for ((x0, y0), (x1, y1)) in line_segments {
         for (x, y) in bresenham(x0, y0, x1, y1) {
-            image.set_black(x as u32, y as u32);
+            // roads intersecting the bounding box might exceed the image
+            if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+                image.set_black(x as u32, y as u32);
+            }
         }
     }
 
@@ -195,6 +220,27 @@ struct Args {
     /// width of the image (height is derived from ratio)
     #[clap(long, short = 'w', default_value = "4320")]
     width: u32,
+    /// only render roads in the bounding box `left,bottom,right,top` (in
+    /// degrees); requires an archive compiled with `--spatial-index`
+    #[clap(long, parse(try_from_str = parse_bbox))]
+    bbox: Option<BoundingBox>,
+}
+
+fn parse_bbox(s: &str) -> Result<BoundingBox, String> {
+    let coords: Vec<f64> = s
+        .split(',')
+        .map(|x| x.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|e: std::num::ParseFloatError| e.to_string())?;
+    match coords[..] {
+        [left, bottom, right, top] => Ok(BoundingBox {
+            left,
+            right,
+            top,
+            bottom,
+        }),
+        _ => Err("expected 'left,bottom,right,top'".into()),
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -202,7 +248,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let archive = Osm::open(FileResourceStorage::new(args.input))?;
 
-    let image = render(&archive, args.width);
+    let image = render(&archive, args.width, args.bbox);
 
     let buf = BufWriter::new(File::create(&args.output)?);
     let mut encoder = png::Encoder::new(buf, image.w, image.h);
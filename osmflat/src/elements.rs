@@ -0,0 +1,442 @@
+//! Borrowed views of nodes, ways and relations.
+//!
+//! A view carries the archive together with the index of its element, and
+//! resolves the references of the element, i.e. its tags, the nodes of a way,
+//! and the members of a relation with their roles. Coordinates are scaled by
+//! `header.coord_scale` to degrees.
+
+use crate::tags::{find_tag, has_tag, iter_tags};
+use crate::{Info, Node, Osm, Relation, RelationMembersRef, Way};
+
+use std::fmt;
+
+impl Osm {
+    /// Returns a view of the node at index `idx` in the `nodes` vector.
+    #[inline]
+    pub fn node(&self, idx: usize) -> Option<NodeRef> {
+        if idx < self.nodes().len() {
+            Some(NodeRef { archive: self, idx })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a view of the way at index `idx` in the `ways` vector.
+    #[inline]
+    pub fn way(&self, idx: usize) -> Option<WayRef> {
+        if idx < self.ways().len() {
+            Some(WayRef { archive: self, idx })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a view of the relation at index `idx` in the `relations`
+    /// vector.
+    #[inline]
+    pub fn relation(&self, idx: usize) -> Option<RelationRef> {
+        if idx < self.relations().len() {
+            Some(RelationRef { archive: self, idx })
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over views of all nodes.
+    #[inline]
+    pub fn node_refs(&self) -> impl ExactSizeIterator<Item = NodeRef> + Clone {
+        (0..self.nodes().len()).map(move |idx| NodeRef { archive: self, idx })
+    }
+
+    /// Returns an iterator over views of all ways.
+    #[inline]
+    pub fn way_refs(&self) -> impl ExactSizeIterator<Item = WayRef> + Clone {
+        (0..self.ways().len()).map(move |idx| WayRef { archive: self, idx })
+    }
+
+    /// Returns an iterator over views of all relations.
+    #[inline]
+    pub fn relation_refs(&self) -> impl ExactSizeIterator<Item = RelationRef> + Clone {
+        (0..self.relations().len()).map(move |idx| RelationRef { archive: self, idx })
+    }
+
+    /// Returns a view of the node with OSM id `id`.
+    ///
+    /// Requires an archive compiled with `osmflatc --id-index`.
+    #[inline]
+    pub fn node_by_id(&self, id: u64) -> Option<NodeRef> {
+        self.node(self.node_index_by_id(id)?)
+    }
+
+    /// Returns a view of the way with OSM id `id`.
+    ///
+    /// Requires an archive compiled with `osmflatc --id-index`.
+    #[inline]
+    pub fn way_by_id(&self, id: u64) -> Option<WayRef> {
+        self.way(self.way_index_by_id(id)?)
+    }
+
+    /// Returns a view of the relation with OSM id `id`.
+    ///
+    /// Requires an archive compiled with `osmflatc --id-index`.
+    #[inline]
+    pub fn relation_by_id(&self, id: u64) -> Option<RelationRef> {
+        self.relation(self.relation_index_by_id(id)?)
+    }
+}
+
+/// View of a node.
+#[derive(Clone, Copy)]
+pub struct NodeRef<'a> {
+    archive: &'a Osm,
+    idx: usize,
+}
+
+impl<'a> NodeRef<'a> {
+    /// Index of the node in the `nodes` vector.
+    #[inline]
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// The raw node.
+    #[inline]
+    pub fn node(&self) -> &'a Node {
+        &self.archive.nodes()[self.idx]
+    }
+
+    /// OSM id of the node.
+    ///
+    /// Requires an archive compiled with `osmflatc --ids`.
+    #[inline]
+    pub fn id(&self) -> Option<u64> {
+        Some(self.archive.ids()?.nodes()[self.idx].value())
+    }
+
+    /// Latitude and longitude of the node in degrees.
+    #[inline]
+    pub fn lat_lon(&self) -> (f64, f64) {
+        let coord_scale = f64::from(self.archive.header().coord_scale());
+        let node = self.node();
+        (
+            f64::from(node.lat()) / coord_scale,
+            f64::from(node.lon()) / coord_scale,
+        )
+    }
+
+    /// Returns an iterator over the keys and values of the tags of the node.
+    #[inline]
+    pub fn tags(&self) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone {
+        iter_tags(self.archive, self.node().tags())
+    }
+
+    /// Returns the value of the tag with the given `key`.
+    #[inline]
+    pub fn tag(&self, key: &[u8]) -> Option<&'a [u8]> {
+        find_tag(self.archive, self.node().tags(), key)
+    }
+
+    /// Checks if the node has a tag with the given `key` and `value`.
+    #[inline]
+    pub fn has_tag(&self, key: &[u8], value: &[u8]) -> bool {
+        has_tag(self.archive, self.node().tags(), key, value)
+    }
+
+    /// Metadata of the node.
+    ///
+    /// Requires an archive compiled with `osmflatc --info`.
+    #[inline]
+    pub fn info(&self) -> Option<&'a Info> {
+        self.archive.info()?.nodes().get(self.idx)
+    }
+}
+
+impl fmt::Debug for NodeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeRef")
+            .field("idx", &self.idx)
+            .field("node", self.node())
+            .finish()
+    }
+}
+
+/// View of a way.
+#[derive(Clone, Copy)]
+pub struct WayRef<'a> {
+    archive: &'a Osm,
+    idx: usize,
+}
+
+impl<'a> WayRef<'a> {
+    /// Index of the way in the `ways` vector.
+    #[inline]
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// The raw way.
+    #[inline]
+    pub fn way(&self) -> &'a Way {
+        &self.archive.ways()[self.idx]
+    }
+
+    /// OSM id of the way.
+    ///
+    /// Requires an archive compiled with `osmflatc --ids`.
+    #[inline]
+    pub fn id(&self) -> Option<u64> {
+        Some(self.archive.ids()?.ways()[self.idx].value())
+    }
+
+    /// Returns an iterator over the nodes of the way.
+    ///
+    /// Nodes which are not contained in the archive, e.g. outside of the
+    /// compiled area, are `None`.
+    #[inline]
+    pub fn nodes(&self) -> impl Iterator<Item = Option<NodeRef<'a>>> + Clone {
+        let archive = self.archive;
+        let nodes_index = archive.nodes_index();
+        self.way().refs().map(move |idx| {
+            nodes_index[idx as usize].value().map(|idx| NodeRef {
+                archive,
+                idx: idx as usize,
+            })
+        })
+    }
+
+    /// Returns an iterator over the keys and values of the tags of the way.
+    #[inline]
+    pub fn tags(&self) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone {
+        iter_tags(self.archive, self.way().tags())
+    }
+
+    /// Returns the value of the tag with the given `key`.
+    #[inline]
+    pub fn tag(&self, key: &[u8]) -> Option<&'a [u8]> {
+        find_tag(self.archive, self.way().tags(), key)
+    }
+
+    /// Checks if the way has a tag with the given `key` and `value`.
+    #[inline]
+    pub fn has_tag(&self, key: &[u8], value: &[u8]) -> bool {
+        has_tag(self.archive, self.way().tags(), key, value)
+    }
+
+    /// Metadata of the way.
+    ///
+    /// Requires an archive compiled with `osmflatc --info`.
+    #[inline]
+    pub fn info(&self) -> Option<&'a Info> {
+        self.archive.info()?.ways().get(self.idx)
+    }
+}
+
+impl fmt::Debug for WayRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WayRef")
+            .field("idx", &self.idx)
+            .field("way", self.way())
+            .finish()
+    }
+}
+
+/// View of a relation.
+#[derive(Clone, Copy)]
+pub struct RelationRef<'a> {
+    archive: &'a Osm,
+    idx: usize,
+}
+
+impl<'a> RelationRef<'a> {
+    /// Index of the relation in the `relations` vector.
+    #[inline]
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// The raw relation.
+    #[inline]
+    pub fn relation(&self) -> &'a Relation {
+        &self.archive.relations()[self.idx]
+    }
+
+    /// OSM id of the relation.
+    ///
+    /// Requires an archive compiled with `osmflatc --ids`.
+    #[inline]
+    pub fn id(&self) -> Option<u64> {
+        Some(self.archive.ids()?.relations()[self.idx].value())
+    }
+
+    /// Returns an iterator over the members of the relation.
+    #[inline]
+    pub fn members(&self) -> impl Iterator<Item = MemberRef<'a>> {
+        let archive = self.archive;
+        let strings = archive.stringtable();
+        archive
+            .relation_members()
+            .at(self.idx)
+            .map(move |member| match member {
+                RelationMembersRef::NodeMember(m) => MemberRef::Node(
+                    m.node_idx().map(|idx| NodeRef {
+                        archive,
+                        idx: idx as usize,
+                    }),
+                    strings.substring_raw(m.role_idx() as usize),
+                ),
+                RelationMembersRef::WayMember(m) => MemberRef::Way(
+                    m.way_idx().map(|idx| WayRef {
+                        archive,
+                        idx: idx as usize,
+                    }),
+                    strings.substring_raw(m.role_idx() as usize),
+                ),
+                RelationMembersRef::RelationMember(m) => MemberRef::Relation(
+                    m.relation_idx().map(|idx| RelationRef {
+                        archive,
+                        idx: idx as usize,
+                    }),
+                    strings.substring_raw(m.role_idx() as usize),
+                ),
+            })
+    }
+
+    /// Returns an iterator over the keys and values of the tags of the
+    /// relation.
+    #[inline]
+    pub fn tags(&self) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone {
+        iter_tags(self.archive, self.relation().tags())
+    }
+
+    /// Returns the value of the tag with the given `key`.
+    #[inline]
+    pub fn tag(&self, key: &[u8]) -> Option<&'a [u8]> {
+        find_tag(self.archive, self.relation().tags(), key)
+    }
+
+    /// Checks if the relation has a tag with the given `key` and `value`.
+    #[inline]
+    pub fn has_tag(&self, key: &[u8], value: &[u8]) -> bool {
+        has_tag(self.archive, self.relation().tags(), key, value)
+    }
+
+    /// Metadata of the relation.
+    ///
+    /// Requires an archive compiled with `osmflatc --info`.
+    #[inline]
+    pub fn info(&self) -> Option<&'a Info> {
+        self.archive.info()?.relations().get(self.idx)
+    }
+}
+
+impl fmt::Debug for RelationRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RelationRef")
+            .field("idx", &self.idx)
+            .field("relation", self.relation())
+            .finish()
+    }
+}
+
+/// Member of a relation with its role.
+///
+/// Members which are not contained in the archive, e.g. outside of the
+/// compiled area, are `None`.
+#[derive(Debug, Clone, Copy)]
+pub enum MemberRef<'a> {
+    /// Node member
+    Node(Option<NodeRef<'a>>, &'a [u8]),
+    /// Way member
+    Way(Option<WayRef<'a>>, &'a [u8]),
+    /// Relation member
+    Relation(Option<RelationRef<'a>>, &'a [u8]),
+}
+
+impl<'a> MemberRef<'a> {
+    /// Role of the member as raw bytes.
+    #[inline]
+    pub fn role(&self) -> &'a [u8] {
+        match *self {
+            MemberRef::Node(_, role) | MemberRef::Way(_, role) | MemberRef::Relation(_, role) => {
+                role
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{Member, TestArchive};
+    use crate::MemberRef;
+
+    #[test]
+    fn test_elements() {
+        let archive = TestArchive::default()
+            .node((1, 2), &[])
+            .node((3, 4), &[("name", "last")])
+            .way(&[0, 1], &[])
+            .way(&[1, 2], &[("highway", "path")])
+            .relation(&[], &[])
+            .relation(
+                &[
+                    Member::Node(1, "stop"),
+                    Member::Way(1, ""),
+                    Member::Relation(0, "sub"),
+                    Member::Relation(2, "missing"),
+                ],
+                &[("type", "route")],
+            )
+            .build();
+
+        assert_eq!(archive.node_refs().len(), 2);
+        assert_eq!(archive.way_refs().len(), 2);
+        assert_eq!(archive.relation_refs().len(), 2);
+        assert!(archive.node(2).is_none());
+        assert!(archive.way(2).is_none());
+        assert!(archive.relation(2).is_none());
+
+        let node = archive.node(1).unwrap();
+        assert_eq!(node.lat_lon(), (3., 4.));
+        assert_eq!(node.tag(b"name"), Some(&b"last"[..]));
+
+        let way = archive.way(1).unwrap();
+        let nodes: Vec<_> = way
+            .nodes()
+            .map(|node| node.map(|node| node.idx()))
+            .collect();
+        assert_eq!(nodes, vec![Some(1), None]);
+        assert!(way.has_tag(b"highway", b"path"));
+
+        let relation = archive.relation(1).unwrap();
+        assert_eq!(relation.tag(b"type"), Some(&b"route"[..]));
+        let members: Vec<_> = relation
+            .members()
+            .map(|member| match member {
+                MemberRef::Node(node, role) => (node.map(|node| node.idx()), role),
+                MemberRef::Way(way, role) => (way.map(|way| way.idx()), role),
+                MemberRef::Relation(relation, role) => (relation.map(|r| r.idx()), role),
+            })
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                (Some(1), &b"stop"[..]),
+                (Some(1), b""),
+                (Some(0), b"sub"),
+                (None, b"missing")
+            ]
+        );
+        assert!(archive.relation(0).unwrap().members().next().is_none());
+    }
+
+    #[test]
+    fn test_empty_archive() {
+        let archive = TestArchive::default().build();
+        assert_eq!(archive.node_refs().len(), 0);
+        assert_eq!(archive.way_refs().len(), 0);
+        assert_eq!(archive.relation_refs().len(), 0);
+        assert!(archive.node(0).is_none());
+        assert!(archive.way(0).is_none());
+        assert!(archive.relation(0).is_none());
+    }
+}
@@ -0,0 +1,508 @@
+//! Assembly of areas from closed ways and multipolygon relations.
+//!
+//! The member ways of a relation of type `multipolygon` or `boundary` are
+//! joined at their end nodes into closed rings, independently of their order
+//! and direction in the relation. The rings are then classified by nesting:
+//! a ring contained in an even number of other rings is an outer ring, every
+//! other ring is an inner ring (hole) of the ring directly containing it. This
+//! way, missing or wrong roles do not break the assembled polygons.
+//!
+//! Outer rings are oriented counterclockwise and inner rings clockwise. All
+//! coordinates are (latitude, longitude) in degrees.
+
+use crate::{MemberRef, RelationRef, WayRef};
+
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+
+/// Closed ring of (latitude, longitude) coordinates in degrees.
+///
+/// The first and the last coordinate of a ring are equal.
+pub type Ring = Vec<(f64, f64)>;
+
+/// Polygon with an outer ring and its inner rings (holes).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polygon {
+    /// Outer ring, oriented counterclockwise
+    pub outer: Ring,
+    /// Inner rings, oriented clockwise
+    pub inners: Vec<Ring>,
+}
+
+/// Error when assembling an area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryError {
+    /// The way is not closed, or the relation is neither a multipolygon nor a
+    /// boundary, or it has no member ways.
+    NotAnArea,
+    /// A node of the way is not contained in the archive.
+    MissingNode {
+        /// Index of the way in the `ways` vector
+        way_idx: usize,
+    },
+    /// A member way of the relation is not contained in the archive.
+    MissingWay {
+        /// Index of the relation in the `relations` vector
+        relation_idx: usize,
+    },
+    /// The member ways of the relation do not form closed rings.
+    OpenRing {
+        /// Index of the first node of the broken ring in the `nodes` vector
+        first_node_idx: usize,
+        /// Index of the last node of the broken ring in the `nodes` vector
+        last_node_idx: usize,
+    },
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GeometryError::NotAnArea => write!(f, "element is not an area"),
+            GeometryError::MissingNode { way_idx } => {
+                write!(f, "way {} has nodes missing in the archive", way_idx)
+            }
+            GeometryError::MissingWay { relation_idx } => write!(
+                f,
+                "relation {} has member ways missing in the archive",
+                relation_idx
+            ),
+            GeometryError::OpenRing {
+                first_node_idx,
+                last_node_idx,
+            } => write!(
+                f,
+                "ring from node {} to node {} is not closed",
+                first_node_idx, last_node_idx
+            ),
+        }
+    }
+}
+
+impl error::Error for GeometryError {}
+
+/// Assembles the polygon of a closed way.
+pub fn way_polygon(way: WayRef) -> Result<Polygon, GeometryError> {
+    let nodes = way_nodes(way)?;
+    if nodes.len() < 4 || nodes[0].0 != nodes[nodes.len() - 1].0 {
+        return Err(GeometryError::NotAnArea);
+    }
+    let mut outer: Ring = nodes.into_iter().map(|(_, coord)| coord).collect();
+    orient(&mut outer, true);
+    Ok(Polygon {
+        outer,
+        inners: Vec::new(),
+    })
+}
+
+/// Assembles the polygons of a relation of type `multipolygon` or
+/// `boundary`.
+///
+/// Only way members with role `outer`, `inner` or without a role are
+/// considered. Fails if any of these ways is not contained in the archive,
+/// or if they do not form closed rings.
+pub fn relation_polygons(relation: RelationRef) -> Result<Vec<Polygon>, GeometryError> {
+    match relation.tag(b"type") {
+        Some(b"multipolygon") | Some(b"boundary") => (),
+        _ => return Err(GeometryError::NotAnArea),
+    }
+
+    let mut way_indices = HashSet::new();
+    let mut segments = Vec::new();
+    for member in relation.members() {
+        match member {
+            MemberRef::Way(way, b"outer")
+            | MemberRef::Way(way, b"inner")
+            | MemberRef::Way(way, b"") => {
+                let way = way.ok_or(GeometryError::MissingWay {
+                    relation_idx: relation.idx(),
+                })?;
+                // ways listed multiple times are only used once
+                if way_indices.insert(way.idx()) {
+                    segments.push(way_nodes(way)?);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let rings = join_rings(segments)?;
+    if rings.is_empty() {
+        return Err(GeometryError::NotAnArea);
+    }
+    Ok(nest_rings(rings))
+}
+
+/// Node of a ring: index in the `nodes` vector and coordinates.
+type RingNode = (usize, (f64, f64));
+
+fn way_nodes(way: WayRef) -> Result<Vec<RingNode>, GeometryError> {
+    way.nodes()
+        .map(|node| {
+            node.map(|node| (node.idx(), node.lat_lon()))
+                .ok_or(GeometryError::MissingNode { way_idx: way.idx() })
+        })
+        .collect()
+}
+
+/// Joins segments at their end nodes into closed rings.
+///
+/// Segments may be given in any order and direction. Degenerated segments and
+/// rings are dropped.
+fn join_rings(segments: Vec<Vec<RingNode>>) -> Result<Vec<Ring>, GeometryError> {
+    let is_closed = |nodes: &[RingNode]| nodes[0].0 == nodes[nodes.len() - 1].0;
+
+    // open segments by their end nodes
+    let mut ends: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, segment) in segments.iter().enumerate() {
+        if segment.len() >= 2 && !is_closed(segment) {
+            ends.entry(segment[0].0).or_default().push(idx);
+            ends.entry(segment[segment.len() - 1].0)
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    let mut used: Vec<bool> = segments.iter().map(|s| s.len() < 2).collect();
+    let mut rings = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let mut ring = segments[start].clone();
+        while !is_closed(&ring) {
+            let end = ring[ring.len() - 1].0;
+            let next = ends
+                .get(&end)
+                .and_then(|candidates| candidates.iter().cloned().find(|&idx| !used[idx]));
+            let next = next.ok_or(GeometryError::OpenRing {
+                first_node_idx: ring[0].0,
+                last_node_idx: end,
+            })?;
+            used[next] = true;
+
+            let segment = &segments[next];
+            if segment[0].0 == end {
+                ring.extend(segment[1..].iter().cloned());
+            } else {
+                ring.extend(segment.iter().rev().skip(1).cloned());
+            }
+        }
+
+        if ring.len() >= 4 {
+            rings.push(ring.into_iter().map(|(_, coord)| coord).collect());
+        }
+    }
+    Ok(rings)
+}
+
+/// Classifies rings into outer and inner rings by their nesting, and groups
+/// them into oriented polygons.
+fn nest_rings(mut rings: Vec<Ring>) -> Vec<Polygon> {
+    let bboxes: Vec<_> = rings.iter().map(|ring| bbox(ring)).collect();
+
+    // rings containing each ring
+    let containing: Vec<Vec<usize>> = (0..rings.len())
+        .map(|inner| {
+            (0..rings.len())
+                .filter(|&outer| {
+                    outer != inner
+                        && contains_bbox(&bboxes[outer], &bboxes[inner])
+                        && contains_ring(&rings[outer], &rings[inner])
+                })
+                .collect()
+        })
+        .collect();
+    let depth: Vec<usize> = containing.iter().map(Vec::len).collect();
+
+    let mut polygon_idx = vec![None; rings.len()];
+    let mut polygons = Vec::new();
+    for idx in 0..rings.len() {
+        if depth[idx] % 2 == 0 {
+            let mut outer = std::mem::take(&mut rings[idx]);
+            orient(&mut outer, true);
+            polygon_idx[idx] = Some(polygons.len());
+            polygons.push(Polygon {
+                outer,
+                inners: Vec::new(),
+            });
+        }
+    }
+    for idx in 0..rings.len() {
+        if depth[idx] % 2 == 1 {
+            // the directly containing outer ring is the deepest one
+            let parent = containing[idx]
+                .iter()
+                .cloned()
+                .filter(|&outer| polygon_idx[outer].is_some())
+                .max_by_key(|&outer| depth[outer])
+                .and_then(|outer| polygon_idx[outer]);
+            let mut ring = std::mem::take(&mut rings[idx]);
+            match parent {
+                Some(parent) => {
+                    orient(&mut ring, false);
+                    polygons[parent].inners.push(ring);
+                }
+                None => {
+                    // only possible for crossing rings
+                    orient(&mut ring, true);
+                    polygons.push(Polygon {
+                        outer: ring,
+                        inners: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    polygons
+}
+
+/// Bounding box (min lat, min lon, max lat, max lon) of a ring.
+fn bbox(ring: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    ring.iter().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |(min_lat, min_lon, max_lat, max_lon), &(lat, lon)| {
+            (
+                min_lat.min(lat),
+                min_lon.min(lon),
+                max_lat.max(lat),
+                max_lon.max(lon),
+            )
+        },
+    )
+}
+
+fn contains_bbox(outer: &(f64, f64, f64, f64), inner: &(f64, f64, f64, f64)) -> bool {
+    outer.0 <= inner.0 && outer.1 <= inner.1 && inner.2 <= outer.2 && inner.3 <= outer.3
+}
+
+/// Checks if the ring `inner` lies inside of the ring `outer`.
+///
+/// Rings of a valid area do not cross, but may touch in single nodes. Hence,
+/// it is enough to test a single coordinate of `inner` which is not a
+/// coordinate of `outer`.
+fn contains_ring(outer: &[(f64, f64)], inner: &[(f64, f64)]) -> bool {
+    inner
+        .iter()
+        .find(|coord| !outer.contains(coord))
+        .map_or(false, |&coord| contains_point(outer, coord))
+}
+
+/// Checks if `coord` lies inside of `ring` by casting a ray along the
+/// latitude.
+fn contains_point(ring: &[(f64, f64)], (lat, lon): (f64, f64)) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let ((lat1, lon1), (lat2, lon2)) = (edge[0], edge[1]);
+        if (lat1 > lat) != (lat2 > lat) {
+            let lon_at_lat = lon1 + (lat - lat1) / (lat2 - lat1) * (lon2 - lon1);
+            if lon < lon_at_lat {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Twice the signed area of a ring in the (longitude, latitude) plane; it is
+/// positive for counterclockwise rings.
+fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2)
+        .map(|edge| {
+            let ((lat1, lon1), (lat2, lon2)) = (edge[0], edge[1]);
+            lon1 * lat2 - lon2 * lat1
+        })
+        .sum()
+}
+
+fn orient(ring: &mut Ring, counterclockwise: bool) {
+    if (signed_area(ring) > 0.0) != counterclockwise {
+        ring.reverse();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{Member, TestArchive};
+
+    /// Corners of a counterclockwise square as (latitude, longitude).
+    const SQUARE: [(f64, f64); 4] = [(0., 0.), (0., 2.), (2., 2.), (2., 0.)];
+
+    fn segment(nodes: &[usize]) -> Vec<RingNode> {
+        nodes.iter().map(|&idx| (idx, SQUARE[idx])).collect()
+    }
+
+    fn square(min: f64, max: f64) -> Ring {
+        vec![(min, min), (min, max), (max, max), (max, min), (min, min)]
+    }
+
+    #[test]
+    fn test_join_rings() {
+        // unordered, and the third segment is reversed
+        let segments = vec![
+            segment(&[2, 3]),
+            segment(&[0, 1]),
+            segment(&[2, 1]),
+            segment(&[3, 0]),
+        ];
+        let rings = join_rings(segments).unwrap();
+        let expected: Ring = [2, 3, 0, 1, 2].iter().map(|&idx| SQUARE[idx]).collect();
+        assert_eq!(rings, vec![expected]);
+    }
+
+    #[test]
+    fn test_join_rings_with_closed_and_degenerated_segments() {
+        let segments = vec![
+            segment(&[1]),
+            segment(&[0, 1, 2, 3, 0]),
+            segment(&[0, 1, 0]),
+        ];
+        let rings = join_rings(segments).unwrap();
+        let expected: Ring = [0, 1, 2, 3, 0].iter().map(|&idx| SQUARE[idx]).collect();
+        assert_eq!(rings, vec![expected]);
+    }
+
+    #[test]
+    fn test_join_open_rings() {
+        let segments = vec![segment(&[0, 1]), segment(&[1, 2])];
+        assert_eq!(
+            join_rings(segments),
+            Err(GeometryError::OpenRing {
+                first_node_idx: 0,
+                last_node_idx: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nest_rings() {
+        let outer = square(0., 10.);
+        let hole = square(2., 8.);
+        let island = square(4., 6.);
+        let separate = square(20., 22.);
+        let mut clockwise_outer = outer.clone();
+        clockwise_outer.reverse();
+
+        let polygons = nest_rings(vec![
+            island.clone(),
+            hole.clone(),
+            separate.clone(),
+            clockwise_outer,
+        ]);
+        let mut clockwise_hole = hole;
+        clockwise_hole.reverse();
+        assert_eq!(
+            polygons,
+            vec![
+                Polygon {
+                    outer: island,
+                    inners: vec![],
+                },
+                Polygon {
+                    outer: separate,
+                    inners: vec![],
+                },
+                Polygon {
+                    outer,
+                    inners: vec![clockwise_hole],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_orient() {
+        let counterclockwise = square(0., 1.);
+        assert!(signed_area(&counterclockwise) > 0.);
+        let mut ring = counterclockwise.clone();
+        orient(&mut ring, true);
+        assert_eq!(ring, counterclockwise);
+        orient(&mut ring, false);
+        assert!(signed_area(&ring) < 0.);
+        assert_eq!(ring[0], counterclockwise[4]);
+        assert_eq!(ring[1], counterclockwise[3]);
+    }
+
+    #[test]
+    fn test_way_polygon() {
+        let archive = TestArchive::default()
+            .node((0, 0), &[])
+            .node((0, 2), &[])
+            .node((2, 2), &[])
+            .way(&[0, 2, 1, 0], &[]) // clockwise
+            .way(&[0, 1, 2], &[])
+            .way(&[0, 1, 3, 0], &[])
+            .build();
+        let polygon = way_polygon(archive.way(0).unwrap()).unwrap();
+        assert_eq!(polygon.outer, vec![(0., 0.), (0., 2.), (2., 2.), (0., 0.)]);
+        assert!(polygon.inners.is_empty());
+        assert_eq!(
+            way_polygon(archive.way(1).unwrap()),
+            Err(GeometryError::NotAnArea)
+        );
+        assert_eq!(
+            way_polygon(archive.way(2).unwrap()),
+            Err(GeometryError::MissingNode { way_idx: 2 })
+        );
+    }
+
+    #[test]
+    fn test_relation_polygons() {
+        let multipolygon = [("type", "multipolygon")];
+        let archive = TestArchive::default()
+            .node((0, 0), &[])
+            .node((0, 4), &[])
+            .node((4, 4), &[])
+            .node((4, 0), &[])
+            .node((1, 1), &[])
+            .node((3, 1), &[])
+            .node((3, 3), &[])
+            .way(&[0, 1, 2], &[])
+            .way(&[0, 3, 2], &[])
+            .way(&[4, 6, 5, 4], &[]) // counterclockwise
+            .relation(
+                &[
+                    Member::Way(2, "inner"),
+                    Member::Way(0, "outer"),
+                    Member::Way(1, ""),
+                    Member::Way(1, "outer"),
+                    Member::Node(0, "label"),
+                ],
+                &multipolygon,
+            )
+            .relation(
+                &[Member::Way(0, "outer"), Member::Way(3, "outer")],
+                &multipolygon,
+            )
+            .relation(&[Member::Way(0, "outer")], &multipolygon)
+            .relation(&[Member::Way(2, "outer")], &[("type", "route")])
+            .build();
+
+        let polygons = relation_polygons(archive.relation(0).unwrap()).unwrap();
+        assert_eq!(
+            polygons,
+            vec![Polygon {
+                outer: vec![(0., 0.), (0., 4.), (4., 4.), (4., 0.), (0., 0.)],
+                inners: vec![vec![(1., 1.), (3., 1.), (3., 3.), (1., 1.)]],
+            }]
+        );
+        assert_eq!(
+            relation_polygons(archive.relation(1).unwrap()),
+            Err(GeometryError::MissingWay { relation_idx: 1 })
+        );
+        assert_eq!(
+            relation_polygons(archive.relation(2).unwrap()),
+            Err(GeometryError::OpenRing {
+                first_node_idx: 0,
+                last_node_idx: 2,
+            })
+        );
+        assert_eq!(
+            relation_polygons(archive.relation(3).unwrap()),
+            Err(GeometryError::NotAnArea)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Lookups of elements by their OSM ids in the optional `id_index`
+//! sub-archive (compiled with `osmflatc --id-index`).
+//!
+//! All methods return `None` if the id is not found or the archive does not
+//! contain an id index.
+
+use crate::{ElementIndex, IdBlock, IdOffset, Osm};
+
+/// Number of lower bits of an id stored in `IdOffset`.
+const ID_BLOCK_BITS: u32 = 24;
+
+impl Osm {
+    /// Returns the index in the `nodes` vector of the node with OSM id `id`.
+    #[inline]
+    pub fn node_index_by_id(&self, id: u64) -> Option<usize> {
+        let id_index = self.id_index()?;
+        index_by_id(
+            id_index.node_blocks(),
+            id_index.node_ids(),
+            id_index.node_order(),
+            id,
+        )
+    }
+
+    /// Returns the index in the `ways` vector of the way with OSM id `id`.
+    #[inline]
+    pub fn way_index_by_id(&self, id: u64) -> Option<usize> {
+        let id_index = self.id_index()?;
+        index_by_id(
+            id_index.way_blocks(),
+            id_index.way_ids(),
+            id_index.way_order(),
+            id,
+        )
+    }
+
+    /// Returns the index in the `relations` vector of the relation with OSM
+    /// id `id`.
+    #[inline]
+    pub fn relation_index_by_id(&self, id: u64) -> Option<usize> {
+        let id_index = self.id_index()?;
+        index_by_id(
+            id_index.relation_blocks(),
+            id_index.relation_ids(),
+            None,
+            id,
+        )
+    }
+}
+
+/// Looks up the position of `id` in the sorted ids, which is the index of its
+/// element, unless the elements are reordered by `order`.
+fn index_by_id(
+    blocks: &[IdBlock],
+    ids: &[IdOffset],
+    order: Option<&[ElementIndex]>,
+    id: u64,
+) -> Option<usize> {
+    let block_idx = (id >> ID_BLOCK_BITS) as usize;
+    if block_idx >= blocks.len() {
+        return None;
+    }
+    let range = blocks[block_idx].ids();
+    let start = range.start as usize;
+    let offset = (id & ((1 << ID_BLOCK_BITS) - 1)) as u32;
+    ids[start..range.end as usize]
+        .binary_search_by_key(&offset, |x| x.value())
+        .ok()
+        .map(|pos| match order {
+            Some(order) => order[start + pos].value() as usize,
+            None => start + pos,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const IDS: [u64; 4] = [1, 2, (1 << 24) + 5, (2 << 24) + 7];
+
+    fn build(ids: &[u64]) -> (flatdata::Vector<IdBlock>, flatdata::Vector<IdOffset>) {
+        let mut blocks = flatdata::Vector::<IdBlock>::new();
+        let mut offsets = flatdata::Vector::<IdOffset>::new();
+        let mut count = 0;
+        for block_idx in 0..=(ids.last().unwrap() >> ID_BLOCK_BITS) {
+            blocks.grow().set_id_first_idx(count);
+            for id in ids.iter().filter(|&id| id >> ID_BLOCK_BITS == block_idx) {
+                offsets
+                    .grow()
+                    .set_value((id & ((1 << ID_BLOCK_BITS) - 1)) as u32);
+                count += 1;
+            }
+        }
+        blocks.grow().set_id_first_idx(count); // sentinel
+        (blocks, offsets)
+    }
+
+    #[test]
+    fn test_index_by_id() {
+        let (blocks, ids) = build(&IDS);
+        let lookup = |id| index_by_id(blocks.as_view(), ids.as_view(), None, id);
+        for (idx, &id) in IDS.iter().enumerate() {
+            assert_eq!(lookup(id), Some(idx));
+        }
+        for id in [
+            0,
+            3,
+            1 << 24,
+            (1 << 24) + 6,
+            (2 << 24) + 8,
+            3 << 24,
+            1 << 40,
+        ] {
+            assert_eq!(lookup(id), None);
+        }
+    }
+
+    #[test]
+    fn test_index_by_id_with_order() {
+        let (blocks, ids) = build(&IDS);
+        let mut order = flatdata::Vector::<ElementIndex>::new();
+        for idx in [3, 1, 0, 2] {
+            order.grow().set_value(idx);
+        }
+        let lookup = |id| index_by_id(blocks.as_view(), ids.as_view(), Some(order.as_view()), id);
+        assert_eq!(lookup(IDS[0]), Some(3));
+        assert_eq!(lookup(IDS[2]), Some(0));
+        assert_eq!(lookup(IDS[3]), Some(2));
+        assert_eq!(lookup(3), None);
+    }
+}
@@ -0,0 +1,33 @@
+//! Access to the metadata of elements stored in the optional `info`
+//! sub-archive (compiled with `osmflatc --info`).
+//!
+//! All functions return `None` if the archive does not contain metadata.
+
+use crate::{Info, Osm};
+
+/// Returns the metadata of the node at index `idx` in the `nodes` vector.
+#[inline]
+pub fn node_info(archive: &Osm, idx: usize) -> Option<&Info> {
+    archive.info()?.nodes().get(idx)
+}
+
+/// Returns the metadata of the way at index `idx` in the `ways` vector.
+#[inline]
+pub fn way_info(archive: &Osm, idx: usize) -> Option<&Info> {
+    archive.info()?.ways().get(idx)
+}
+
+/// Returns the metadata of the relation at index `idx` in the `relations`
+/// vector.
+#[inline]
+pub fn relation_info(archive: &Osm, idx: usize) -> Option<&Info> {
+    archive.info()?.relations().get(idx)
+}
+
+/// Returns the name of the user who last modified an element as raw bytes.
+#[inline]
+pub fn user<'a>(archive: &'a Osm, info: &Info) -> Option<&'a [u8]> {
+    let strings = archive.stringtable();
+    info.user_idx()
+        .map(|idx| strings.substring_raw(idx as usize))
+}
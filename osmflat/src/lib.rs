@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 #![allow(clippy::all)] // generated code is not clippy friendly
+#![allow(unknown_lints, mismatched_lifetime_syntaxes)] // neither are its elided lifetimes
 
 //! Flat OpenStreetMap (OSM) data format providing an efficient *random* data
 //! access through [memory mapped files].
@@ -38,9 +39,22 @@
 // generated osm module
 include!("osmflat_generated.rs");
 
+mod elements;
+mod geometry;
+mod ids;
+mod info;
+mod reverse;
+mod spatial;
 mod tags;
+#[cfg(test)]
+mod test;
 
+pub use crate::elements::*;
+pub use crate::geometry::*;
+pub use crate::info::*;
 pub use crate::osm::*;
+pub use crate::reverse::*;
+pub use crate::spatial::*;
 pub use crate::tags::*;
 
 // re-export what is needed from flatdata to use osmflat
@@ -11,38 +11,38 @@ pub const INVALID_IDX: u64 = 1_099_511_627_775;
 #[repr(transparent)]
 #[derive(Clone)]
 pub struct Header {
-    data: [u8; 51],
+    data: [u8; 56],
 }
 
 impl Header {
     /// Unsafe since the struct might not be self-contained
     pub unsafe fn new_unchecked( ) -> Self {
-        Self{data : [0; 51]}
+        Self{data : [0; 56]}
     }
 }
 
 impl flatdata::Struct for Header {
     unsafe fn create_unchecked( ) -> Self {
-        Self{data : [0; 51]}
+        Self{data : [0; 56]}
     }
 
-    const SIZE_IN_BYTES: usize = 51;
+    const SIZE_IN_BYTES: usize = 56;
     const IS_OVERLAPPING_WITH_NEXT : bool = false;
 }
 
 impl Header {
     pub fn new( ) -> Self {
-        Self{data : [0; 51]}
+        Self{data : [0; 56]}
     }
 
     /// Create reference from byte array of matching size
-    pub fn from_bytes(data: &[u8; 51]) -> &Self {
+    pub fn from_bytes(data: &[u8; 56]) -> &Self {
         // Safety: This is safe since Header is repr(transparent)
         unsafe{ std::mem::transmute( data ) }
     }
 
     /// Create reference from byte array of matching size
-    pub fn from_bytes_mut(data: &mut [u8; 51]) -> &mut Self {
+    pub fn from_bytes_mut(data: &mut [u8; 56]) -> &mut Self {
         // Safety: This is safe since Header is repr(transparent)
         unsafe{ std::mem::transmute( data ) }
     }
@@ -50,11 +50,11 @@ impl Header {
     /// Create reference from byte array
     pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
         // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
-        if data.len() < 51 {
-            assert_eq!(data.len(), 51);
+        if data.len() < 56 {
+            assert_eq!(data.len(), 56);
             return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
         }
-        let ptr = data.as_ptr() as *const [u8; 51];
+        let ptr = data.as_ptr() as *const [u8; 56];
         // Safety: We checked length before
         Ok(Self::from_bytes(unsafe { &*ptr }))
     }
@@ -62,16 +62,16 @@ impl Header {
     /// Create reference from byte array
     pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
         // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
-        if data.len() < 51 {
-            assert_eq!(data.len(), 51);
+        if data.len() < 56 {
+            assert_eq!(data.len(), 56);
             return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
         }
-        let ptr = data.as_ptr() as *mut [u8; 51];
+        let ptr = data.as_ptr() as *mut [u8; 56];
         // Safety: We checked length before
         Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
     }
 
-    pub fn as_bytes(&self) -> &[u8; 51] {
+    pub fn as_bytes(&self) -> &[u8; 56] {
         &self.data
     }
 }
@@ -161,6 +161,19 @@ impl Header {
         unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
+    /// Policy of tags dropped at compile time as `keep=PATTERNS;drop=PATTERNS` (reference to
+/// `stringtable`), or `INVALID_IDX` if all tags were compiled.
+///
+/// Patterns are comma separated keys, optionally ending with `*` to match by prefix. If
+/// patterns to keep are given, only tags with matching keys were compiled. Tags with keys
+/// matching patterns to drop were not compiled.
+    #[inline]
+    pub fn tag_policy_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 408, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
+    }
+
 }
 
 impl std::fmt::Debug for Header {
@@ -176,6 +189,7 @@ impl std::fmt::Debug for Header {
             .field("replication_timestamp", &self.replication_timestamp())
             .field("replication_sequence_number", &self.replication_sequence_number())
             .field("replication_base_url_idx", &self.replication_base_url_idx())
+            .field("tag_policy_idx", &self.tag_policy_idx())
             .finish()
     }
 }
@@ -183,7 +197,7 @@ impl std::fmt::Debug for Header {
 impl std::cmp::PartialEq for Header {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
-        self.coord_scale() == other.coord_scale() &&        self.bbox_left() == other.bbox_left() &&        self.bbox_right() == other.bbox_right() &&        self.bbox_top() == other.bbox_top() &&        self.bbox_bottom() == other.bbox_bottom() &&        self.writingprogram_idx() == other.writingprogram_idx() &&        self.source_idx() == other.source_idx() &&        self.replication_timestamp() == other.replication_timestamp() &&        self.replication_sequence_number() == other.replication_sequence_number() &&        self.replication_base_url_idx() == other.replication_base_url_idx()     }
+        self.coord_scale() == other.coord_scale() &&        self.bbox_left() == other.bbox_left() &&        self.bbox_right() == other.bbox_right() &&        self.bbox_top() == other.bbox_top() &&        self.bbox_bottom() == other.bbox_bottom() &&        self.writingprogram_idx() == other.writingprogram_idx() &&        self.source_idx() == other.source_idx() &&        self.replication_timestamp() == other.replication_timestamp() &&        self.replication_sequence_number() == other.replication_sequence_number() &&        self.replication_base_url_idx() == other.replication_base_url_idx() &&        self.tag_policy_idx() == other.tag_policy_idx()     }
 }
 
 impl Header {
@@ -263,6 +277,18 @@ impl Header {
         flatdata_write_bytes!(u64; value, self.data, 368, 40)
     }
 
+    /// Policy of tags dropped at compile time as `keep=PATTERNS;drop=PATTERNS` (reference to
+/// `stringtable`), or `INVALID_IDX` if all tags were compiled.
+///
+/// Patterns are comma separated keys, optionally ending with `*` to match by prefix. If
+/// patterns to keep are given, only tags with matching keys were compiled. Tags with keys
+/// matching patterns to drop were not compiled.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_tag_policy_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 408, 40)
+    }
+
 
     /// Copies the data from `other` into this struct.
     #[inline]
@@ -277,6 +303,7 @@ impl Header {
         self.set_replication_timestamp(other.replication_timestamp());
         self.set_replication_sequence_number(other.replication_sequence_number());
         self.set_replication_base_url_idx(other.replication_base_url_idx());
+        self.set_tag_policy_idx(other.tag_policy_idx());
     }
 }
 /// A `(key, value)` attached to a `Node`, `Way`, or `Relation.
@@ -1469,798 +1496,4321 @@ impl Id {
         self.set_value(other.value());
     }
 }
-
-
-
-/// An optional sub-archive storing the original OSM ids of nodes, ways, and relations
-#[derive(Clone)]
-pub struct Ids {
-    _storage: flatdata::StorageHandle,
-    nodes : &'static [super::osm::Id],
-    ways : &'static [super::osm::Id],
-    relations : &'static [super::osm::Id],
+/// Block of OSM ids sharing the same upper bits (`id >> 24`).
+#[repr(transparent)]
+pub struct IdBlock {
+    data: [u8; 5],
 }
 
-impl Ids {
-    fn signature_name(archive_name: &str) -> String {
-        format!("{}.archive", archive_name)
+impl IdBlock {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
     }
+}
 
-    /// List of OSM ids of all nodes in the parent archive
-/// nodes[i] has its id stored in ids.nodes[i]
-    #[inline]
-    pub fn nodes(&self) -> &[super::osm::Id] {
-        self.nodes
+impl flatdata::Struct for IdBlock {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
     }
 
-    /// List of OSM ids of all ways in the parent archive
-/// ways[i] has its id stored in ids.ways[i]
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for IdBlock {}
+
+impl IdBlock {
+    /// First element of the range [`ids`].
+    ///
+    /// [`ids`]: #method.ids
     #[inline]
-    pub fn ways(&self) -> &[super::osm::Id] {
-        self.ways
+    pub fn id_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
-    /// List of OSM ids of all relations in the parent archive
-/// relations[i] has its id stored in ids.relations[i]
+    /// Range of ids in this block.
+///
+/// The values of the range are indexes in the sorted vector of id offsets.
     #[inline]
-    pub fn relations(&self) -> &[super::osm::Id] {
-        self.relations
+    pub fn ids(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
     }
 
 }
 
-impl ::std::fmt::Debug for Ids {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        f.debug_struct("Ids")
-            .field("nodes", &self.nodes())
-            .field("ways", &self.ways())
-            .field("relations", &self.relations())
+impl std::fmt::Debug for IdBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IdBlock")
+            .field("id_first_idx", &self.id_first_idx())
             .finish()
     }
 }
 
-impl Ids {
-    pub fn open(storage: flatdata::StorageHandle)
-        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
-    {
-        #[allow(unused_imports)]
-        use flatdata::SliceExt;
-        #[allow(unused_variables)]
-        use flatdata::ResourceStorageError as Error;
-        // extend lifetime since Rust cannot know that we reference a cache here
-        #[allow(unused_variables)]
-        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+impl std::cmp::PartialEq for IdBlock {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id_first_idx() == other.id_first_idx()     }
+}
 
-        storage.read(&Self::signature_name("Ids"), schema::ids::IDS)?;
+impl IdBlock {
+    /// First element of the range [`ids`].
+    ///
+    /// [`ids`]: struct.IdBlockRef.html#method.ids
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_id_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
 
-        let nodes = {
-            use flatdata::check_resource as check;
-            let max_size = None;
-            let resource = extend(storage.read("nodes", schema::ids::resources::NODES));
-            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
-        };
-        let ways = {
-            use flatdata::check_resource as check;
-            let max_size = None;
-            let resource = extend(storage.read("ways", schema::ids::resources::WAYS));
-            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
-        };
-        let relations = {
-            use flatdata::check_resource as check;
-            let max_size = None;
-            let resource = extend(storage.read("relations", schema::ids::resources::RELATIONS));
-            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
-        };
 
-        Ok(Self {
-            _storage: storage,
-            nodes,
-            ways,
-            relations,
-        })
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &IdBlock) {
+        self.set_id_first_idx(other.id_first_idx());
     }
 }
+/// Lower 24 bits of an OSM id, i.e. the offset of the id in its `IdBlock`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct IdOffset {
+    data: [u8; 3],
+}
 
-/// Builder for creating [`Ids`] archives.
-///
-///[`Ids`]: struct.Ids.html
-#[derive(Clone, Debug)]
-pub struct IdsBuilder {
-    storage: flatdata::StorageHandle
+impl IdOffset {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 3]}
+    }
 }
 
-impl IdsBuilder {
-    #[inline]
-    /// Stores [`nodes`] in the archive.
-    ///
-    /// [`nodes`]: struct.Ids.html#method.nodes
-    pub fn set_nodes(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes", schema::ids::resources::NODES, vector.as_bytes())
+impl flatdata::Struct for IdOffset {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 3]}
     }
 
-    /// Opens [`nodes`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`nodes`]: struct.Ids.html#method.nodes
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
-        flatdata::create_external_vector(&*self.storage, "nodes", schema::ids::resources::NODES)
+    const SIZE_IN_BYTES: usize = 3;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl IdOffset {
+    pub fn new( ) -> Self {
+        Self{data : [0; 3]}
     }
 
-    #[inline]
-    /// Stores [`ways`] in the archive.
-    ///
-    /// [`ways`]: struct.Ids.html#method.ways
-    pub fn set_ways(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("ways", schema::ids::resources::WAYS, vector.as_bytes())
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 3]) -> &Self {
+        // Safety: This is safe since IdOffset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
     }
 
-    /// Opens [`ways`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`ways`]: struct.Ids.html#method.ways
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
-        flatdata::create_external_vector(&*self.storage, "ways", schema::ids::resources::WAYS)
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 3]) -> &mut Self {
+        // Safety: This is safe since IdOffset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
     }
 
-    #[inline]
-    /// Stores [`relations`] in the archive.
-    ///
-    /// [`relations`]: struct.Ids.html#method.relations
-    pub fn set_relations(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("relations", schema::ids::resources::RELATIONS, vector.as_bytes())
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 3 {
+            assert_eq!(data.len(), 3);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 3];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
     }
 
-    /// Opens [`relations`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relations`]: struct.Ids.html#method.relations
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
-        flatdata::create_external_vector(&*self.storage, "relations", schema::ids::resources::RELATIONS)
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 3 {
+            assert_eq!(data.len(), 3);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 3];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
     }
 
+    pub fn as_bytes(&self) -> &[u8; 3] {
+        &self.data
+    }
 }
 
-impl IdsBuilder {
-    pub fn new(
-        storage: flatdata::StorageHandle,
-    ) -> Result<Self, flatdata::ResourceStorageError> {
-        flatdata::create_archive("Ids", schema::ids::IDS, &storage)?;
-        Ok(Self { storage })
+impl Default for IdOffset {
+    fn default( ) -> Self {
+        Self::new( )
     }
 }
 
+unsafe impl flatdata::NoOverlap for IdOffset {}
 
+impl IdOffset {
+    #[inline]
+    pub fn value(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 0, 24);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
 
-/// Enum for read-only heterogeneous access to elements in a
-/// bucket of the [`relation_members`] resource.
-///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-#[derive(Clone, PartialEq)]
-pub enum RelationMembersRef<'a> {
-    #[allow(missing_docs)]
-    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
-    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
-    RelationMember(&'a super::osm::RelationMember),}
+}
 
-impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        match *self {
-            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
-        }
+impl std::fmt::Debug for IdOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IdOffset")
+            .field("value", &self.value())
+            .finish()
     }
 }
 
-impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
+impl std::cmp::PartialEq for IdOffset {
     #[inline]
-    fn size_in_bytes(&self) -> usize {
-        match *self {
-            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
-        }
-    }
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
 }
 
-/// Builder of buckets in the [`relation_members`] resource.
-///
-/// Refers to a single bucket in the [`relation_members`] multivector and
-/// provides methods for adding heterogeneous data to the bucket.
-///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-pub struct RelationMembersBuilder<'a> {
-    data: &'a mut Vec<u8>
-}
+impl IdOffset {
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 0, 24)
+    }
 
-impl<'a> RelationMembersBuilder<'a> {
-    /// Adds data of the type [`NodeMember`] to the bucket.
-    ///
-    /// [`NodeMember`]: struct.NodeMember.html
-    #[inline]
-    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 0;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
-    }
-    /// Adds data of the type [`WayMember`] to the bucket.
-    ///
-    /// [`WayMember`]: struct.WayMember.html
-    #[inline]
-    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 1;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
-    }
-    /// Adds data of the type [`RelationMember`] to the bucket.
-    ///
-    /// [`RelationMember`]: struct.RelationMember.html
+
+    /// Copies the data from `other` into this struct.
     #[inline]
-    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 2;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+    pub fn fill_from(&mut self, other: &IdOffset) {
+        self.set_value(other.value());
     }
 }
-
-/// Variadic struct attached to the [`relation_members`] archive resource.
-///
-/// It unifies the following data types:
-//
-/// * [`NodeMember`]
-/// * [`WayMember`]
-/// * [`RelationMember`]
-///
-/// ## Access pattern
-///
-/// This structure is used as a template parameter in [`relation_members`] multivector/
-/// multiarray view. It does not contain any data, instead it references
-///
-/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
-/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
+/// Metadata of the last modification of a node, way, or relation.
 ///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-/// [`RelationMembersRef`]: enum.RelationMembersRef.html
-/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
-/// [`NodeMember`]: struct.NodeMember.html
-/// [`WayMember`]: struct.WayMember.html
-/// [`RelationMember`]: struct.RelationMember.html
+/// See <https://wiki.openstreetmap.org/wiki/Elements#Common_attributes>.
+#[repr(transparent)]
 #[derive(Clone)]
-pub struct RelationMembers {}
+pub struct Info {
+    data: [u8; 29],
+}
 
-impl flatdata::VariadicIndex for RelationMembers {
-    type Index = super::_builtin::multivector::IndexType40;
+impl Info {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 29]}
+    }
 }
 
-impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
-    type Item = RelationMembersRef<'a>;
+impl flatdata::Struct for Info {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 29]}
+    }
 
-    #[inline]
-    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
-    {
-        match index {
-                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
-                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
-                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
-            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+    const SIZE_IN_BYTES: usize = 29;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Info {
+    pub fn new( ) -> Self {
+        Self{data : [0; 29]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 29]) -> &Self {
+        // Safety: This is safe since Info is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 29]) -> &mut Self {
+        // Safety: This is safe since Info is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 29 {
+            assert_eq!(data.len(), 29);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
         }
+        let ptr = data.as_ptr() as *const [u8; 29];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
     }
 
-    type ItemMut = RelationMembersBuilder<'a>;
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 29 {
+            assert_eq!(data.len(), 29);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 29];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
 
-    #[inline]
-    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
-    {
-        Self::ItemMut { data }
+    pub fn as_bytes(&self) -> &[u8; 29] {
+        &self.data
     }
 }
 
-/// OSM data archive
-///
-/// Relations and relation members are indexed with the same index, i.e.
-/// a relation at index `i` in the vector `relations` has the members
-/// at index `i` in the multivector `relation_members`.
-///
-/// All 1:n relationships are modeled in-place by using an additional index. This is a
-/// common pattern in flatdata. For example, a node might have multiple tags attached
-/// to it. To model this, a node in `nodes` references the first tag attached to it
-/// by storing an index in the `tags_index` vector. The next node in `nodes` again
-/// references its first tag, which is the last tag (exclusive) of the previous node.
-///
-/// ```text
-/// nodes:      [ ..., n_1, n_2, ... ]
-///                     |    |
-///                     |    +-------+
-///                     v            v
-/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
-/// ```
-#[derive(Clone)]
-pub struct Osm {
-    _storage: flatdata::StorageHandle,
-    header : &'static super::osm::Header,
-    nodes : &'static [super::osm::Node],
-    ways : &'static [super::osm::Way],
-    relations : &'static [super::osm::Relation],
-    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
-    tags : &'static [super::osm::Tag],
-    tags_index : &'static [super::osm::TagIndex],
-    nodes_index : &'static [super::osm::NodeIndex],
-    stringtable : flatdata::RawData<'static>,
-    ids : Option<super::osm::Ids
->,
+impl Default for Info {
+    fn default( ) -> Self {
+        Self::new( )
+    }
 }
 
-impl Osm {
-    fn signature_name(archive_name: &str) -> String {
-        format!("{}.archive", archive_name)
+unsafe impl flatdata::NoOverlap for Info {}
+
+impl Info {
+    /// Edit version of the element.
+    #[inline]
+    pub fn version(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// Header which contains the metadata attached to the archive.
+    /// Time of the last modification, expressed in seconds since the epoch.
     #[inline]
-    pub fn header(&self) -> &super::osm::Header {
-        self.header
+    pub fn timestamp(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 32, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// List of nodes.
-///
-/// A node references a range of tags in the `tags_index` vector.
+    /// Changeset in which the element was last modified.
     #[inline]
-    pub fn nodes(&self) -> &[super::osm::Node] {
-        self.nodes
+    pub fn changeset(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 96, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// List of ways.
-///
-/// A way references
-///
-/// * a range of tags in the `tags_index` vector, and
-/// * a range of nodes in the `nodes_index` vector.
+    /// Id of the user who last modified the element.
     #[inline]
-    pub fn ways(&self) -> &[super::osm::Way] {
-        self.ways
+    pub fn uid(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 160, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// List of relations.
-///
-/// A relation references a range of tags in `tags_index` vectors.
-/// Members are attached to a relation implicitly: members that belong to a
-/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    /// Name of the user who last modified the element (index in `stringtable` of the
+/// parent archive).
     #[inline]
-    pub fn relations(&self) -> &[super::osm::Relation] {
-        self.relations
+    pub fn user_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 192, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
     }
 
-    /// Members attached to relations.
-///
-/// An index in this multivector corresponds to an index in the `relations` vector.
-///
-/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
-/// Each type references its role in the `stringtable` raw data. Additionally,
-///
-/// * a node member references a node in the `nodes` vector,
-/// * a way member references a way in the `ways` vector,
-/// * a relation member references a relation in the `relations` vector.
+}
+
+impl std::fmt::Debug for Info {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Info")
+            .field("version", &self.version())
+            .field("timestamp", &self.timestamp())
+            .field("changeset", &self.changeset())
+            .field("uid", &self.uid())
+            .field("user_idx", &self.user_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Info {
     #[inline]
-    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
-        &self.relation_members
+    fn eq(&self, other: &Self) -> bool {
+        self.version() == other.version() &&        self.timestamp() == other.timestamp() &&        self.changeset() == other.changeset() &&        self.uid() == other.uid() &&        self.user_idx() == other.user_idx()     }
+}
+
+impl Info {
+    /// Edit version of the element.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_version(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
     }
 
-    /// List of tags.
-///
-/// A tag references its key and value in the `stringtable` raw data.
+    /// Time of the last modification, expressed in seconds since the epoch.
     #[inline]
-    pub fn tags(&self) -> &[super::osm::Tag] {
-        self.tags
+    #[allow(missing_docs)]
+    pub fn set_timestamp(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 32, 64)
     }
 
-    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
-/// and tags.
+    /// Changeset in which the element was last modified.
     #[inline]
-    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
-        self.tags_index
+    #[allow(missing_docs)]
+    pub fn set_changeset(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 96, 64)
     }
 
-    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    /// Id of the user who last modified the element.
     #[inline]
-    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
-        self.nodes_index
+    #[allow(missing_docs)]
+    pub fn set_uid(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 160, 32)
     }
 
-    /// List of strings separated by `\0`.
+    /// Name of the user who last modified the element (index in `stringtable` of the
+/// parent archive).
     #[inline]
-    pub fn stringtable(&self) -> flatdata::RawData {
-        self.stringtable
+    #[allow(missing_docs)]
+    pub fn set_user_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 192, 40)
     }
 
+
+    /// Copies the data from `other` into this struct.
     #[inline]
-    pub fn ids(&self) -> Option<&super::osm::Ids> {
-        self.ids.as_ref()
+    pub fn fill_from(&mut self, other: &Info) {
+        self.set_version(other.version());
+        self.set_timestamp(other.timestamp());
+        self.set_changeset(other.changeset());
+        self.set_uid(other.uid());
+        self.set_user_idx(other.user_idx());
     }
+}
+/// Entry of a packed R-tree.
+///
+/// The entries of a tree are stored level by level starting with the root. Children of an
+/// inner entry are consecutive entries on the next level. Leaf entries are at the end and
+/// reference an element in the parent archive.
+#[repr(transparent)]
+pub struct RTreeEntry {
+    data: [u8; 26],
+}
 
+impl RTreeEntry {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 26]}
+    }
 }
 
-impl ::std::fmt::Debug for Osm {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        f.debug_struct("Osm")
-            .field("header", &self.header())
-            .field("nodes", &self.nodes())
-            .field("ways", &self.ways())
-            .field("relations", &self.relations())
-            .field("relation_members", &self.relation_members())
-            .field("tags", &self.tags())
-            .field("tags_index", &self.tags_index())
-            .field("nodes_index", &self.nodes_index())
-            .field("stringtable", &self.stringtable())
-            .field("ids", &self.ids())
-            .finish()
+impl flatdata::Struct for RTreeEntry {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 26]}
     }
+
+    const SIZE_IN_BYTES: usize = 26;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
 }
 
-impl Osm {
-    pub fn open(storage: flatdata::StorageHandle)
-        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
-    {
-        #[allow(unused_imports)]
-        use flatdata::SliceExt;
-        #[allow(unused_variables)]
-        use flatdata::ResourceStorageError as Error;
-        // extend lifetime since Rust cannot know that we reference a cache here
-        #[allow(unused_variables)]
-        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
-
-        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
-
-        let header = {
-            use flatdata::check_resource as check;
-            let max_size = None;
-            let resource = extend(storage.read("header", schema::osm::resources::HEADER));
-            check("header", |_| 0, max_size, resource.and_then(|x| super::osm::Header::from_bytes_slice(x)))?
-        };
-        let nodes = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
-            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Node]>::from_bytes(x)))?
-        };
-        let ways = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
-            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Way]>::from_bytes(x)))?
-        };
-        let relations = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
-            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Relation]>::from_bytes(x)))?
-        };
-        let relation_members = {
-            use flatdata::check_resource as check;
-            let max_size = None;
-            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
-            let index = extend(storage.read("relation_members_index", &index_schema));
-            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
-            let result = match (index, data) {
-                (Ok(index), Ok(data)) => {
-                    Ok(flatdata::MultiArrayView::new(
-                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
-                        data
-                    ))
-                }
-                // is resource completely missing?
-                (Err(Error::Missing), Err(Error::Missing))  => Err(Error::Missing),
-                // is resource partially missing / broken -> extract best error to propagate
-                (Ok(_), Err(Error::Missing)) | (Err(Error::Missing), Ok(_)) => Err(Error::MissingData),
-                (Err(Error::Missing), Err(x)) | (Err(x), Err(Error::Missing)) => {return Err(x);}
-                (_, Err(x)) | (Err(x), _) => {return Err(x);}
-            };
-            check("relation_members", |r| r.len(), max_size, result)?
-        };
-        let tags = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
-            check("tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Tag]>::from_bytes(x)))?
-        };
-        let tags_index = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
-            check("tags_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
-        };
-        let nodes_index = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
-            check("nodes_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?
-        };
-        let stringtable = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
-            check("stringtable", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
-        };
-        let ids = {
-            use flatdata::check_optional_resource as check;
-            let max_size = None;
-            check("ids", |_| 0, max_size, super::osm::Ids::open(storage.subdir("ids")))?
-        };
+impl flatdata::Overlap for RTreeEntry {}
 
-        Ok(Self {
-            _storage: storage,
-            header,
-            nodes,
-            ways,
-            relations,
-            relation_members,
-            tags,
-            tags_index,
-            nodes_index,
-            stringtable,
-            ids,
-        })
+impl RTreeEntry {
+    /// Bounding box (min longitude scaled with `header.coord_scale`)
+    #[inline]
+    pub fn bbox_left(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
-}
 
-/// Builder for creating [`Osm`] archives.
-///
-///[`Osm`]: struct.Osm.html
-#[derive(Clone, Debug)]
-pub struct OsmBuilder {
-    storage: flatdata::StorageHandle
-}
+    /// Bounding box (max longitude scaled with `header.coord_scale`)
+    #[inline]
+    pub fn bbox_right(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 32, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
 
-impl OsmBuilder {
+    /// Bounding box (max latitude scaled with `header.coord_scale`)
     #[inline]
-    /// Stores [`header`] in the archive.
-    ///
-    /// [`header`]: struct.Osm.html#method.header
-    /// Stores [`header`] in the archive.
-    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
-        let data = resource.as_bytes();
-        self.storage.write("header", schema::osm::resources::HEADER, data)
+    pub fn bbox_top(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 64, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
+    /// Bounding box (min latitude scaled with `header.coord_scale`)
     #[inline]
-    /// Stores [`nodes`] in the archive.
-    ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
-    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+    pub fn bbox_bottom(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 96, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// Opens [`nodes`] in the archive for buffered writing.
+    /// First element of the range [`children`].
     ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// [`children`]: #method.children
     #[inline]
-    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
-        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    pub fn child_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 128, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
+    /// Range of children of this entry.
+///
+/// The values of the range are indexes in the same vector. The range is empty for leaves.
     #[inline]
-    /// Stores [`ways`] in the archive.
-    ///
-    /// [`ways`]: struct.Osm.html#method.ways
-    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+    pub fn children(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 128, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 128 + 26 * 8, 40);
+        start..end
     }
 
-    /// Opens [`ways`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`ways`]: struct.Osm.html#method.ways
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// Index of the element in the parent archive (only set for leaves).
     #[inline]
-    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
-        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+    pub fn element_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 168, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
+    }
+
+}
+
+impl std::fmt::Debug for RTreeEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RTreeEntry")
+            .field("bbox_left", &self.bbox_left())
+            .field("bbox_right", &self.bbox_right())
+            .field("bbox_top", &self.bbox_top())
+            .field("bbox_bottom", &self.bbox_bottom())
+            .field("child_first_idx", &self.child_first_idx())
+            .field("element_idx", &self.element_idx())
+            .finish()
     }
+}
 
+impl std::cmp::PartialEq for RTreeEntry {
     #[inline]
-    /// Stores [`relations`] in the archive.
-    ///
-    /// [`relations`]: struct.Osm.html#method.relations
-    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+    fn eq(&self, other: &Self) -> bool {
+        self.bbox_left() == other.bbox_left() &&        self.bbox_right() == other.bbox_right() &&        self.bbox_top() == other.bbox_top() &&        self.bbox_bottom() == other.bbox_bottom() &&        self.child_first_idx() == other.child_first_idx() &&        self.element_idx() == other.element_idx()     }
+}
+
+impl RTreeEntry {
+    /// Bounding box (min longitude scaled with `header.coord_scale`)
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_bbox_left(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
     }
 
-    /// Opens [`relations`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relations`]: struct.Osm.html#method.relations
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// Bounding box (max longitude scaled with `header.coord_scale`)
     #[inline]
-    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
-        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    #[allow(missing_docs)]
+    pub fn set_bbox_right(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 32, 32)
     }
 
-    /// Opens [`relation_members`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the multivector until the [`MultiVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relation_members`]: struct.Osm.html#method.relation_members
-    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
+    /// Bounding box (max latitude scaled with `header.coord_scale`)
     #[inline]
-    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
-        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+    #[allow(missing_docs)]
+    pub fn set_bbox_top(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 64, 32)
     }
 
+    /// Bounding box (min latitude scaled with `header.coord_scale`)
     #[inline]
-    /// Stores [`tags`] in the archive.
-    ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+    #[allow(missing_docs)]
+    pub fn set_bbox_bottom(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 96, 32)
     }
 
-    /// Opens [`tags`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
+    /// First element of the range [`children`].
     ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// [`children`]: struct.RTreeEntryRef.html#method.children
     #[inline]
-    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
-        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
+    #[allow(missing_docs)]
+    pub fn set_child_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 128, 40)
     }
 
+    /// Index of the element in the parent archive (only set for leaves).
     #[inline]
-    /// Stores [`tags_index`] in the archive.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    #[allow(missing_docs)]
+    pub fn set_element_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 168, 40)
     }
 
-    /// Opens [`tags_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+
+    /// Copies the data from `other` into this struct.
     #[inline]
-    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
-        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    pub fn fill_from(&mut self, other: &RTreeEntry) {
+        self.set_bbox_left(other.bbox_left());
+        self.set_bbox_right(other.bbox_right());
+        self.set_bbox_top(other.bbox_top());
+        self.set_bbox_bottom(other.bbox_bottom());
+        self.set_child_first_idx(other.child_first_idx());
+        self.set_element_idx(other.element_idx());
+    }
+}
+/// Index of a way.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct WayIndex {
+    data: [u8; 5],
+}
+
+impl WayIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for WayIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl WayIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since WayIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since WayIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for WayIndex {
+    fn default( ) -> Self {
+        Self::new( )
     }
+}
 
+unsafe impl flatdata::NoOverlap for WayIndex {}
+
+impl WayIndex {
+    /// Index in the `ways` vector of the parent archive.
     #[inline]
-    /// Stores [`nodes_index`] in the archive.
-    ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
-    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
-    /// Opens [`nodes_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+}
+
+impl std::fmt::Debug for WayIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WayIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for WayIndex {
     #[inline]
-    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
-        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl WayIndex {
+    /// Index in the `ways` vector of the parent archive.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
     }
 
-    /// Stores [`stringtable`] in the archive.
-    ///
-    /// [`stringtable`]: struct.Osm.html#method.stringtable
+
+    /// Copies the data from `other` into this struct.
     #[inline]
-    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
-        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    pub fn fill_from(&mut self, other: &WayIndex) {
+        self.set_value(other.value());
+    }
+}
+/// Ways referencing a node.
+#[repr(transparent)]
+pub struct WayRange {
+    data: [u8; 5],
+}
+
+impl WayRange {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
     }
+}
 
-    /// Stores [`ids`] in the archive.
+impl flatdata::Struct for WayRange {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for WayRange {}
+
+impl WayRange {
+    /// First element of the range [`ways`].
     ///
-    /// [`ids`]: struct.Osm.html#method.ids
+    /// [`ways`]: #method.ways
     #[inline]
-    pub fn ids(&self) -> Result<super::osm::IdsBuilder, flatdata::ResourceStorageError> {
-        let storage = self.storage.subdir("ids");
-        super::osm::IdsBuilder::new(storage)
+    pub fn way_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of ways referencing the node.
+///
+/// The values of the range are indexes in the `ways_index` vector.
+    #[inline]
+    pub fn ways(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
     }
 
 }
 
-impl OsmBuilder {
-    pub fn new(
-        storage: flatdata::StorageHandle,
-    ) -> Result<Self, flatdata::ResourceStorageError> {
-        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
-        Ok(Self { storage })
+impl std::fmt::Debug for WayRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WayRange")
+            .field("way_first_idx", &self.way_first_idx())
+            .finish()
     }
 }
 
+impl std::cmp::PartialEq for WayRange {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.way_first_idx() == other.way_first_idx()     }
+}
 
-#[doc(hidden)]
-pub mod schema {
-pub mod ids {
+impl WayRange {
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: struct.WayRangeRef.html#method.ways
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_way_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
 
-pub const IDS: &str = r#"namespace osm {
-struct Id
-{
-    value : u64 : 40;
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &WayRange) {
+        self.set_way_first_idx(other.way_first_idx());
+    }
 }
+/// Relation referencing an element as a member.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct ParentRelation {
+    data: [u8; 10],
 }
 
-namespace osm {
-archive Ids
-{
-    nodes : vector< .osm.Id >;
-    ways : vector< .osm.Id >;
-    relations : vector< .osm.Id >;
+impl ParentRelation {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
 }
+
+impl flatdata::Struct for ParentRelation {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
+
+    const SIZE_IN_BYTES: usize = 10;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
 }
 
-"#;
+impl ParentRelation {
+    pub fn new( ) -> Self {
+        Self{data : [0; 10]}
+    }
 
-pub mod resources {
-pub const NODES: &str = r#"namespace osm {
-struct Id
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 10]) -> &Self {
+        // Safety: This is safe since ParentRelation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 10]) -> &mut Self {
+        // Safety: This is safe since ParentRelation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 10] {
+        &self.data
+    }
+}
+
+impl Default for ParentRelation {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for ParentRelation {}
+
+impl ParentRelation {
+    /// Index of the relation in the `relations` vector of the parent archive.
+    #[inline]
+    pub fn relation_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Role of the element in the relation.
+///
+/// Index in `stringtable` of the parent archive.
+    #[inline]
+    pub fn role_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for ParentRelation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ParentRelation")
+            .field("relation_idx", &self.relation_idx())
+            .field("role_idx", &self.role_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ParentRelation {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.relation_idx() == other.relation_idx() &&        self.role_idx() == other.role_idx()     }
+}
+
+impl ParentRelation {
+    /// Index of the relation in the `relations` vector of the parent archive.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Role of the element in the relation.
+///
+/// Index in `stringtable` of the parent archive.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_role_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ParentRelation) {
+        self.set_relation_idx(other.relation_idx());
+        self.set_role_idx(other.role_idx());
+    }
+}
+/// Relations referencing an element as a member.
+#[repr(transparent)]
+pub struct ParentRange {
+    data: [u8; 5],
+}
+
+impl ParentRange {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for ParentRange {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for ParentRange {}
+
+impl ParentRange {
+    /// First element of the range [`parents`].
+    ///
+    /// [`parents`]: #method.parents
+    #[inline]
+    pub fn parent_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of relations referencing the element.
+///
+/// The values of the range are indexes in the corresponding `*_parents` vector.
+    #[inline]
+    pub fn parents(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for ParentRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ParentRange")
+            .field("parent_first_idx", &self.parent_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ParentRange {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.parent_first_idx() == other.parent_first_idx()     }
+}
+
+impl ParentRange {
+    /// First element of the range [`parents`].
+    ///
+    /// [`parents`]: struct.ParentRangeRef.html#method.parents
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_parent_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ParentRange) {
+        self.set_parent_first_idx(other.parent_first_idx());
+    }
+}
+/// Index of a node, way or relation.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct ElementIndex {
+    data: [u8; 5],
+}
+
+impl ElementIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for ElementIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl ElementIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since ElementIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since ElementIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for ElementIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for ElementIndex {}
+
+impl ElementIndex {
+    /// Index in the `nodes`, `ways` or `relations` vector of the parent archive.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for ElementIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ElementIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ElementIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl ElementIndex {
+    /// Index in the `nodes`, `ways` or `relations` vector of the parent archive.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ElementIndex) {
+        self.set_value(other.value());
+    }
+}
+/// Elements having a tag.
+#[repr(transparent)]
+pub struct TagElementRanges {
+    data: [u8; 15],
+}
+
+impl TagElementRanges {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 15]}
+    }
+}
+
+impl flatdata::Struct for TagElementRanges {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 15]}
+    }
+
+    const SIZE_IN_BYTES: usize = 15;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for TagElementRanges {}
+
+impl TagElementRanges {
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: #method.nodes
+    #[inline]
+    pub fn node_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of nodes having the tag.
+///
+/// The values of the range are indexes in the `nodes` vector.
+    #[inline]
+    pub fn nodes(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 15 * 8, 40);
+        start..end
+    }
+
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: #method.ways
+    #[inline]
+    pub fn way_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of ways having the tag.
+///
+/// The values of the range are indexes in the `ways` vector.
+    #[inline]
+    pub fn ways(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 40 + 15 * 8, 40);
+        start..end
+    }
+
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: #method.relations
+    #[inline]
+    pub fn relation_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of relations having the tag.
+///
+/// The values of the range are indexes in the `relations` vector.
+    #[inline]
+    pub fn relations(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 80 + 15 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for TagElementRanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TagElementRanges")
+            .field("node_first_idx", &self.node_first_idx())
+            .field("way_first_idx", &self.way_first_idx())
+            .field("relation_first_idx", &self.relation_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for TagElementRanges {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.node_first_idx() == other.node_first_idx() &&        self.way_first_idx() == other.way_first_idx() &&        self.relation_first_idx() == other.relation_first_idx()     }
+}
+
+impl TagElementRanges {
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: struct.TagElementRangesRef.html#method.nodes
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_node_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// First element of the range [`ways`].
+    ///
+    /// [`ways`]: struct.TagElementRangesRef.html#method.ways
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_way_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+    /// First element of the range [`relations`].
+    ///
+    /// [`relations`]: struct.TagElementRangesRef.html#method.relations
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 80, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TagElementRanges) {
+        self.set_node_first_idx(other.node_first_idx());
+        self.set_way_first_idx(other.way_first_idx());
+        self.set_relation_first_idx(other.relation_first_idx());
+    }
+}
+
+
+
+/// An optional sub-archive storing the original OSM ids of nodes, ways, and relations
+#[derive(Clone)]
+pub struct Ids {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::Id],
+    ways : &'static [super::osm::Id],
+    relations : &'static [super::osm::Id],
+}
+
+impl Ids {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// List of OSM ids of all nodes in the parent archive
+/// nodes[i] has its id stored in ids.nodes[i]
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Id] {
+        self.nodes
+    }
+
+    /// List of OSM ids of all ways in the parent archive
+/// ways[i] has its id stored in ids.ways[i]
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Id] {
+        self.ways
+    }
+
+    /// List of OSM ids of all relations in the parent archive
+/// relations[i] has its id stored in ids.relations[i]
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Id] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for Ids {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Ids")
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .finish()
+    }
+}
+
+impl Ids {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Ids"), schema::ids::IDS)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::ids::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::ids::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::ids::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Id]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways,
+            relations,
+        })
+    }
+}
+
+/// Builder for creating [`Ids`] archives.
+///
+///[`Ids`]: struct.Ids.html
+#[derive(Clone, Debug)]
+pub struct IdsBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl IdsBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Ids.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::ids::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Ids.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::ids::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.Ids.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::ids::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.Ids.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::ids::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.Ids.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Id]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::ids::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.Ids.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Id>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::ids::resources::RELATIONS)
+    }
+
+}
+
+impl IdsBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Ids", schema::ids::IDS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive storing the metadata of nodes, ways, and relations
+#[derive(Clone)]
+pub struct Infos {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::Info],
+    ways : &'static [super::osm::Info],
+    relations : &'static [super::osm::Info],
+}
+
+impl Infos {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// List of metadata of all nodes in the parent archive
+/// nodes[i] has its metadata stored in info.nodes[i]
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Info] {
+        self.nodes
+    }
+
+    /// List of metadata of all ways in the parent archive
+/// ways[i] has its metadata stored in info.ways[i]
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Info] {
+        self.ways
+    }
+
+    /// List of metadata of all relations in the parent archive
+/// relations[i] has its metadata stored in info.relations[i]
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Info] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for Infos {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Infos")
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .finish()
+    }
+}
+
+impl Infos {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Infos"), schema::infos::INFOS)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::infos::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::infos::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::infos::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Info]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways,
+            relations,
+        })
+    }
+}
+
+/// Builder for creating [`Infos`] archives.
+///
+///[`Infos`]: struct.Infos.html
+#[derive(Clone, Debug)]
+pub struct InfosBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl InfosBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Infos.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::infos::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Infos.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::infos::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.Infos.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::infos::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.Infos.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::infos::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.Infos.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Info]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::infos::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.Infos.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Info>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::infos::resources::RELATIONS)
+    }
+
+}
+
+impl InfosBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Infos", schema::infos::INFOS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive storing spatial indexes of nodes and ways
+#[derive(Clone)]
+pub struct SpatialIndex {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::RTreeEntry],
+    ways : &'static [super::osm::RTreeEntry],
+}
+
+impl SpatialIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Packed R-tree over the positions of all nodes in the parent archive
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::RTreeEntry] {
+        self.nodes
+    }
+
+    /// Packed R-tree over the bounding boxes of all ways in the parent archive
+///
+/// Ways without any resolved nodes are not indexed.
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::RTreeEntry] {
+        self.ways
+    }
+
+}
+
+impl ::std::fmt::Debug for SpatialIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("SpatialIndex")
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .finish()
+    }
+}
+
+impl SpatialIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("SpatialIndex"), schema::spatial_index::SPATIAL_INDEX)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::spatial_index::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::RTreeEntry]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::spatial_index::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::RTreeEntry]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways,
+        })
+    }
+}
+
+/// Builder for creating [`SpatialIndex`] archives.
+///
+///[`SpatialIndex`]: struct.SpatialIndex.html
+#[derive(Clone, Debug)]
+pub struct SpatialIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl SpatialIndexBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.SpatialIndex.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::RTreeEntry]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::spatial_index::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.SpatialIndex.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::RTreeEntry>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::spatial_index::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.SpatialIndex.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::RTreeEntry]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::spatial_index::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.SpatialIndex.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::RTreeEntry>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::spatial_index::resources::WAYS)
+    }
+
+}
+
+impl SpatialIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("SpatialIndex", schema::spatial_index::SPATIAL_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive mapping OSM ids of nodes, ways, and relations to their indexes
+///
+/// The ids of each element type are split into blocks by their upper bits. The offsets of
+/// the ids in a block are sorted, and the position of an offset in the vector of offsets
+/// is the index of the element in the parent archive. If the nodes and ways of the parent
+/// archive are not ordered by id, the positions are mapped to their indexes by `node_order`
+/// resp. `way_order`.
+#[derive(Clone)]
+pub struct IdIndex {
+    _storage: flatdata::StorageHandle,
+    node_blocks : &'static [super::osm::IdBlock],
+    node_ids : &'static [super::osm::IdOffset],
+    way_blocks : &'static [super::osm::IdBlock],
+    way_ids : &'static [super::osm::IdOffset],
+    relation_blocks : &'static [super::osm::IdBlock],
+    relation_ids : &'static [super::osm::IdOffset],
+    node_order : Option<&'static [super::osm::ElementIndex]>,
+    way_order : Option<&'static [super::osm::ElementIndex]>,
+}
+
+impl IdIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Blocks of node ids: the ids `(i << 24)..((i + 1) << 24)` are in range
+/// id_index.node_blocks[i].ids() of node_ids
+    #[inline]
+    pub fn node_blocks(&self) -> &[super::osm::IdBlock] {
+        self.node_blocks
+    }
+
+    /// Sorted offsets of node ids in their blocks
+/// The node with the id at node_ids[i] is nodes[i] (resp. nodes[node_order[i]])
+    #[inline]
+    pub fn node_ids(&self) -> &[super::osm::IdOffset] {
+        self.node_ids
+    }
+
+    /// Blocks of way ids: the ids `(i << 24)..((i + 1) << 24)` are in range
+/// id_index.way_blocks[i].ids() of way_ids
+    #[inline]
+    pub fn way_blocks(&self) -> &[super::osm::IdBlock] {
+        self.way_blocks
+    }
+
+    /// Sorted offsets of way ids in their blocks
+/// The way with the id at way_ids[i] is ways[i] (resp. ways[way_order[i]])
+    #[inline]
+    pub fn way_ids(&self) -> &[super::osm::IdOffset] {
+        self.way_ids
+    }
+
+    /// Blocks of relation ids: the ids `(i << 24)..((i + 1) << 24)` are in range
+/// id_index.relation_blocks[i].ids() of relation_ids
+    #[inline]
+    pub fn relation_blocks(&self) -> &[super::osm::IdBlock] {
+        self.relation_blocks
+    }
+
+    /// Sorted offsets of relation ids in their blocks
+/// The relation with the id at relation_ids[i] is relations[i]
+    #[inline]
+    pub fn relation_ids(&self) -> &[super::osm::IdOffset] {
+        self.relation_ids
+    }
+
+    /// Indexes of the nodes in the order of `node_ids`, only present if the nodes are not
+/// ordered by id, e.g. when compiled with `osmflatc --spatial-order`
+    #[inline]
+    pub fn node_order(&self) -> Option<&[super::osm::ElementIndex]> {
+        self.node_order
+    }
+
+    /// Indexes of the ways in the order of `way_ids`, only present if the ways are not
+/// ordered by id, e.g. when compiled with `osmflatc --spatial-order`
+    #[inline]
+    pub fn way_order(&self) -> Option<&[super::osm::ElementIndex]> {
+        self.way_order
+    }
+
+}
+
+impl ::std::fmt::Debug for IdIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("IdIndex")
+            .field("node_blocks", &self.node_blocks())
+            .field("node_ids", &self.node_ids())
+            .field("way_blocks", &self.way_blocks())
+            .field("way_ids", &self.way_ids())
+            .field("relation_blocks", &self.relation_blocks())
+            .field("relation_ids", &self.relation_ids())
+            .field("node_order", &self.node_order())
+            .field("way_order", &self.way_order())
+            .finish()
+    }
+}
+
+impl IdIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("IdIndex"), schema::id_index::ID_INDEX)?;
+
+        let node_blocks = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_blocks", schema::id_index::resources::NODE_BLOCKS));
+            check("node_blocks", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdBlock]>::from_bytes(x)))?
+        };
+        let node_ids = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("node_ids", schema::id_index::resources::NODE_IDS));
+            check("node_ids", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdOffset]>::from_bytes(x)))?
+        };
+        let way_blocks = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_blocks", schema::id_index::resources::WAY_BLOCKS));
+            check("way_blocks", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdBlock]>::from_bytes(x)))?
+        };
+        let way_ids = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("way_ids", schema::id_index::resources::WAY_IDS));
+            check("way_ids", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdOffset]>::from_bytes(x)))?
+        };
+        let relation_blocks = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relation_blocks", schema::id_index::resources::RELATION_BLOCKS));
+            check("relation_blocks", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdBlock]>::from_bytes(x)))?
+        };
+        let relation_ids = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("relation_ids", schema::id_index::resources::RELATION_IDS));
+            check("relation_ids", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IdOffset]>::from_bytes(x)))?
+        };
+        let node_order = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_order", schema::id_index::resources::NODE_ORDER));
+            check("node_order", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ElementIndex]>::from_bytes(x)))?
+        };
+        let way_order = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_order", schema::id_index::resources::WAY_ORDER));
+            check("way_order", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ElementIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            node_blocks,
+            node_ids,
+            way_blocks,
+            way_ids,
+            relation_blocks,
+            relation_ids,
+            node_order,
+            way_order,
+        })
+    }
+}
+
+/// Builder for creating [`IdIndex`] archives.
+///
+///[`IdIndex`]: struct.IdIndex.html
+#[derive(Clone, Debug)]
+pub struct IdIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl IdIndexBuilder {
+    #[inline]
+    /// Stores [`node_blocks`] in the archive.
+    ///
+    /// [`node_blocks`]: struct.IdIndex.html#method.node_blocks
+    pub fn set_node_blocks(&self, vector: &[super::osm::IdBlock]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_blocks", schema::id_index::resources::NODE_BLOCKS, vector.as_bytes())
+    }
+
+    /// Opens [`node_blocks`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_blocks`]: struct.IdIndex.html#method.node_blocks
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_blocks(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdBlock>> {
+        flatdata::create_external_vector(&*self.storage, "node_blocks", schema::id_index::resources::NODE_BLOCKS)
+    }
+
+    #[inline]
+    /// Stores [`node_ids`] in the archive.
+    ///
+    /// [`node_ids`]: struct.IdIndex.html#method.node_ids
+    pub fn set_node_ids(&self, vector: &[super::osm::IdOffset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_ids", schema::id_index::resources::NODE_IDS, vector.as_bytes())
+    }
+
+    /// Opens [`node_ids`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_ids`]: struct.IdIndex.html#method.node_ids
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_ids(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdOffset>> {
+        flatdata::create_external_vector(&*self.storage, "node_ids", schema::id_index::resources::NODE_IDS)
+    }
+
+    #[inline]
+    /// Stores [`way_blocks`] in the archive.
+    ///
+    /// [`way_blocks`]: struct.IdIndex.html#method.way_blocks
+    pub fn set_way_blocks(&self, vector: &[super::osm::IdBlock]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_blocks", schema::id_index::resources::WAY_BLOCKS, vector.as_bytes())
+    }
+
+    /// Opens [`way_blocks`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_blocks`]: struct.IdIndex.html#method.way_blocks
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_blocks(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdBlock>> {
+        flatdata::create_external_vector(&*self.storage, "way_blocks", schema::id_index::resources::WAY_BLOCKS)
+    }
+
+    #[inline]
+    /// Stores [`way_ids`] in the archive.
+    ///
+    /// [`way_ids`]: struct.IdIndex.html#method.way_ids
+    pub fn set_way_ids(&self, vector: &[super::osm::IdOffset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_ids", schema::id_index::resources::WAY_IDS, vector.as_bytes())
+    }
+
+    /// Opens [`way_ids`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_ids`]: struct.IdIndex.html#method.way_ids
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_ids(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdOffset>> {
+        flatdata::create_external_vector(&*self.storage, "way_ids", schema::id_index::resources::WAY_IDS)
+    }
+
+    #[inline]
+    /// Stores [`relation_blocks`] in the archive.
+    ///
+    /// [`relation_blocks`]: struct.IdIndex.html#method.relation_blocks
+    pub fn set_relation_blocks(&self, vector: &[super::osm::IdBlock]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_blocks", schema::id_index::resources::RELATION_BLOCKS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_blocks`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_blocks`]: struct.IdIndex.html#method.relation_blocks
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_blocks(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdBlock>> {
+        flatdata::create_external_vector(&*self.storage, "relation_blocks", schema::id_index::resources::RELATION_BLOCKS)
+    }
+
+    #[inline]
+    /// Stores [`relation_ids`] in the archive.
+    ///
+    /// [`relation_ids`]: struct.IdIndex.html#method.relation_ids
+    pub fn set_relation_ids(&self, vector: &[super::osm::IdOffset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_ids", schema::id_index::resources::RELATION_IDS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_ids`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_ids`]: struct.IdIndex.html#method.relation_ids
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_ids(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IdOffset>> {
+        flatdata::create_external_vector(&*self.storage, "relation_ids", schema::id_index::resources::RELATION_IDS)
+    }
+
+    #[inline]
+    /// Stores [`node_order`] in the archive.
+    ///
+    /// [`node_order`]: struct.IdIndex.html#method.node_order
+    pub fn set_node_order(&self, vector: &[super::osm::ElementIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_order", schema::id_index::resources::NODE_ORDER, vector.as_bytes())
+    }
+
+    /// Opens [`node_order`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_order`]: struct.IdIndex.html#method.node_order
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_order(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ElementIndex>> {
+        flatdata::create_external_vector(&*self.storage, "node_order", schema::id_index::resources::NODE_ORDER)
+    }
+
+    #[inline]
+    /// Stores [`way_order`] in the archive.
+    ///
+    /// [`way_order`]: struct.IdIndex.html#method.way_order
+    pub fn set_way_order(&self, vector: &[super::osm::ElementIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_order", schema::id_index::resources::WAY_ORDER, vector.as_bytes())
+    }
+
+    /// Opens [`way_order`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_order`]: struct.IdIndex.html#method.way_order
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_order(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ElementIndex>> {
+        flatdata::create_external_vector(&*self.storage, "way_order", schema::id_index::resources::WAY_ORDER)
+    }
+
+}
+
+impl IdIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("IdIndex", schema::id_index::ID_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive storing for each node the ways referencing it
+#[derive(Clone)]
+pub struct NodeWays {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::WayRange],
+    ways_index : &'static [super::osm::WayIndex],
+}
+
+impl NodeWays {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// List of ranges of ways of all nodes in the parent archive
+/// nodes[i] is referenced by the ways in range node_ways.nodes[i].ways()
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::WayRange] {
+        self.nodes
+    }
+
+    /// Auxiliary index of ways to model the 1:n relationship between nodes and ways
+    #[inline]
+    pub fn ways_index(&self) -> &[super::osm::WayIndex] {
+        self.ways_index
+    }
+
+}
+
+impl ::std::fmt::Debug for NodeWays {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("NodeWays")
+            .field("nodes", &self.nodes())
+            .field("ways_index", &self.ways_index())
+            .finish()
+    }
+}
+
+impl NodeWays {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("NodeWays"), schema::node_ways::NODE_WAYS)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::node_ways::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::WayRange]>::from_bytes(x)))?
+        };
+        let ways_index = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("ways_index", schema::node_ways::resources::WAYS_INDEX));
+            check("ways_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::WayIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways_index,
+        })
+    }
+}
+
+/// Builder for creating [`NodeWays`] archives.
+///
+///[`NodeWays`]: struct.NodeWays.html
+#[derive(Clone, Debug)]
+pub struct NodeWaysBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl NodeWaysBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.NodeWays.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::WayRange]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::node_ways::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.NodeWays.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::WayRange>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::node_ways::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways_index`] in the archive.
+    ///
+    /// [`ways_index`]: struct.NodeWays.html#method.ways_index
+    pub fn set_ways_index(&self, vector: &[super::osm::WayIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways_index", schema::node_ways::resources::WAYS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`ways_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways_index`]: struct.NodeWays.html#method.ways_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::WayIndex>> {
+        flatdata::create_external_vector(&*self.storage, "ways_index", schema::node_ways::resources::WAYS_INDEX)
+    }
+
+}
+
+impl NodeWaysBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("NodeWays", schema::node_ways::NODE_WAYS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive storing for each node, way and relation the relations having it as
+/// a member
+///
+/// A relation having an element multiple times as a member is stored once per membership.
+#[derive(Clone)]
+pub struct ParentRelations {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::ParentRange],
+    node_parents : &'static [super::osm::ParentRelation],
+    ways : &'static [super::osm::ParentRange],
+    way_parents : &'static [super::osm::ParentRelation],
+    relations : &'static [super::osm::ParentRange],
+    relation_parents : &'static [super::osm::ParentRelation],
+}
+
+impl ParentRelations {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// List of ranges of parent relations of all nodes in the parent archive
+/// nodes[i] is a member of the relations in range parent_relations.nodes[i].parents()
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::ParentRange] {
+        self.nodes
+    }
+
+    /// Parent relations of nodes
+    #[inline]
+    pub fn node_parents(&self) -> &[super::osm::ParentRelation] {
+        self.node_parents
+    }
+
+    /// List of ranges of parent relations of all ways in the parent archive
+/// ways[i] is a member of the relations in range parent_relations.ways[i].parents()
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::ParentRange] {
+        self.ways
+    }
+
+    /// Parent relations of ways
+    #[inline]
+    pub fn way_parents(&self) -> &[super::osm::ParentRelation] {
+        self.way_parents
+    }
+
+    /// List of ranges of parent relations of all relations in the parent archive
+/// relations[i] is a member of the relations in range parent_relations.relations[i].parents()
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::ParentRange] {
+        self.relations
+    }
+
+    /// Parent relations of relations
+    #[inline]
+    pub fn relation_parents(&self) -> &[super::osm::ParentRelation] {
+        self.relation_parents
+    }
+
+}
+
+impl ::std::fmt::Debug for ParentRelations {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("ParentRelations")
+            .field("nodes", &self.nodes())
+            .field("node_parents", &self.node_parents())
+            .field("ways", &self.ways())
+            .field("way_parents", &self.way_parents())
+            .field("relations", &self.relations())
+            .field("relation_parents", &self.relation_parents())
+            .finish()
+    }
+}
+
+impl ParentRelations {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("ParentRelations"), schema::parent_relations::PARENT_RELATIONS)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::parent_relations::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRange]>::from_bytes(x)))?
+        };
+        let node_parents = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("node_parents", schema::parent_relations::resources::NODE_PARENTS));
+            check("node_parents", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::parent_relations::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRange]>::from_bytes(x)))?
+        };
+        let way_parents = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("way_parents", schema::parent_relations::resources::WAY_PARENTS));
+            check("way_parents", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::parent_relations::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRange]>::from_bytes(x)))?
+        };
+        let relation_parents = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("relation_parents", schema::parent_relations::resources::RELATION_PARENTS));
+            check("relation_parents", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ParentRelation]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            node_parents,
+            ways,
+            way_parents,
+            relations,
+            relation_parents,
+        })
+    }
+}
+
+/// Builder for creating [`ParentRelations`] archives.
+///
+///[`ParentRelations`]: struct.ParentRelations.html
+#[derive(Clone, Debug)]
+pub struct ParentRelationsBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl ParentRelationsBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.ParentRelations.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::ParentRange]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::parent_relations::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.ParentRelations.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRange>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::parent_relations::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`node_parents`] in the archive.
+    ///
+    /// [`node_parents`]: struct.ParentRelations.html#method.node_parents
+    pub fn set_node_parents(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_parents", schema::parent_relations::resources::NODE_PARENTS, vector.as_bytes())
+    }
+
+    /// Opens [`node_parents`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_parents`]: struct.ParentRelations.html#method.node_parents
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_parents(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "node_parents", schema::parent_relations::resources::NODE_PARENTS)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.ParentRelations.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::ParentRange]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::parent_relations::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.ParentRelations.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRange>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::parent_relations::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`way_parents`] in the archive.
+    ///
+    /// [`way_parents`]: struct.ParentRelations.html#method.way_parents
+    pub fn set_way_parents(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_parents", schema::parent_relations::resources::WAY_PARENTS, vector.as_bytes())
+    }
+
+    /// Opens [`way_parents`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_parents`]: struct.ParentRelations.html#method.way_parents
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_parents(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "way_parents", schema::parent_relations::resources::WAY_PARENTS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.ParentRelations.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::ParentRange]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::parent_relations::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.ParentRelations.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRange>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::parent_relations::resources::RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`relation_parents`] in the archive.
+    ///
+    /// [`relation_parents`]: struct.ParentRelations.html#method.relation_parents
+    pub fn set_relation_parents(&self, vector: &[super::osm::ParentRelation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_parents", schema::parent_relations::resources::RELATION_PARENTS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_parents`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_parents`]: struct.ParentRelations.html#method.relation_parents
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_parents(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ParentRelation>> {
+        flatdata::create_external_vector(&*self.storage, "relation_parents", schema::parent_relations::resources::RELATION_PARENTS)
+    }
+
+}
+
+impl ParentRelationsBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("ParentRelations", schema::parent_relations::PARENT_RELATIONS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// An optional sub-archive storing for each tag the elements having it (inverted tag index)
+#[derive(Clone)]
+pub struct TagElements {
+    _storage: flatdata::StorageHandle,
+    tags : &'static [super::osm::TagElementRanges],
+    nodes : &'static [super::osm::ElementIndex],
+    ways : &'static [super::osm::ElementIndex],
+    relations : &'static [super::osm::ElementIndex],
+    sorted_tags : &'static [super::osm::TagIndex],
+}
+
+impl TagElements {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// List of ranges of elements of all tags in the parent archive
+/// tags[i] is attached to the elements in ranges tag_elements.tags[i].nodes(),
+/// tag_elements.tags[i].ways() and tag_elements.tags[i].relations()
+/// Elements are indexed under the first tag with the same key and value, the
+/// ranges of all other such tags are empty.
+    #[inline]
+    pub fn tags(&self) -> &[super::osm::TagElementRanges] {
+        self.tags
+    }
+
+    /// Indexes of nodes having a tag, sorted by tag and ascending
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::ElementIndex] {
+        self.nodes
+    }
+
+    /// Indexes of ways having a tag, sorted by tag and ascending
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::ElementIndex] {
+        self.ways
+    }
+
+    /// Indexes of relations having a tag, sorted by tag and ascending
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::ElementIndex] {
+        self.relations
+    }
+
+    /// Indexes of all distinct tags in the parent archive sorted by key and value
+/// Each key and value occurs once, by the first tag having it.
+    #[inline]
+    pub fn sorted_tags(&self) -> &[super::osm::TagIndex] {
+        self.sorted_tags
+    }
+
+}
+
+impl ::std::fmt::Debug for TagElements {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("TagElements")
+            .field("tags", &self.tags())
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .field("sorted_tags", &self.sorted_tags())
+            .finish()
+    }
+}
+
+impl TagElements {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("TagElements"), schema::tag_elements::TAG_ELEMENTS)?;
+
+        let tags = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tags", schema::tag_elements::resources::TAGS));
+            check("tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagElementRanges]>::from_bytes(x)))?
+        };
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("nodes", schema::tag_elements::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ElementIndex]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("ways", schema::tag_elements::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ElementIndex]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("relations", schema::tag_elements::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ElementIndex]>::from_bytes(x)))?
+        };
+        let sorted_tags = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("sorted_tags", schema::tag_elements::resources::SORTED_TAGS));
+            check("sorted_tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            tags,
+            nodes,
+            ways,
+            relations,
+            sorted_tags,
+        })
+    }
+}
+
+/// Builder for creating [`TagElements`] archives.
+///
+///[`TagElements`]: struct.TagElements.html
+#[derive(Clone, Debug)]
+pub struct TagElementsBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl TagElementsBuilder {
+    #[inline]
+    /// Stores [`tags`] in the archive.
+    ///
+    /// [`tags`]: struct.TagElements.html#method.tags
+    pub fn set_tags(&self, vector: &[super::osm::TagElementRanges]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags", schema::tag_elements::resources::TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags`]: struct.TagElements.html#method.tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagElementRanges>> {
+        flatdata::create_external_vector(&*self.storage, "tags", schema::tag_elements::resources::TAGS)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.TagElements.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::ElementIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::tag_elements::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.TagElements.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ElementIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::tag_elements::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.TagElements.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::ElementIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::tag_elements::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.TagElements.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ElementIndex>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::tag_elements::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.TagElements.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::ElementIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::tag_elements::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.TagElements.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ElementIndex>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::tag_elements::resources::RELATIONS)
+    }
+
+    #[inline]
+    /// Stores [`sorted_tags`] in the archive.
+    ///
+    /// [`sorted_tags`]: struct.TagElements.html#method.sorted_tags
+    pub fn set_sorted_tags(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("sorted_tags", schema::tag_elements::resources::SORTED_TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`sorted_tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`sorted_tags`]: struct.TagElements.html#method.sorted_tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_sorted_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
+        flatdata::create_external_vector(&*self.storage, "sorted_tags", schema::tag_elements::resources::SORTED_TAGS)
+    }
+
+}
+
+impl TagElementsBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("TagElements", schema::tag_elements::TAG_ELEMENTS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+
+/// Enum for read-only heterogeneous access to elements in a
+/// bucket of the [`relation_members`] resource.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+#[derive(Clone, PartialEq)]
+pub enum RelationMembersRef<'a> {
+    #[allow(missing_docs)]
+    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
+    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
+    RelationMember(&'a super::osm::RelationMember),}
+
+impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
+        }
+    }
+}
+
+impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
+    #[inline]
+    fn size_in_bytes(&self) -> usize {
+        match *self {
+            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
+        }
+    }
+}
+
+/// Builder of buckets in the [`relation_members`] resource.
+///
+/// Refers to a single bucket in the [`relation_members`] multivector and
+/// provides methods for adding heterogeneous data to the bucket.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+pub struct RelationMembersBuilder<'a> {
+    data: &'a mut Vec<u8>
+}
+
+impl<'a> RelationMembersBuilder<'a> {
+    /// Adds data of the type [`NodeMember`] to the bucket.
+    ///
+    /// [`NodeMember`]: struct.NodeMember.html
+    #[inline]
+    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 0;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
+    }
+    /// Adds data of the type [`WayMember`] to the bucket.
+    ///
+    /// [`WayMember`]: struct.WayMember.html
+    #[inline]
+    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 1;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
+    }
+    /// Adds data of the type [`RelationMember`] to the bucket.
+    ///
+    /// [`RelationMember`]: struct.RelationMember.html
+    #[inline]
+    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 2;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+    }
+}
+
+/// Variadic struct attached to the [`relation_members`] archive resource.
+///
+/// It unifies the following data types:
+//
+/// * [`NodeMember`]
+/// * [`WayMember`]
+/// * [`RelationMember`]
+///
+/// ## Access pattern
+///
+/// This structure is used as a template parameter in [`relation_members`] multivector/
+/// multiarray view. It does not contain any data, instead it references
+///
+/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
+/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+/// [`RelationMembersRef`]: enum.RelationMembersRef.html
+/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
+/// [`NodeMember`]: struct.NodeMember.html
+/// [`WayMember`]: struct.WayMember.html
+/// [`RelationMember`]: struct.RelationMember.html
+#[derive(Clone)]
+pub struct RelationMembers {}
+
+impl flatdata::VariadicIndex for RelationMembers {
+    type Index = super::_builtin::multivector::IndexType40;
+}
+
+impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
+    type Item = RelationMembersRef<'a>;
+
+    #[inline]
+    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
+    {
+        match index {
+                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
+                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
+                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
+            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+        }
+    }
+
+    type ItemMut = RelationMembersBuilder<'a>;
+
+    #[inline]
+    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
+    {
+        Self::ItemMut { data }
+    }
+}
+
+/// OSM data archive
+///
+/// Relations and relation members are indexed with the same index, i.e.
+/// a relation at index `i` in the vector `relations` has the members
+/// at index `i` in the multivector `relation_members`.
+///
+/// All 1:n relationships are modeled in-place by using an additional index. This is a
+/// common pattern in flatdata. For example, a node might have multiple tags attached
+/// to it. To model this, a node in `nodes` references the first tag attached to it
+/// by storing an index in the `tags_index` vector. The next node in `nodes` again
+/// references its first tag, which is the last tag (exclusive) of the previous node.
+///
+/// ```text
+/// nodes:      [ ..., n_1, n_2, ... ]
+///                     |    |
+///                     |    +-------+
+///                     v            v
+/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
+/// ```
+#[derive(Clone)]
+pub struct Osm {
+    _storage: flatdata::StorageHandle,
+    header : &'static super::osm::Header,
+    nodes : &'static [super::osm::Node],
+    ways : &'static [super::osm::Way],
+    relations : &'static [super::osm::Relation],
+    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
+    tags : &'static [super::osm::Tag],
+    tags_index : &'static [super::osm::TagIndex],
+    nodes_index : &'static [super::osm::NodeIndex],
+    stringtable : flatdata::RawData<'static>,
+    ids : Option<super::osm::Ids
+>,
+    id_index : Option<super::osm::IdIndex
+>,
+    info : Option<super::osm::Infos
+>,
+    spatial_index : Option<super::osm::SpatialIndex
+>,
+    node_ways : Option<super::osm::NodeWays
+>,
+    parent_relations : Option<super::osm::ParentRelations
+>,
+    tag_elements : Option<super::osm::TagElements
+>,
+}
+
+impl Osm {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Header which contains the metadata attached to the archive.
+    #[inline]
+    pub fn header(&self) -> &super::osm::Header {
+        self.header
+    }
+
+    /// List of nodes.
+///
+/// A node references a range of tags in the `tags_index` vector.
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Node] {
+        self.nodes
+    }
+
+    /// List of ways.
+///
+/// A way references
+///
+/// * a range of tags in the `tags_index` vector, and
+/// * a range of nodes in the `nodes_index` vector.
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Way] {
+        self.ways
+    }
+
+    /// List of relations.
+///
+/// A relation references a range of tags in `tags_index` vectors.
+/// Members are attached to a relation implicitly: members that belong to a
+/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Relation] {
+        self.relations
+    }
+
+    /// Members attached to relations.
+///
+/// An index in this multivector corresponds to an index in the `relations` vector.
+///
+/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
+/// Each type references its role in the `stringtable` raw data. Additionally,
+///
+/// * a node member references a node in the `nodes` vector,
+/// * a way member references a way in the `ways` vector,
+/// * a relation member references a relation in the `relations` vector.
+    #[inline]
+    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
+        &self.relation_members
+    }
+
+    /// List of tags.
+///
+/// A tag references its key and value in the `stringtable` raw data.
+    #[inline]
+    pub fn tags(&self) -> &[super::osm::Tag] {
+        self.tags
+    }
+
+    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
+/// and tags.
+    #[inline]
+    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
+        self.tags_index
+    }
+
+    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    #[inline]
+    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
+        self.nodes_index
+    }
+
+    /// List of strings separated by `\0`.
+    #[inline]
+    pub fn stringtable(&self) -> flatdata::RawData {
+        self.stringtable
+    }
+
+    #[inline]
+    pub fn ids(&self) -> Option<&super::osm::Ids> {
+        self.ids.as_ref()
+    }
+
+    #[inline]
+    pub fn id_index(&self) -> Option<&super::osm::IdIndex> {
+        self.id_index.as_ref()
+    }
+
+    #[inline]
+    pub fn info(&self) -> Option<&super::osm::Infos> {
+        self.info.as_ref()
+    }
+
+    #[inline]
+    pub fn spatial_index(&self) -> Option<&super::osm::SpatialIndex> {
+        self.spatial_index.as_ref()
+    }
+
+    #[inline]
+    pub fn node_ways(&self) -> Option<&super::osm::NodeWays> {
+        self.node_ways.as_ref()
+    }
+
+    #[inline]
+    pub fn parent_relations(&self) -> Option<&super::osm::ParentRelations> {
+        self.parent_relations.as_ref()
+    }
+
+    #[inline]
+    pub fn tag_elements(&self) -> Option<&super::osm::TagElements> {
+        self.tag_elements.as_ref()
+    }
+
+}
+
+impl ::std::fmt::Debug for Osm {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Osm")
+            .field("header", &self.header())
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .field("relation_members", &self.relation_members())
+            .field("tags", &self.tags())
+            .field("tags_index", &self.tags_index())
+            .field("nodes_index", &self.nodes_index())
+            .field("stringtable", &self.stringtable())
+            .field("ids", &self.ids())
+            .field("id_index", &self.id_index())
+            .field("info", &self.info())
+            .field("spatial_index", &self.spatial_index())
+            .field("node_ways", &self.node_ways())
+            .field("parent_relations", &self.parent_relations())
+            .field("tag_elements", &self.tag_elements())
+            .finish()
+    }
+}
+
+impl Osm {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
+
+        let header = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("header", schema::osm::resources::HEADER));
+            check("header", |_| 0, max_size, resource.and_then(|x| super::osm::Header::from_bytes_slice(x)))?
+        };
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Node]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Way]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Relation]>::from_bytes(x)))?
+        };
+        let relation_members = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
+            let index = extend(storage.read("relation_members_index", &index_schema));
+            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
+            let result = match (index, data) {
+                (Ok(index), Ok(data)) => {
+                    Ok(flatdata::MultiArrayView::new(
+                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
+                        data
+                    ))
+                }
+                // is resource completely missing?
+                (Err(Error::Missing), Err(Error::Missing))  => Err(Error::Missing),
+                // is resource partially missing / broken -> extract best error to propagate
+                (Ok(_), Err(Error::Missing)) | (Err(Error::Missing), Ok(_)) => Err(Error::MissingData),
+                (Err(Error::Missing), Err(x)) | (Err(x), Err(Error::Missing)) => {return Err(x);}
+                (_, Err(x)) | (Err(x), _) => {return Err(x);}
+            };
+            check("relation_members", |r| r.len(), max_size, result)?
+        };
+        let tags = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
+            check("tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Tag]>::from_bytes(x)))?
+        };
+        let tags_index = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
+            check("tags_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
+        };
+        let nodes_index = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
+            check("nodes_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?
+        };
+        let stringtable = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
+            check("stringtable", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
+        };
+        let ids = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("ids", |_| 0, max_size, super::osm::Ids::open(storage.subdir("ids")))?
+        };
+        let id_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("id_index", |_| 0, max_size, super::osm::IdIndex::open(storage.subdir("id_index")))?
+        };
+        let info = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("info", |_| 0, max_size, super::osm::Infos::open(storage.subdir("info")))?
+        };
+        let spatial_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("spatial_index", |_| 0, max_size, super::osm::SpatialIndex::open(storage.subdir("spatial_index")))?
+        };
+        let node_ways = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("node_ways", |_| 0, max_size, super::osm::NodeWays::open(storage.subdir("node_ways")))?
+        };
+        let parent_relations = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("parent_relations", |_| 0, max_size, super::osm::ParentRelations::open(storage.subdir("parent_relations")))?
+        };
+        let tag_elements = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("tag_elements", |_| 0, max_size, super::osm::TagElements::open(storage.subdir("tag_elements")))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            header,
+            nodes,
+            ways,
+            relations,
+            relation_members,
+            tags,
+            tags_index,
+            nodes_index,
+            stringtable,
+            ids,
+            id_index,
+            info,
+            spatial_index,
+            node_ways,
+            parent_relations,
+            tag_elements,
+        })
+    }
+}
+
+/// Builder for creating [`Osm`] archives.
+///
+///[`Osm`]: struct.Osm.html
+#[derive(Clone, Debug)]
+pub struct OsmBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl OsmBuilder {
+    #[inline]
+    /// Stores [`header`] in the archive.
+    ///
+    /// [`header`]: struct.Osm.html#method.header
+    /// Stores [`header`] in the archive.
+    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
+        let data = resource.as_bytes();
+        self.storage.write("header", schema::osm::resources::HEADER, data)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    }
+
+    /// Opens [`relation_members`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the multivector until the [`MultiVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_members`]: struct.Osm.html#method.relation_members
+    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
+    #[inline]
+    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
+        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+    }
+
+    #[inline]
+    /// Stores [`tags`] in the archive.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
+        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
+    }
+
+    #[inline]
+    /// Stores [`tags_index`] in the archive.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`tags_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`nodes_index`] in the archive.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`nodes_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    }
+
+    /// Stores [`stringtable`] in the archive.
+    ///
+    /// [`stringtable`]: struct.Osm.html#method.stringtable
+    #[inline]
+    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
+        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    }
+
+    /// Stores [`ids`] in the archive.
+    ///
+    /// [`ids`]: struct.Osm.html#method.ids
+    #[inline]
+    pub fn ids(&self) -> Result<super::osm::IdsBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("ids");
+        super::osm::IdsBuilder::new(storage)
+    }
+
+    /// Stores [`id_index`] in the archive.
+    ///
+    /// [`id_index`]: struct.Osm.html#method.id_index
+    #[inline]
+    pub fn id_index(&self) -> Result<super::osm::IdIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("id_index");
+        super::osm::IdIndexBuilder::new(storage)
+    }
+
+    /// Stores [`info`] in the archive.
+    ///
+    /// [`info`]: struct.Osm.html#method.info
+    #[inline]
+    pub fn info(&self) -> Result<super::osm::InfosBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("info");
+        super::osm::InfosBuilder::new(storage)
+    }
+
+    /// Stores [`spatial_index`] in the archive.
+    ///
+    /// [`spatial_index`]: struct.Osm.html#method.spatial_index
+    #[inline]
+    pub fn spatial_index(&self) -> Result<super::osm::SpatialIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("spatial_index");
+        super::osm::SpatialIndexBuilder::new(storage)
+    }
+
+    /// Stores [`node_ways`] in the archive.
+    ///
+    /// [`node_ways`]: struct.Osm.html#method.node_ways
+    #[inline]
+    pub fn node_ways(&self) -> Result<super::osm::NodeWaysBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("node_ways");
+        super::osm::NodeWaysBuilder::new(storage)
+    }
+
+    /// Stores [`parent_relations`] in the archive.
+    ///
+    /// [`parent_relations`]: struct.Osm.html#method.parent_relations
+    #[inline]
+    pub fn parent_relations(&self) -> Result<super::osm::ParentRelationsBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("parent_relations");
+        super::osm::ParentRelationsBuilder::new(storage)
+    }
+
+    /// Stores [`tag_elements`] in the archive.
+    ///
+    /// [`tag_elements`]: struct.Osm.html#method.tag_elements
+    #[inline]
+    pub fn tag_elements(&self) -> Result<super::osm::TagElementsBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("tag_elements");
+        super::osm::TagElementsBuilder::new(storage)
+    }
+
+}
+
+impl OsmBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+#[doc(hidden)]
+pub mod schema {
+pub mod ids {
+
+pub const IDS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    nodes : vector< .osm.Id >;
+    ways : vector< .osm.Id >;
+    relations : vector< .osm.Id >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    nodes : vector< .osm.Id >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    ways : vector< .osm.Id >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    relations : vector< .osm.Id >;
+}
+}
+
+"#;
+}
+}
+pub mod infos {
+
+pub const INFOS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Infos
+{
+    nodes : vector< .osm.Info >;
+    ways : vector< .osm.Info >;
+    relations : vector< .osm.Info >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Infos
+{
+    nodes : vector< .osm.Info >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Infos
+{
+    ways : vector< .osm.Info >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Infos
+{
+    relations : vector< .osm.Info >;
+}
+}
+
+"#;
+}
+}
+pub mod spatial_index {
+
+pub const SPATIAL_INDEX: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct RTreeEntry
+{
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    @range( children )
+    child_first_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    element_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.RTreeEntry >;
+    ways : vector< .osm.RTreeEntry >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct RTreeEntry
+{
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    @range( children )
+    child_first_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    element_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.RTreeEntry >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct RTreeEntry
+{
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    @range( children )
+    child_first_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    element_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    ways : vector< .osm.RTreeEntry >;
+}
+}
+
+"#;
+}
+}
+pub mod id_index {
+
+pub const ID_INDEX: &str = r#"namespace osm {
+struct IdBlock
+{
+    @range( ids )
+    id_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct IdOffset
+{
+    value : u32 : 24;
+}
+}
+
+namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.node_ids )
+    node_blocks : vector< .osm.IdBlock >;
+    node_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.way_ids )
+    way_blocks : vector< .osm.IdBlock >;
+    way_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.relation_ids )
+    relation_blocks : vector< .osm.IdBlock >;
+    relation_ids : vector< .osm.IdOffset >;
+    @optional
+    node_order : vector< .osm.ElementIndex >;
+    @optional
+    way_order : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODE_BLOCKS: &str = r#"namespace osm {
+struct IdBlock
+{
+    @range( ids )
+    id_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.node_ids )
+    node_blocks : vector< .osm.IdBlock >;
+}
+}
+
+"#;
+pub const NODE_IDS: &str = r#"namespace osm {
+struct IdOffset
+{
+    value : u32 : 24;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    node_ids : vector< .osm.IdOffset >;
+}
+}
+
+"#;
+pub const WAY_BLOCKS: &str = r#"namespace osm {
+struct IdBlock
+{
+    @range( ids )
+    id_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.way_ids )
+    way_blocks : vector< .osm.IdBlock >;
+}
+}
+
+"#;
+pub const WAY_IDS: &str = r#"namespace osm {
+struct IdOffset
+{
+    value : u32 : 24;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    way_ids : vector< .osm.IdOffset >;
+}
+}
+
+"#;
+pub const RELATION_BLOCKS: &str = r#"namespace osm {
+struct IdBlock
+{
+    @range( ids )
+    id_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.relation_ids )
+    relation_blocks : vector< .osm.IdBlock >;
+}
+}
+
+"#;
+pub const RELATION_IDS: &str = r#"namespace osm {
+struct IdOffset
+{
+    value : u32 : 24;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    relation_ids : vector< .osm.IdOffset >;
+}
+}
+
+"#;
+pub const NODE_ORDER: &str = r#"namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @optional
+    node_order : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+pub const WAY_ORDER: &str = r#"namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @optional
+    way_order : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod node_ways {
+
+pub const NODE_WAYS: &str = r#"namespace osm {
+struct WayRange
+{
+    @range( ways )
+    way_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive NodeWays
+{
+    @explicit_reference( .osm.WayRange.way_first_idx, .osm.NodeWays.ways_index )
+    nodes : vector< .osm.WayRange >;
+    ways_index : vector< .osm.WayIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct WayRange
+{
+    @range( ways )
+    way_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive NodeWays
+{
+    @explicit_reference( .osm.WayRange.way_first_idx, .osm.NodeWays.ways_index )
+    nodes : vector< .osm.WayRange >;
+}
+}
+
+"#;
+pub const WAYS_INDEX: &str = r#"namespace osm {
+struct WayIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive NodeWays
+{
+    ways_index : vector< .osm.WayIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod parent_relations {
+
+pub const PARENT_RELATIONS: &str = r#"namespace osm {
+struct ParentRange
+{
+    @range( parents )
+    parent_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.node_parents )
+    nodes : vector< .osm.ParentRange >;
+    node_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.way_parents )
+    ways : vector< .osm.ParentRange >;
+    way_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.relation_parents )
+    relations : vector< .osm.ParentRange >;
+    relation_parents : vector< .osm.ParentRelation >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct ParentRange
+{
+    @range( parents )
+    parent_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.node_parents )
+    nodes : vector< .osm.ParentRange >;
+}
+}
+
+"#;
+pub const NODE_PARENTS: &str = r#"namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    node_parents : vector< .osm.ParentRelation >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct ParentRange
+{
+    @range( parents )
+    parent_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.way_parents )
+    ways : vector< .osm.ParentRange >;
+}
+}
+
+"#;
+pub const WAY_PARENTS: &str = r#"namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    way_parents : vector< .osm.ParentRelation >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct ParentRange
+{
+    @range( parents )
+    parent_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.relation_parents )
+    relations : vector< .osm.ParentRange >;
+}
+}
+
+"#;
+pub const RELATION_PARENTS: &str = r#"namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    relation_parents : vector< .osm.ParentRelation >;
+}
+}
+
+"#;
+}
+}
+pub mod tag_elements {
+
+pub const TAG_ELEMENTS: &str = r#"namespace osm {
+struct TagElementRanges
+{
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    @explicit_reference( .osm.TagElementRanges.node_first_idx, .osm.TagElements.nodes )
+    @explicit_reference( .osm.TagElementRanges.way_first_idx, .osm.TagElements.ways )
+    @explicit_reference( .osm.TagElementRanges.relation_first_idx, .osm.TagElements.relations )
+    tags : vector< .osm.TagElementRanges >;
+    nodes : vector< .osm.ElementIndex >;
+    ways : vector< .osm.ElementIndex >;
+    relations : vector< .osm.ElementIndex >;
+    sorted_tags : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const TAGS: &str = r#"namespace osm {
+struct TagElementRanges
+{
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    @explicit_reference( .osm.TagElementRanges.node_first_idx, .osm.TagElements.nodes )
+    @explicit_reference( .osm.TagElementRanges.way_first_idx, .osm.TagElements.ways )
+    @explicit_reference( .osm.TagElementRanges.relation_first_idx, .osm.TagElements.relations )
+    tags : vector< .osm.TagElementRanges >;
+}
+}
+
+"#;
+pub const NODES: &str = r#"namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    nodes : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    ways : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    relations : vector< .osm.ElementIndex >;
+}
+}
+
+"#;
+pub const SORTED_TAGS: &str = r#"namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    sorted_tags : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod osm {
+
+pub const OSM: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Header
+{
+    coord_scale : i32 : 32;
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    writingprogram_idx : u64 : 40;
+    source_idx : u64 : 40;
+    replication_timestamp : i64 : 64;
+    replication_sequence_number : i64 : 64;
+    replication_base_url_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    tag_policy_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Node
+{
+    lat : i32 : 32;
+    lon : i32 : 32;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Way
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+    @range( refs )
+    ref_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Relation
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct NodeMember
+{
+    @optional( .osm.INVALID_IDX )
+    node_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayMember
+{
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationMember
+{
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Tag
+{
+    key_idx : u64 : 40;
+    value_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
 {
     value : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+struct NodeIndex
 {
-    nodes : vector< .osm.Id >;
+    @optional( .osm.INVALID_IDX )
+    value : u64 : 40;
 }
 }
 
-"#;
-pub const WAYS: &str = r#"namespace osm {
+namespace osm {
 struct Id
 {
     value : u64 : 40;
@@ -2270,141 +5820,174 @@ struct Id
 namespace osm {
 archive Ids
 {
+    nodes : vector< .osm.Id >;
     ways : vector< .osm.Id >;
+    relations : vector< .osm.Id >;
 }
 }
 
-"#;
-pub const RELATIONS: &str = r#"namespace osm {
-struct Id
+namespace osm {
+struct IdBlock
 {
-    value : u64 : 40;
+    @range( ids )
+    id_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+struct IdOffset
 {
-    relations : vector< .osm.Id >;
+    value : u32 : 24;
 }
 }
 
-"#;
+namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
 }
 }
-pub mod osm {
 
-pub const OSM: &str = r#"namespace osm {
-struct Header
+namespace osm {
+archive IdIndex
 {
-    coord_scale : i32 : 32;
-    bbox_left : i32 : 32;
-    bbox_right : i32 : 32;
-    bbox_top : i32 : 32;
-    bbox_bottom : i32 : 32;
-    writingprogram_idx : u64 : 40;
-    source_idx : u64 : 40;
-    replication_timestamp : i64 : 64;
-    replication_sequence_number : i64 : 64;
-    replication_base_url_idx : u64 : 40;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.node_ids )
+    node_blocks : vector< .osm.IdBlock >;
+    node_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.way_ids )
+    way_blocks : vector< .osm.IdBlock >;
+    way_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.relation_ids )
+    relation_blocks : vector< .osm.IdBlock >;
+    relation_ids : vector< .osm.IdOffset >;
+    @optional
+    node_order : vector< .osm.ElementIndex >;
+    @optional
+    way_order : vector< .osm.ElementIndex >;
 }
 }
 
 namespace osm {
-struct Node
+struct Info
 {
-    lat : i32 : 32;
-    lon : i32 : 32;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct Way
+archive Infos
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
-    @range( refs )
-    ref_first_idx : u64 : 40;
+    nodes : vector< .osm.Info >;
+    ways : vector< .osm.Info >;
+    relations : vector< .osm.Info >;
 }
 }
 
 namespace osm {
-struct Relation
+struct RTreeEntry
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    @range( children )
+    child_first_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    element_idx : u64 : 40;
 }
 }
 
 namespace osm {
-const u64 INVALID_IDX = 1099511627775;
+archive SpatialIndex
+{
+    nodes : vector< .osm.RTreeEntry >;
+    ways : vector< .osm.RTreeEntry >;
+}
 }
 
 namespace osm {
-struct NodeMember
+struct WayRange
 {
-    @optional( .osm.INVALID_IDX )
-    node_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct WayMember
+struct WayIndex
 {
-    @optional( .osm.INVALID_IDX )
-    way_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
-struct RelationMember
+archive NodeWays
 {
-    @optional( .osm.INVALID_IDX )
-    relation_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @explicit_reference( .osm.WayRange.way_first_idx, .osm.NodeWays.ways_index )
+    nodes : vector< .osm.WayRange >;
+    ways_index : vector< .osm.WayIndex >;
 }
 }
 
 namespace osm {
-struct Tag
+struct ParentRange
 {
-    key_idx : u64 : 40;
-    value_idx : u64 : 40;
+    @range( parents )
+    parent_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct TagIndex
+struct ParentRelation
 {
-    value : u64 : 40;
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct NodeIndex
+archive ParentRelations
 {
-    @optional( .osm.INVALID_IDX )
-    value : u64 : 40;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.node_parents )
+    nodes : vector< .osm.ParentRange >;
+    node_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.way_parents )
+    ways : vector< .osm.ParentRange >;
+    way_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.relation_parents )
+    relations : vector< .osm.ParentRange >;
+    relation_parents : vector< .osm.ParentRelation >;
 }
 }
 
 namespace osm {
-struct Id
+struct TagElementRanges
 {
-    value : u64 : 40;
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+archive TagElements
 {
-    nodes : vector< .osm.Id >;
-    ways : vector< .osm.Id >;
-    relations : vector< .osm.Id >;
+    @explicit_reference( .osm.TagElementRanges.node_first_idx, .osm.TagElements.nodes )
+    @explicit_reference( .osm.TagElementRanges.way_first_idx, .osm.TagElements.ways )
+    @explicit_reference( .osm.TagElementRanges.relation_first_idx, .osm.TagElements.relations )
+    tags : vector< .osm.TagElementRanges >;
+    nodes : vector< .osm.ElementIndex >;
+    ways : vector< .osm.ElementIndex >;
+    relations : vector< .osm.ElementIndex >;
+    sorted_tags : vector< .osm.TagIndex >;
 }
 }
 
@@ -2440,6 +6023,18 @@ archive Osm
     stringtable : raw_data;
     @optional
     ids : archive .osm.Ids;
+    @optional
+    id_index : archive .osm.IdIndex;
+    @optional
+    info : archive .osm.Infos;
+    @optional
+    spatial_index : archive .osm.SpatialIndex;
+    @optional
+    node_ways : archive .osm.NodeWays;
+    @optional
+    parent_relations : archive .osm.ParentRelations;
+    @optional
+    tag_elements : archive .osm.TagElements;
 }
 }
 
@@ -2447,6 +6042,10 @@ archive Osm
 
 pub mod resources {
 pub const HEADER: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
 struct Header
 {
     coord_scale : i32 : 32;
@@ -2459,6 +6058,8 @@ struct Header
     replication_timestamp : i64 : 64;
     replication_sequence_number : i64 : 64;
     replication_base_url_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    tag_policy_idx : u64 : 40;
 }
 }
 
@@ -2661,6 +6262,247 @@ archive Osm
 }
 }
 
+"#;
+pub const ID_INDEX: &str = r#"namespace osm {
+struct IdBlock
+{
+    @range( ids )
+    id_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct IdOffset
+{
+    value : u32 : 24;
+}
+}
+
+namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.node_ids )
+    node_blocks : vector< .osm.IdBlock >;
+    node_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.way_ids )
+    way_blocks : vector< .osm.IdBlock >;
+    way_ids : vector< .osm.IdOffset >;
+    @explicit_reference( .osm.IdBlock.id_first_idx, .osm.IdIndex.relation_ids )
+    relation_blocks : vector< .osm.IdBlock >;
+    relation_ids : vector< .osm.IdOffset >;
+    @optional
+    node_order : vector< .osm.ElementIndex >;
+    @optional
+    way_order : vector< .osm.ElementIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    id_index : archive .osm.IdIndex;
+}
+}
+
+"#;
+pub const INFO: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Info
+{
+    version : i32 : 32;
+    timestamp : i64 : 64;
+    changeset : i64 : 64;
+    uid : i32 : 32;
+    @optional( .osm.INVALID_IDX )
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Infos
+{
+    nodes : vector< .osm.Info >;
+    ways : vector< .osm.Info >;
+    relations : vector< .osm.Info >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    info : archive .osm.Infos;
+}
+}
+
+"#;
+pub const SPATIAL_INDEX: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct RTreeEntry
+{
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    @range( children )
+    child_first_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    element_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.RTreeEntry >;
+    ways : vector< .osm.RTreeEntry >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    spatial_index : archive .osm.SpatialIndex;
+}
+}
+
+"#;
+pub const NODE_WAYS: &str = r#"namespace osm {
+struct WayRange
+{
+    @range( ways )
+    way_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive NodeWays
+{
+    @explicit_reference( .osm.WayRange.way_first_idx, .osm.NodeWays.ways_index )
+    nodes : vector< .osm.WayRange >;
+    ways_index : vector< .osm.WayIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    node_ways : archive .osm.NodeWays;
+}
+}
+
+"#;
+pub const PARENT_RELATIONS: &str = r#"namespace osm {
+struct ParentRange
+{
+    @range( parents )
+    parent_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ParentRelation
+{
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ParentRelations
+{
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.node_parents )
+    nodes : vector< .osm.ParentRange >;
+    node_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.way_parents )
+    ways : vector< .osm.ParentRange >;
+    way_parents : vector< .osm.ParentRelation >;
+    @explicit_reference( .osm.ParentRange.parent_first_idx, .osm.ParentRelations.relation_parents )
+    relations : vector< .osm.ParentRange >;
+    relation_parents : vector< .osm.ParentRelation >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    parent_relations : archive .osm.ParentRelations;
+}
+}
+
+"#;
+pub const TAG_ELEMENTS: &str = r#"namespace osm {
+struct TagElementRanges
+{
+    @range( nodes )
+    node_first_idx : u64 : 40;
+    @range( ways )
+    way_first_idx : u64 : 40;
+    @range( relations )
+    relation_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ElementIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive TagElements
+{
+    @explicit_reference( .osm.TagElementRanges.node_first_idx, .osm.TagElements.nodes )
+    @explicit_reference( .osm.TagElementRanges.way_first_idx, .osm.TagElements.ways )
+    @explicit_reference( .osm.TagElementRanges.relation_first_idx, .osm.TagElements.relations )
+    tags : vector< .osm.TagElementRanges >;
+    nodes : vector< .osm.ElementIndex >;
+    ways : vector< .osm.ElementIndex >;
+    relations : vector< .osm.ElementIndex >;
+    sorted_tags : vector< .osm.TagIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    tag_elements : archive .osm.TagElements;
+}
+}
+
 "#;
 }
 }
@@ -0,0 +1,72 @@
+//! Reverse lookups from elements to the elements referencing them.
+//!
+//! The lookups require optional sub-archives compiled by `osmflatc`. All
+//! functions return `None` if the corresponding sub-archive is missing.
+
+use crate::{Osm, ParentRange, ParentRelation};
+
+/// Returns an iterator over indexes of ways in the `ways` vector which
+/// reference the node at index `idx` in the `nodes` vector.
+///
+/// The ways are returned in ascending order. Requires an archive compiled
+/// with `osmflatc --node-ways`.
+#[inline]
+pub fn ways_of_node(archive: &Osm, idx: usize) -> Option<impl Iterator<Item = usize> + '_> {
+    let node_ways = archive.node_ways()?;
+    let ways_index = node_ways.ways_index();
+    let range = node_ways.nodes()[idx].ways();
+    Some(range.map(move |idx| ways_index[idx as usize].value() as usize))
+}
+
+/// Returns an iterator over the relations having the node at index `idx` in
+/// the `nodes` vector as a member.
+///
+/// Requires an archive compiled with `osmflatc --parent-relations`.
+#[inline]
+pub fn parents_of_node(archive: &Osm, idx: usize) -> Option<impl Iterator<Item = &ParentRelation>> {
+    let parent_relations = archive.parent_relations()?;
+    Some(parents(
+        parent_relations.nodes(),
+        parent_relations.node_parents(),
+        idx,
+    ))
+}
+
+/// Returns an iterator over the relations having the way at index `idx` in
+/// the `ways` vector as a member.
+///
+/// Requires an archive compiled with `osmflatc --parent-relations`.
+#[inline]
+pub fn parents_of_way(archive: &Osm, idx: usize) -> Option<impl Iterator<Item = &ParentRelation>> {
+    let parent_relations = archive.parent_relations()?;
+    Some(parents(
+        parent_relations.ways(),
+        parent_relations.way_parents(),
+        idx,
+    ))
+}
+
+/// Returns an iterator over the relations having the relation at index `idx`
+/// in the `relations` vector as a member.
+///
+/// Requires an archive compiled with `osmflatc --parent-relations`.
+#[inline]
+pub fn parents_of_relation(
+    archive: &Osm,
+    idx: usize,
+) -> Option<impl Iterator<Item = &ParentRelation>> {
+    let parent_relations = archive.parent_relations()?;
+    Some(parents(
+        parent_relations.relations(),
+        parent_relations.relation_parents(),
+        idx,
+    ))
+}
+
+fn parents<'a>(
+    ranges: &'a [ParentRange],
+    parents: &'a [ParentRelation],
+    idx: usize,
+) -> impl Iterator<Item = &'a ParentRelation> {
+    ranges[idx].parents().map(move |idx| &parents[idx as usize])
+}
@@ -0,0 +1,113 @@
+//! Spatial queries on the optional `spatial_index` sub-archive (compiled with
+//! `osmflatc --spatial-index`).
+//!
+//! Only the entries of the R-trees which intersect the query box are visited,
+//! therefore only the corresponding pages of the memory mapped archive are
+//! touched.
+
+use crate::{Osm, RTreeEntry};
+use std::ops::Range;
+
+/// Geographic bounding box in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoundingBox {
+    /// Min longitude
+    pub left: f64,
+    /// Max longitude
+    pub right: f64,
+    /// Max latitude
+    pub top: f64,
+    /// Min latitude
+    pub bottom: f64,
+}
+
+/// Returns an iterator over indexes of nodes in the `nodes` vector inside
+/// `bbox`.
+///
+/// Returns `None` if the archive does not contain a spatial index.
+pub fn nodes_in_bbox<'a>(archive: &'a Osm, bbox: &BoundingBox) -> Option<RTreeQuery<'a>> {
+    let entries = archive.spatial_index()?.nodes();
+    Some(query(archive, entries, bbox))
+}
+
+/// Returns an iterator over indexes of ways in the `ways` vector whose
+/// bounding box intersects `bbox`.
+///
+/// Returns `None` if the archive does not contain a spatial index.
+pub fn ways_in_bbox<'a>(archive: &'a Osm, bbox: &BoundingBox) -> Option<RTreeQuery<'a>> {
+    let entries = archive.spatial_index()?.ways();
+    Some(query(archive, entries, bbox))
+}
+
+fn query<'a>(archive: &Osm, entries: &'a [RTreeEntry], bbox: &BoundingBox) -> RTreeQuery<'a> {
+    let scale = f64::from(archive.header().coord_scale());
+    RTreeQuery::new(
+        entries,
+        (bbox.left * scale).floor() as i32,
+        (bbox.right * scale).ceil() as i32,
+        (bbox.top * scale).ceil() as i32,
+        (bbox.bottom * scale).floor() as i32,
+    )
+}
+
+/// Iterator over the elements of an R-tree whose bounding box intersects a
+/// query box.
+///
+/// The elements are returned in no particular order.
+#[derive(Debug, Clone)]
+pub struct RTreeQuery<'a> {
+    entries: &'a [RTreeEntry],
+    bbox: (i32, i32, i32, i32),
+    stack: Vec<Range<u64>>,
+}
+
+impl<'a> RTreeQuery<'a> {
+    /// Creates a query of `entries` with a bounding box in coordinates scaled
+    /// with `header.coord_scale`.
+    pub fn new(entries: &'a [RTreeEntry], left: i32, right: i32, top: i32, bottom: i32) -> Self {
+        // the first entry is the root
+        let stack = if entries.is_empty() {
+            Vec::new()
+        } else {
+            vec![0..1]
+        };
+        Self {
+            entries,
+            bbox: (left, right, top, bottom),
+            stack,
+        }
+    }
+
+    fn intersects(&self, entry: &RTreeEntry) -> bool {
+        let (left, right, top, bottom) = self.bbox;
+        entry.bbox_left() <= right
+            && left <= entry.bbox_right()
+            && entry.bbox_bottom() <= top
+            && bottom <= entry.bbox_top()
+    }
+}
+
+impl<'a> Iterator for RTreeQuery<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some(range) = self.stack.last_mut() {
+            let idx = match range.next() {
+                Some(idx) => idx,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let entry = &self.entries[idx as usize];
+            if !self.intersects(entry) {
+                continue;
+            }
+            match entry.element_idx() {
+                Some(element_idx) => return Some(element_idx as usize),
+                None => self.stack.push(entry.children()),
+            }
+        }
+        None
+    }
+}
@@ -2,7 +2,9 @@
 //! It is easy to combine these with `std::str::from_utf8` family of functions,
 //! to lift them to operate on `str`.
 
-use crate::Osm;
+use crate::{ElementIndex, Osm, TagElements, TagIndex};
+
+use std::cmp::Ordering;
 use std::ops::Range;
 
 /// Returns an iterator over tags specified by `range`.
@@ -82,3 +84,135 @@ pub fn has_tag(archive: &Osm, range: Range<u64>, key: &[u8], value: &[u8]) -> bo
     }
     false
 }
+
+/// Indexes of elements having a tag.
+///
+/// Each slice is sorted ascending.
+#[derive(Debug, Clone, Copy)]
+pub struct TaggedElements<'a> {
+    /// Indexes in the `nodes` vector.
+    pub nodes: &'a [ElementIndex],
+    /// Indexes in the `ways` vector.
+    pub ways: &'a [ElementIndex],
+    /// Indexes in the `relations` vector.
+    pub relations: &'a [ElementIndex],
+}
+
+/// Finds all elements having the tag `key=value`.
+///
+/// Requires an archive compiled with `osmflatc --tag-elements`, otherwise
+/// `None` is returned.
+pub fn find_elements_by_tag<'a>(
+    archive: &'a Osm,
+    key: &[u8],
+    value: &[u8],
+) -> Option<TaggedElements<'a>> {
+    let index = archive.tag_elements()?;
+    let sorted_tags = find_sorted_tags(archive, index, |k, v| (k, v).cmp(&(key, value)));
+    debug_assert!(sorted_tags.len() <= 1, "sorted tags are not distinct");
+    Some(match sorted_tags.first() {
+        Some(tag) => tagged_elements(index, tag.value()),
+        None => TaggedElements {
+            nodes: &[],
+            ways: &[],
+            relations: &[],
+        },
+    })
+}
+
+/// Finds all elements having a tag with the given `key`.
+///
+/// Returns an iterator over the values of the key, each with the elements
+/// having the corresponding tag. The values are distinct and sorted ascending.
+///
+/// Requires an archive compiled with `osmflatc --tag-elements`, otherwise
+/// `None` is returned.
+pub fn find_elements_by_key<'a>(
+    archive: &'a Osm,
+    key: &[u8],
+) -> Option<impl Iterator<Item = (&'a [u8], TaggedElements<'a>)> + Clone> {
+    let index = archive.tag_elements()?;
+    let tags = archive.tags();
+    let strings = archive.stringtable();
+    let sorted_tags = find_sorted_tags(archive, index, |k, _| k.cmp(key));
+    Some(sorted_tags.iter().map(move |tag_idx| {
+        let tag = &tags[tag_idx.value() as usize];
+        let value = strings.substring_raw(tag.value_idx() as usize);
+        (value, tagged_elements(index, tag_idx.value()))
+    }))
+}
+
+/// Finds the range of tags in the sorted tags for which `cmp` returns `Equal`.
+///
+/// `cmp` is called with key and value of a tag, and must be consistent with
+/// the order of sorted tags.
+fn find_sorted_tags<'a>(
+    archive: &Osm,
+    index: &'a TagElements,
+    cmp: impl Fn(&[u8], &[u8]) -> Ordering,
+) -> &'a [TagIndex] {
+    let tags = archive.tags();
+    let strings = archive.stringtable();
+    let cmp = |tag_idx: &TagIndex| {
+        let tag = &tags[tag_idx.value() as usize];
+        cmp(
+            strings.substring_raw(tag.key_idx() as usize),
+            strings.substring_raw(tag.value_idx() as usize),
+        )
+    };
+
+    let sorted_tags = index.sorted_tags();
+    let start = sorted_tags.partition_point(|tag_idx| cmp(tag_idx) == Ordering::Less);
+    let len = sorted_tags[start..].partition_point(|tag_idx| cmp(tag_idx) == Ordering::Equal);
+    &sorted_tags[start..start + len]
+}
+
+fn tagged_elements(index: &TagElements, tag_idx: u64) -> TaggedElements {
+    let ranges = &index.tags()[tag_idx as usize];
+    fn slice(elements: &[ElementIndex], range: Range<u64>) -> &[ElementIndex] {
+        &elements[range.start as usize..range.end as usize]
+    }
+    TaggedElements {
+        nodes: slice(index.nodes(), ranges.nodes()),
+        ways: slice(index.ways(), ranges.ways()),
+        relations: slice(index.relations(), ranges.relations()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::TestArchive;
+
+    #[test]
+    fn test_tags() {
+        let archive = TestArchive::default()
+            .node((0, 0), &[("amenity", "pub"), ("name", "The Anchor")])
+            .node((0, 0), &[("name", "x"), ("name:en", "y")])
+            .build();
+        let nodes = archive.nodes();
+
+        let tags: Vec<_> = iter_tags(&archive, nodes[0].tags()).collect();
+        assert_eq!(
+            tags,
+            vec![
+                (&b"amenity"[..], &b"pub"[..]),
+                (&b"name"[..], &b"The Anchor"[..])
+            ]
+        );
+        assert_eq!(
+            find_tag(&archive, nodes[0].tags(), b"name"),
+            Some(&b"The Anchor"[..])
+        );
+        assert_eq!(find_tag(&archive, nodes[0].tags(), b"nam"), None);
+        assert_eq!(
+            find_tag(&archive, nodes[1].tags(), b"name:en"),
+            Some(&b"y"[..])
+        );
+        assert!(has_tag(&archive, nodes[0].tags(), b"amenity", b"pub"));
+        assert!(!has_tag(&archive, nodes[0].tags(), b"amenity", b"pu"));
+        assert!(!has_tag(&archive, nodes[1].tags(), b"amenity", b"pub"));
+        // no tag elements index
+        assert!(find_elements_by_tag(&archive, b"amenity", b"pub").is_none());
+    }
+}
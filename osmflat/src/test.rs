@@ -0,0 +1,182 @@
+//! In-memory archives for unit tests.
+
+use crate::{Header, NodeMember, Osm, OsmBuilder, RelationMember, Tag, TagIndex, WayMember};
+
+use flatdata::MemoryResourceStorage;
+use std::collections::HashMap;
+
+type Tags = Vec<(&'static str, &'static str)>;
+
+/// Member of a relation by the index of the element and the role.
+///
+/// Indexes beyond the elements of the archive are stored as missing.
+#[derive(Debug, Clone, Copy)]
+pub enum Member {
+    Node(u64, &'static str),
+    Way(u64, &'static str),
+    Relation(u64, &'static str),
+}
+
+/// Builder of a minimal archive in memory.
+///
+/// Coordinates are stored with a `coord_scale` of 1, i.e. as whole degrees.
+#[derive(Debug, Default)]
+pub struct TestArchive {
+    nodes: Vec<((i32, i32), Tags)>,
+    ways: Vec<(Vec<u64>, Tags)>,
+    relations: Vec<(Vec<Member>, Tags)>,
+}
+
+impl TestArchive {
+    /// Adds a node at (latitude, longitude).
+    pub fn node(mut self, lat_lon: (i32, i32), tags: &[(&'static str, &'static str)]) -> Self {
+        self.nodes.push((lat_lon, tags.to_vec()));
+        self
+    }
+
+    /// Adds a way with the given node indexes. Indexes beyond the nodes of
+    /// the archive are stored as missing.
+    pub fn way(mut self, nodes: &[u64], tags: &[(&'static str, &'static str)]) -> Self {
+        self.ways.push((nodes.to_vec(), tags.to_vec()));
+        self
+    }
+
+    /// Adds a relation with the given members.
+    pub fn relation(mut self, members: &[Member], tags: &[(&'static str, &'static str)]) -> Self {
+        self.relations.push((members.to_vec(), tags.to_vec()));
+        self
+    }
+
+    pub fn build(self) -> Osm {
+        let storage = MemoryResourceStorage::new("/root/osm");
+        let builder = OsmBuilder::new(storage.clone()).unwrap();
+
+        let mut strings = StringTable::default();
+        let mut header = Header::new();
+        header.set_coord_scale(1);
+        header.set_tag_policy_idx(None);
+        builder.set_header(&header).unwrap();
+
+        let mut tags = Vec::new();
+        let mut add_tags = |strings: &mut StringTable, element_tags: &Tags| {
+            let first_idx = tags.len() as u64;
+            for (key, value) in element_tags {
+                let mut tag = Tag::new();
+                tag.set_key_idx(strings.insert(key));
+                tag.set_value_idx(strings.insert(value));
+                tags.push(tag);
+            }
+            first_idx
+        };
+
+        let mut nodes = builder.start_nodes().unwrap();
+        for ((lat, lon), node_tags) in &self.nodes {
+            let tag_first_idx = add_tags(&mut strings, node_tags);
+            let node = nodes.grow().unwrap();
+            node.set_lat(*lat);
+            node.set_lon(*lon);
+            node.set_tag_first_idx(tag_first_idx);
+        }
+        let tag_first_idx = add_tags(&mut strings, &Tags::new());
+        nodes.grow().unwrap().set_tag_first_idx(tag_first_idx);
+        nodes.close().unwrap();
+
+        let num_nodes = self.nodes.len() as u64;
+        let mut ways = builder.start_ways().unwrap();
+        let mut nodes_index = builder.start_nodes_index().unwrap();
+        let mut num_refs = 0;
+        for (way_nodes, way_tags) in &self.ways {
+            let tag_first_idx = add_tags(&mut strings, way_tags);
+            let way = ways.grow().unwrap();
+            way.set_tag_first_idx(tag_first_idx);
+            way.set_ref_first_idx(num_refs);
+            for &idx in way_nodes {
+                nodes_index
+                    .grow()
+                    .unwrap()
+                    .set_value(Some(idx).filter(|&idx| idx < num_nodes));
+                num_refs += 1;
+            }
+        }
+        let tag_first_idx = add_tags(&mut strings, &Tags::new());
+        let sentinel = ways.grow().unwrap();
+        sentinel.set_tag_first_idx(tag_first_idx);
+        sentinel.set_ref_first_idx(num_refs);
+        ways.close().unwrap();
+        nodes_index.close().unwrap();
+
+        let num_ways = self.ways.len() as u64;
+        let num_relations = self.relations.len() as u64;
+        let mut relations = builder.start_relations().unwrap();
+        let mut relation_members = builder.start_relation_members().unwrap();
+        for (members, relation_tags) in &self.relations {
+            let tag_first_idx = add_tags(&mut strings, relation_tags);
+            relations.grow().unwrap().set_tag_first_idx(tag_first_idx);
+            let mut new_members = relation_members.grow().unwrap();
+            for &member in members {
+                match member {
+                    Member::Node(idx, role) => {
+                        let m: &mut NodeMember = new_members.add_node_member();
+                        m.set_node_idx(Some(idx).filter(|&idx| idx < num_nodes));
+                        m.set_role_idx(strings.insert(role));
+                    }
+                    Member::Way(idx, role) => {
+                        let m: &mut WayMember = new_members.add_way_member();
+                        m.set_way_idx(Some(idx).filter(|&idx| idx < num_ways));
+                        m.set_role_idx(strings.insert(role));
+                    }
+                    Member::Relation(idx, role) => {
+                        let m: &mut RelationMember = new_members.add_relation_member();
+                        m.set_relation_idx(Some(idx).filter(|&idx| idx < num_relations));
+                        m.set_role_idx(strings.insert(role));
+                    }
+                }
+            }
+        }
+        let tag_first_idx = add_tags(&mut strings, &Tags::new());
+        relations.grow().unwrap().set_tag_first_idx(tag_first_idx);
+        relations.close().unwrap();
+        relation_members.close().unwrap();
+
+        let tags_index: Vec<_> = (0..tags.len() as u64)
+            .map(|idx| {
+                let mut tag_index = TagIndex::new();
+                tag_index.set_value(idx);
+                tag_index
+            })
+            .collect();
+        builder.set_tags(&tags).unwrap();
+        builder.set_tags_index(&tags_index).unwrap();
+        builder.set_stringtable(&strings.data).unwrap();
+
+        std::mem::drop(builder);
+        Osm::open(storage).unwrap()
+    }
+}
+
+/// Zero terminated strings, starting with the empty string.
+struct StringTable {
+    data: Vec<u8>,
+    indexes: HashMap<&'static str, u64>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self {
+            data: vec![0],
+            indexes: std::iter::once(("", 0)).collect(),
+        }
+    }
+}
+
+impl StringTable {
+    fn insert(&mut self, s: &'static str) -> u64 {
+        let data = &mut self.data;
+        *self.indexes.entry(s).or_insert_with(|| {
+            let idx = data.len() as u64;
+            data.extend_from_slice(s.as_bytes());
+            data.push(0);
+            idx
+        })
+    }
+}
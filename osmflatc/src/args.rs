@@ -8,13 +8,122 @@ pub struct Args {
     #[clap(short, long, parse(from_occurrences))]
     pub verbose: u8,
 
-    /// Input OSM pbf file
-    pub input: PathBuf,
+    /// Input OSM pbf file, or OSM xml file (optionally compressed with gzip or bzip2),
+    /// or osmflat archive when applying changes. Use `-` to read from stdin. Multiple inputs
+    /// are merged, elements contained in several inputs are deduplicated by their ids keeping
+    /// the highest version.
+    #[clap(required = true)]
+    pub inputs: Vec<PathBuf>,
 
     /// Output directory for OSM flatdata archive
     pub output: PathBuf,
 
+    /// OSM change file (.osc, optionally compressed with gzip or bzip2) to apply to the input
+    /// archive, which must be compiled with `--ids`. Can be given multiple times, the changes
+    /// are applied in the given order.
+    #[structopt(long = "changes")]
+    pub changes: Vec<PathBuf>,
+
+    /// Replication state file (state.txt) of the last applied change file, used to update
+    /// the replication fields of the header
+    #[structopt(long = "state", requires = "changes")]
+    pub state: Option<PathBuf>,
+
+    /// Only compile the elements inside the bounding box given as `left,bottom,right,top` in
+    /// degrees
+    #[structopt(long = "bbox", conflicts_with = "polygon")]
+    pub bbox: Option<String>,
+
+    /// Only compile the elements inside the polygon given as osmosis polygon file (.poly) or
+    /// GeoJSON file
+    #[structopt(long = "polygon")]
+    pub polygon: Option<PathBuf>,
+
+    /// When compiling only the elements inside an area, whether to include all nodes of the
+    /// ways inside the area
+    #[structopt(long = "complete-ways")]
+    pub complete_ways: bool,
+
+    /// Only compile the elements matching the tag filter expression, e.g.
+    /// `w/highway n/amenity=pub r/type=route`. Can be given multiple times.
+    #[structopt(long = "filter")]
+    pub filter: Vec<String>,
+
+    /// When filtering elements by tags, whether to include the untagged or non-matching
+    /// nodes of the matching ways
+    #[structopt(long = "keep-way-nodes")]
+    pub keep_way_nodes: bool,
+
+    /// Only compile the tags with keys matching the comma separated patterns, e.g.
+    /// `highway,name*`. Can be given multiple times.
+    #[structopt(long = "keep-tags")]
+    pub keep_tags: Vec<String>,
+
+    /// Do not compile the tags with keys matching the comma separated patterns, e.g.
+    /// `created_by,source:*,note,fixme`. Can be given multiple times.
+    #[structopt(long = "drop-tags")]
+    pub drop_tags: Vec<String>,
+
+    /// JSON file with lists `keep` and `drop` of key patterns of tags to compile resp. not to
+    /// compile, in addition to `--keep-tags` and `--drop-tags`
+    #[structopt(long = "tag-policy")]
+    pub tag_policy: Option<PathBuf>,
+
+    /// Abort on corrupt blocks of the input (default)
+    #[structopt(long = "strict", conflicts_with = "lenient")]
+    pub strict: bool,
+
+    /// Skip corrupt blocks of the input instead of aborting, the number of skipped blocks is
+    /// reported in the statistics
+    #[structopt(long = "lenient")]
+    pub lenient: bool,
+
+    /// Sort the input by type and id with an external merge sort before compiling it. Without
+    /// this option, an input with unsorted ids is an error.
+    #[structopt(long = "sort")]
+    pub sort: bool,
+
+    /// Limit the memory used by the stringtable to about this many MiB, at least 16. Beyond, the
+    /// strings are spilled to a temporary file next to the output archive, at the cost of less
+    /// deduplicated strings.
+    #[structopt(long = "max-memory")]
+    pub max_memory: Option<usize>,
+
+    /// Order nodes, and ways by their centroids, along a Hilbert curve instead of by their ids, so
+    /// that spatially close elements are stored close together.
+    #[structopt(long = "spatial-order")]
+    pub spatial_order: bool,
+
+    /// Order the strings of the stringtable by their number of uses, so that the most common
+    /// keys, values and roles are stored at its beginning.
+    #[structopt(long = "sort-strings")]
+    pub sort_strings: bool,
+
     /// Whether to compile the optional ids subs
     #[structopt(long = "ids")]
     pub ids: bool,
+
+    /// Whether to compile the optional index from OSM ids to elements
+    #[structopt(long = "id-index")]
+    pub id_index: bool,
+
+    /// Whether to compile the optional info sub-archive with metadata of elements
+    #[structopt(long = "info")]
+    pub info: bool,
+
+    /// Whether to compile the optional spatial index of nodes and ways
+    #[structopt(long = "spatial-index")]
+    pub spatial_index: bool,
+
+    /// Whether to compile the optional index from nodes to the ways referencing them
+    #[structopt(long = "node-ways")]
+    pub node_ways: bool,
+
+    /// Whether to compile the optional index from elements to their parent relations
+    #[structopt(long = "parent-relations")]
+    pub parent_relations: bool,
+
+    /// Whether to compile the optional inverted index from tags to elements
+    #[structopt(long = "tag-elements")]
+    pub tag_elements: bool,
 }
@@ -0,0 +1,112 @@
+use osmflatc::{Area, Error, ExportFormat, Exporter, PbfWriter, TagFilter};
+
+use clap::Parser;
+use colored::*;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Exporter of an osmflat archive to GeoJSON or OSM PBF
+#[derive(Debug, clap::Parser)]
+#[clap(version, author)]
+struct Args {
+    /// Verbose mode (-v, -vv, -vvv, etc.)
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Input osmflat archive
+    input: PathBuf,
+
+    /// Output GeoJSON or PBF file. Use `-` to write to stdout.
+    output: PathBuf,
+
+    /// Write the whole archive as OSM PBF instead of GeoJSON. Ids are synthesized if the
+    /// archive was compiled without `--ids`.
+    #[clap(
+        long = "pbf",
+        conflicts_with_all = &["seq", "bbox", "polygon", "filter", "ids"]
+    )]
+    pbf: bool,
+
+    /// Write newline-delimited GeoJSON (GeoJSONSeq) with one feature per line instead of a
+    /// single feature collection
+    #[clap(long = "seq")]
+    seq: bool,
+
+    /// Only export the elements with any of their nodes inside the bounding box given as
+    /// `left,bottom,right,top` in degrees
+    #[clap(long = "bbox", conflicts_with = "polygon")]
+    bbox: Option<String>,
+
+    /// Only export the elements with any of their nodes inside the polygon given as osmosis
+    /// polygon file (.poly) or GeoJSON file
+    #[clap(long = "polygon")]
+    polygon: Option<PathBuf>,
+
+    /// Only export the elements matching the tag filter expression, e.g.
+    /// `w/building n/amenity=pub`. Can be given multiple times.
+    #[clap(long = "filter")]
+    filter: Vec<String>,
+
+    /// Set the ids of the features to the OSM ids of the elements, e.g. `w42`. Requires an
+    /// archive compiled with `--ids`.
+    #[clap(long = "ids")]
+    ids: bool,
+}
+
+fn create_output(path: &Path) -> io::Result<BufWriter<Box<dyn Write>>> {
+    let output: Box<dyn Write> = if path.as_os_str() == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(File::create(path)?)
+    };
+    Ok(BufWriter::new(output))
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    if args.pbf {
+        return PbfWriter::new(args.input).write(create_output(&args.output)?);
+    }
+
+    let format = if args.seq {
+        ExportFormat::GeoJsonSeq
+    } else {
+        ExportFormat::GeoJson
+    };
+    let mut exporter = Exporter::new(args.input)
+        .with_format(format)
+        .with_ids(args.ids);
+    match (&args.bbox, &args.polygon) {
+        (Some(bbox), _) => exporter = exporter.with_area(Area::from_bbox(bbox)?),
+        (None, Some(path)) => exporter = exporter.with_area(Area::from_polygon_file(path)?),
+        (None, None) => (),
+    }
+    if !args.filter.is_empty() {
+        let filter = TagFilter::parse(args.filter.iter().map(String::as_str))?;
+        exporter = exporter.with_filter(filter);
+    }
+
+    let stats = exporter.export(create_output(&args.output)?)?;
+    // stdout might be the output
+    eprintln!("{}", stats);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    let level = match args.verbose {
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level))
+        .format_module_path(false)
+        .format_timestamp_nanos()
+        .init();
+
+    if let Err(e) = run(args) {
+        eprintln!("{}: {}", "Error".red(), e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,2209 @@
+//! Compilation of OSM data to an osmflat archive.
+
+use crate::extract::{self, Area};
+use crate::filter::{TagFilter, TagPolicy};
+use crate::ids;
+use crate::merge;
+use crate::osmchange;
+use crate::osmpbf::{self, build_block_index, read_block, BlockIndex, BlockType, PbfError};
+use crate::osmxml;
+use crate::parallel;
+use crate::reverse;
+use crate::spatial;
+use crate::stats::Stats;
+use crate::strings::{self, StringTable};
+use crate::Error;
+
+use ahash::AHashMap;
+use flatdata::FileResourceStorage;
+use log::{info, warn};
+use memmap2::Mmap;
+
+use std::collections::hash_map;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+
+/// Max number of elements sorted in memory at once when sorting the input
+const SORT_RUN_LEN: usize = 4_000_000;
+
+/// Progress of a step of the compilation, reported to the progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Description of the step, e.g. `Converting nodes...`
+    pub message: &'a str,
+    /// Number of processed blocks of the step
+    pub done: u64,
+    /// Total number of blocks of the step
+    pub total: u64,
+}
+
+type ProgressCallback = dyn Fn(Progress) + Send + Sync;
+
+/// Reports the progress of a step to the optional progress callback.
+struct ProgressReporter<'a> {
+    callback: Option<&'a ProgressCallback>,
+    message: &'static str,
+    done: u64,
+    total: u64,
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(callback: Option<&'a ProgressCallback>, message: &'static str, total: u64) -> Self {
+        let reporter = Self {
+            callback,
+            message,
+            done: 0,
+            total,
+        };
+        reporter.report();
+        reporter
+    }
+
+    fn inc(&mut self) {
+        self.done += 1;
+        self.report();
+    }
+
+    fn report(&self) {
+        if let Some(callback) = self.callback {
+            callback(Progress {
+                message: self.message,
+                done: self.done,
+                total: self.total,
+            });
+        }
+    }
+}
+
+/// Compiler of OSM data to an osmflat archive.
+///
+/// The input is an OSM pbf file, an OSM xml file (optionally compressed with
+/// gzip or bzip2), or an osmflat archive when applying changes. The input
+/// path `-` reads a pbf or xml input from stdin. The optional parts of the
+/// archive and the selection of the compiled data are configured with the
+/// builder methods:
+///
+/// ```no_run
+/// # fn main() -> Result<(), osmflatc::Error> {
+/// let stats = osmflatc::Compiler::new("input.osm.pbf")
+///     .with_ids(true)
+///     .progress(|progress| println!("{} {}/{}", progress.message, progress.done, progress.total))
+///     .compile("output.osm.flatdata")?;
+/// println!("{}", stats);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Compiler {
+    input: PathBuf,
+    inputs: Vec<PathBuf>,
+    changes: Vec<PathBuf>,
+    state: Option<PathBuf>,
+    area: Option<Area>,
+    complete_ways: bool,
+    filter: Option<TagFilter>,
+    keep_way_nodes: bool,
+    tag_policy: Option<TagPolicy>,
+    ids: bool,
+    id_index: bool,
+    info: bool,
+    spatial_index: bool,
+    node_ways: bool,
+    parent_relations: bool,
+    tag_elements: bool,
+    lenient: bool,
+    sort: bool,
+    max_memory: Option<usize>,
+    spatial_order: bool,
+    sort_strings: bool,
+    progress: Option<Box<ProgressCallback>>,
+}
+
+impl Compiler {
+    /// Creates a compiler of the input file, which compiles all elements and
+    /// tags without any optional parts of the archive.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            inputs: Vec::new(),
+            changes: Vec::new(),
+            state: None,
+            area: None,
+            complete_ways: false,
+            filter: None,
+            keep_way_nodes: false,
+            tag_policy: None,
+            ids: false,
+            id_index: false,
+            info: false,
+            spatial_index: false,
+            node_ways: false,
+            parent_relations: false,
+            tag_elements: false,
+            lenient: false,
+            sort: false,
+            max_memory: None,
+            spatial_order: false,
+            sort_strings: false,
+            progress: None,
+        }
+    }
+
+    /// Merges further inputs with the input. Elements contained in several
+    /// inputs are deduplicated by their ids, keeping the highest version.
+    pub fn with_inputs(mut self, inputs: Vec<PathBuf>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Applies the OSM change files (.osc, optionally compressed with gzip or
+    /// bzip2) in the given order to the input archive, which must be compiled
+    /// with ids.
+    pub fn with_changes(mut self, changes: Vec<PathBuf>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// Updates the replication fields of the header from the replication
+    /// state file (state.txt) of the last applied change file.
+    pub fn with_state(mut self, state: impl Into<PathBuf>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Only compiles the elements inside the area.
+    pub fn with_area(mut self, area: Area) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    /// Whether to compile all nodes of the ways inside the area.
+    pub fn with_complete_ways(mut self, complete_ways: bool) -> Self {
+        self.complete_ways = complete_ways;
+        self
+    }
+
+    /// Only compiles the elements matching the tag filter.
+    pub fn with_filter(mut self, filter: TagFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Whether to compile the nodes of the ways matching the tag filter.
+    pub fn with_keep_way_nodes(mut self, keep_way_nodes: bool) -> Self {
+        self.keep_way_nodes = keep_way_nodes;
+        self
+    }
+
+    /// Only compiles the tags kept by the policy.
+    pub fn with_tag_policy(mut self, tag_policy: TagPolicy) -> Self {
+        self.tag_policy = Some(tag_policy).filter(|policy| !policy.is_empty());
+        self
+    }
+
+    /// Whether to compile the optional ids sub-archive.
+    pub fn with_ids(mut self, ids: bool) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Whether to compile the optional index from OSM ids to elements.
+    pub fn with_id_index(mut self, id_index: bool) -> Self {
+        self.id_index = id_index;
+        self
+    }
+
+    /// Whether to compile the optional info sub-archive with metadata of
+    /// elements.
+    pub fn with_info(mut self, info: bool) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Whether to compile the optional spatial index of nodes and ways.
+    pub fn with_spatial_index(mut self, spatial_index: bool) -> Self {
+        self.spatial_index = spatial_index;
+        self
+    }
+
+    /// Whether to compile the optional index from nodes to the ways
+    /// referencing them.
+    pub fn with_node_ways(mut self, node_ways: bool) -> Self {
+        self.node_ways = node_ways;
+        self
+    }
+
+    /// Whether to compile the optional index from elements to their parent
+    /// relations.
+    pub fn with_parent_relations(mut self, parent_relations: bool) -> Self {
+        self.parent_relations = parent_relations;
+        self
+    }
+
+    /// Whether to compile the optional inverted index from tags to elements.
+    pub fn with_tag_elements(mut self, tag_elements: bool) -> Self {
+        self.tag_elements = tag_elements;
+        self
+    }
+
+    /// Whether to skip corrupt blocks of the input instead of failing. The
+    /// number of skipped blocks is reported in the stats.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Whether to sort the input by type and id before compiling it, which
+    /// is required for inputs with unsorted ids. Otherwise, unsorted inputs
+    /// are an error.
+    pub fn with_sort(mut self, sort: bool) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Limits the memory used by the stringtable to about `max_memory` bytes,
+    /// at least `strings::MIN_MAX_MEMORY`. Beyond, the strings are spilled to
+    /// a temporary file next to the output archive, and only strings inserted
+    /// since the last spill, or used repeatedly before, are deduplicated.
+    pub fn with_max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    /// Orders the nodes, and the ways by their centroids, along a Hilbert
+    /// curve, so that spatially close elements are stored close together.
+    /// Otherwise, the elements are ordered by their ids. This needs an
+    /// additional pass over the compiled archive.
+    pub fn with_spatial_order(mut self, spatial_order: bool) -> Self {
+        self.spatial_order = spatial_order;
+        self
+    }
+
+    /// Orders the strings of the stringtable by their number of uses, so that
+    /// the most used keys, values and roles are stored close together at its
+    /// beginning. This needs an additional pass over the compiled archive.
+    pub fn with_sort_strings(mut self, sort_strings: bool) -> Self {
+        self.sort_strings = sort_strings;
+        self
+    }
+
+    /// Sets a callback, which is called with the progress of the steps of
+    /// the compilation.
+    pub fn progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Compiles the input to an osmflat archive in the output directory.
+    pub fn compile(&self, output: impl AsRef<Path>) -> Result<Stats, Error> {
+        let output = output.as_ref();
+        let progress = self.progress.as_deref();
+        let updated_pbf = if self.changes.is_empty() {
+            None
+        } else {
+            Some(self.apply_changes(output)?)
+        };
+        let stdin = Path::new("-");
+        let inputs: Vec<&Path> = std::iter::once(&self.input)
+            .chain(&self.inputs)
+            .map(PathBuf::as_path)
+            .collect();
+        let spooled_input = if inputs.contains(&stdin) && updated_pbf.is_none() {
+            Some(spool_stdin(output)?)
+        } else {
+            None
+        };
+        let inputs: Vec<&Path> = inputs
+            .into_iter()
+            .map(|path| match &spooled_input {
+                Some(spooled_input) if path == stdin => spooled_input.as_path(),
+                _ => path,
+            })
+            .collect();
+
+        let mut stats = Stats::default();
+        let merged_pbf = if updated_pbf.is_some() {
+            None
+        } else if self.sort {
+            Some(self.sort_inputs(&inputs, output, &mut stats)?)
+        } else if inputs.len() > 1 {
+            Some(self.merge_inputs(&inputs, output, &mut stats)?)
+        } else {
+            None
+        };
+        let input_path = updated_pbf
+            .as_deref()
+            .or(merged_pbf.as_deref())
+            .unwrap_or(inputs[0]);
+
+        let input_file = File::open(input_path)?;
+        let mut input_mmap = unsafe { Mmap::map(&input_file)? };
+        let converted_pbf = if osmxml::is_osm_xml(input_path, &input_mmap) {
+            let path = temp_path(output, ".xml.osm.pbf");
+            convert_xml(input_path, &input_mmap, &path, true)?;
+            input_mmap = unsafe { Mmap::map(&File::open(&path)?)? };
+            Some(path)
+        } else {
+            None
+        };
+        let input_data: &[u8] = &input_mmap;
+
+        let tag_policy = self.tag_policy.as_ref();
+        if let Some(tag_policy) = tag_policy {
+            info!("Applying tag policy: {}", tag_policy);
+        }
+
+        let storage = FileResourceStorage::new(output.to_owned());
+        let builder = osmflat::OsmBuilder::new(storage.clone())?;
+
+        let spilled_strings = self
+            .max_memory
+            .map(|max_memory| (temp_path(output, ".stringtable"), max_memory));
+        let mut stringtable = match &spilled_strings {
+            Some((path, max_memory)) => {
+                if *max_memory < strings::MIN_MAX_MEMORY {
+                    warn!(
+                        "Memory limit of the stringtable is too low, using {} bytes",
+                        strings::MIN_MAX_MEMORY
+                    );
+                }
+                info!(
+                    "Limiting the memory of the stringtable to {} bytes, spilling to: {}",
+                    max_memory,
+                    path.display()
+                );
+                StringTable::with_spill_file(path.clone(), *max_memory)
+            }
+            None => StringTable::new(),
+        };
+        let mut tags = TagSerializer::new(&builder, tag_policy)?;
+
+        info!("Initialized new osmflat archive at: {}", output.display());
+
+        info!("Building index of PBF blocks...");
+        let (mut block_index, errors) = build_block_index(input_data);
+        for error in errors {
+            if !self.lenient {
+                return Err(error.into());
+            }
+            warn!("Skipping corrupt block: {}", error);
+            stats.num_skipped_blocks += 1;
+        }
+        let mut greatest_common_granularity = 1000000000;
+        for block in &block_index {
+            if block.block_type == BlockType::Nodes || block.block_type == BlockType::DenseNodes {
+                // only nodes have coordinates we need to scale
+                if let Some(block_granularity) = block.granularity {
+                    greatest_common_granularity =
+                        crate::gcd(greatest_common_granularity, block_granularity as i32);
+                }
+            }
+        }
+        let coord_scale = 1000000000 / greatest_common_granularity;
+        info!(
+            "Greatest common granularity: {}, Coordinate scaling factor: {}",
+            greatest_common_granularity, coord_scale
+        );
+
+        // blocks of different types might be interleaved, and dense and
+        // non-dense node blocks might be mixed, so the blocks of each type are
+        // kept in the order of their appearance in the input
+        block_index.sort_unstable_by_key(|b| b.blob_start);
+        let mut pbf_header = Vec::new();
+        let mut pbf_nodes = Vec::new();
+        let mut pbf_ways = Vec::new();
+        let mut pbf_relations = Vec::new();
+        for block in block_index {
+            match block.block_type {
+                BlockType::Header => pbf_header.push(block),
+                BlockType::Nodes | BlockType::DenseNodes => pbf_nodes.push(block),
+                BlockType::Ways => pbf_ways.push(block),
+                BlockType::Relations => pbf_relations.push(block),
+            }
+        }
+        info!("PBF block index built.");
+
+        // Serialize header
+        if pbf_header.len() != 1 {
+            return Err(format!(
+                "Require exactly one header block, but found {}",
+                pbf_header.len()
+            )
+            .into());
+        }
+        let idx = &pbf_header[0];
+        let mut pbf_header: osmpbf::HeaderBlock = read_block(input_data, idx)?;
+        if let Some(ref area) = self.area {
+            pbf_header.bbox = Some(area.header_bbox());
+        }
+        serialize_header(
+            &pbf_header,
+            coord_scale,
+            tag_policy,
+            &builder,
+            &mut stringtable,
+        )?;
+        info!("Header written.");
+
+        let selection = if self.area.is_some() || self.filter.is_some() {
+            let criteria = extract::Criteria {
+                area: self.area.as_ref(),
+                complete_ways: self.complete_ways,
+                filter: self.filter.as_ref(),
+                keep_way_nodes: self.keep_way_nodes,
+            };
+            Some(extract::select(
+                input_data,
+                &pbf_nodes,
+                &pbf_ways,
+                &pbf_relations,
+                criteria,
+            )?)
+        } else {
+            None
+        };
+
+        let ids_archive;
+        let mut node_ids = None;
+        let mut way_ids = None;
+        let mut relation_ids = None;
+        if self.ids {
+            ids_archive = builder.ids()?;
+            node_ids = Some(ids_archive.start_nodes()?);
+            way_ids = Some(ids_archive.start_ways()?);
+            relation_ids = Some(ids_archive.start_relations()?);
+        }
+
+        let info_archive;
+        let mut node_infos = None;
+        let mut way_infos = None;
+        let mut relation_infos = None;
+        if self.info {
+            info_archive = builder.info()?;
+            node_infos = Some(info_archive.start_nodes()?);
+            way_infos = Some(info_archive.start_ways()?);
+            relation_infos = Some(info_archive.start_relations()?);
+        }
+
+        let nodes_id_to_idx = serialize_node_blocks(
+            &builder,
+            greatest_common_granularity,
+            node_ids,
+            node_infos,
+            pbf_nodes,
+            input_data,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            selection.as_ref(),
+            self.lenient,
+            progress,
+        )?;
+
+        let ways_id_to_idx = serialize_way_blocks(
+            &builder,
+            way_ids,
+            way_infos,
+            pbf_ways,
+            input_data,
+            &nodes_id_to_idx,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            selection.as_ref(),
+            self.lenient,
+            progress,
+        )?;
+
+        let relations_id_to_idx = serialize_relation_blocks(
+            &builder,
+            relation_ids,
+            relation_infos,
+            pbf_relations,
+            input_data,
+            &nodes_id_to_idx,
+            &ways_id_to_idx,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            selection.as_ref(),
+            self.lenient,
+            progress,
+        )?;
+
+        if self.id_index {
+            info!("Writing id index...");
+            let id_index = builder.id_index()?;
+            nodes_id_to_idx.write(id_index.start_node_blocks()?, id_index.start_node_ids()?)?;
+            ways_id_to_idx.write(id_index.start_way_blocks()?, id_index.start_way_ids()?)?;
+            relations_id_to_idx.write(
+                id_index.start_relation_blocks()?,
+                id_index.start_relation_ids()?,
+            )?;
+            info!("Id index written.");
+        }
+
+        // Finalize data structures
+        tags.close(); // drop the reference to stringtable
+
+        info!("Writing stringtable to disk...");
+        builder.set_stringtable(&stringtable.finish()?)?;
+
+        if self.spatial_order {
+            rewrite_archive(output, ".spatial-order", spatial::reorder)?;
+        }
+        if self.sort_strings {
+            rewrite_archive(output, ".sorted-strings", strings::sort_by_frequency)?;
+        }
+
+        if self.spatial_index || self.node_ways || self.parent_relations || self.tag_elements {
+            // the indexes are built from the already written data
+            let archive = osmflat::Osm::open(storage.clone())?;
+            if self.spatial_index {
+                spatial::build_spatial_index(&archive, &builder.spatial_index()?)?;
+            }
+            if self.node_ways {
+                reverse::build_node_ways(
+                    &archive,
+                    &builder.node_ways()?,
+                    &temp_path(output, ".node_ways"),
+                )?;
+            }
+            if self.parent_relations {
+                reverse::build_parent_relations(
+                    &archive,
+                    &builder.parent_relations()?,
+                    &temp_path(output, ".parent_relations"),
+                )?;
+            }
+            if self.tag_elements {
+                reverse::build_tag_elements(
+                    &archive,
+                    &builder.tag_elements()?,
+                    &temp_path(output, ".tag_elements"),
+                )?;
+            }
+        }
+
+        info!("osmflat archive built.");
+
+        std::mem::drop(builder);
+        osmflat::Osm::open(storage)?;
+
+        info!("verified that osmflat archive can be opened.");
+
+        let spilled_strings = spilled_strings
+            .map(|(path, _)| path)
+            .filter(|path| path.exists());
+        std::mem::drop(input_mmap);
+        for path in updated_pbf
+            .into_iter()
+            .chain(spooled_input)
+            .chain(merged_pbf)
+            .chain(converted_pbf)
+            .chain(spilled_strings)
+        {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Applies the change files to the input archive.
+    ///
+    /// The result is written to a temporary PBF file next to the output archive,
+    /// whose path is returned.
+    fn apply_changes(&self, output: &Path) -> Result<PathBuf, Error> {
+        if self.input == Path::new("-") {
+            return Err("cannot apply changes to an input read from stdin".into());
+        }
+        if !self.inputs.is_empty() {
+            return Err("cannot apply changes to multiple inputs".into());
+        }
+        if output.exists() && self.input.canonicalize()? == output.canonicalize()? {
+            return Err("cannot apply changes to the input archive in place".into());
+        }
+
+        let mut changes = osmchange::Changes::default();
+        for path in &self.changes {
+            info!("Reading changes from: {}", path.display());
+            let file = File::open(path)?;
+            let data = unsafe { Mmap::map(&file)? };
+            changes.read(&data)?;
+        }
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(self.input.clone()))?;
+        let mut header = osmchange::header(&archive);
+        if let Some(path) = &self.state {
+            let (sequence_number, timestamp) =
+                osmchange::read_state(&std::fs::read_to_string(path)?)?;
+            header.osmosis_replication_sequence_number = Some(sequence_number);
+            header.osmosis_replication_timestamp = Some(timestamp);
+        } else if changes.timestamp() > header.osmosis_replication_timestamp {
+            header.osmosis_replication_timestamp = changes.timestamp();
+        }
+
+        let path = temp_path(output, ".changes.osm.pbf");
+        info!(
+            "Applying changes to {}, writing temporary PBF file: {}",
+            self.input.display(),
+            path.display()
+        );
+        let out = io::BufWriter::new(File::create(&path)?);
+        osmchange::write_pbf(&archive, &changes, &header, out)?;
+        info!("Changes applied.");
+        Ok(path)
+    }
+
+    /// Merges the inputs by the ids of their elements.
+    ///
+    /// The result is written to a temporary PBF file next to the output
+    /// archive, whose path is returned.
+    fn merge_inputs(
+        &self,
+        inputs: &[&Path],
+        output: &Path,
+        stats: &mut Stats,
+    ) -> Result<PathBuf, Error> {
+        let path = temp_path(output, ".merged.osm.pbf");
+        info!(
+            "Merging {} inputs, writing temporary PBF file: {}",
+            inputs.len(),
+            path.display()
+        );
+        map_inputs(inputs, output, true, |data| {
+            let out = io::BufWriter::new(File::create(&path)?);
+            stats.num_skipped_blocks += merge::write_pbf(data, self.lenient, out)?;
+            Ok(())
+        })?;
+        info!("Inputs merged.");
+        Ok(path)
+    }
+
+    /// Sorts the inputs by type and id with an external merge sort, which
+    /// also merges multiple inputs.
+    ///
+    /// The sorted runs and the result are written to temporary PBF files next
+    /// to the output archive. The path of the result is returned.
+    fn sort_inputs(
+        &self,
+        inputs: &[&Path],
+        output: &Path,
+        stats: &mut Stats,
+    ) -> Result<PathBuf, Error> {
+        info!("Sorting inputs in runs of {} elements...", SORT_RUN_LEN);
+        let mut runs = Vec::new();
+        // XML inputs are not checked for order, since they are sorted here
+        map_inputs(inputs, output, false, |data| {
+            for data in data {
+                stats.num_skipped_blocks +=
+                    merge::write_sorted_runs(data, self.lenient, SORT_RUN_LEN, || {
+                        let path = temp_path(output, &format!(".run{}.osm.pbf", runs.len()));
+                        let file = File::create(&path)?;
+                        runs.push(path);
+                        Ok(io::BufWriter::new(file))
+                    })?;
+            }
+            Ok(())
+        })?;
+
+        let path = temp_path(output, ".sorted.osm.pbf");
+        info!(
+            "Merging {} sorted runs, writing temporary PBF file: {}",
+            runs.len(),
+            path.display()
+        );
+        let run_paths: Vec<&Path> = runs.iter().map(PathBuf::as_path).collect();
+        map_inputs(&run_paths, output, true, |data| {
+            let out = io::BufWriter::new(File::create(&path)?);
+            merge::write_pbf(data, false, out)?;
+            Ok(())
+        })?;
+        for run in runs {
+            std::fs::remove_file(run)?;
+        }
+        info!("Inputs sorted.");
+        Ok(path)
+    }
+}
+
+/// Maps the inputs into memory, converting OSM XML inputs to temporary PBF
+/// files next to the output archive, and calls `f` with their data.
+///
+/// With `check_sorted`, an XML input which is not sorted by type and id is an
+/// error.
+fn map_inputs<T>(
+    inputs: &[&Path],
+    output: &Path,
+    check_sorted: bool,
+    f: impl FnOnce(&[&[u8]]) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut converted = Vec::new();
+    let mmaps = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, path)| -> Result<Mmap, Error> {
+            let mmap = unsafe { Mmap::map(&File::open(path)?)? };
+            if !osmxml::is_osm_xml(path, &mmap) {
+                return Ok(mmap);
+            }
+            let pbf_path = temp_path(output, &format!(".xml{}.osm.pbf", i));
+            convert_xml(path, &mmap, &pbf_path, check_sorted)?;
+            let mmap = unsafe { Mmap::map(&File::open(&pbf_path)?)? };
+            converted.push(pbf_path);
+            Ok(mmap)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let data: Vec<&[u8]> = mmaps.iter().map(|mmap| &mmap[..]).collect();
+    let result = f(&data);
+    std::mem::drop(data);
+    std::mem::drop(mmaps);
+    for path in converted {
+        std::fs::remove_file(path)?;
+    }
+    result
+}
+
+/// Converts the (optionally compressed) OSM XML input at `path` with the
+/// contents `data` to a PBF file at `pbf_path`.
+///
+/// See `osmxml::write_pbf` for `check_sorted`.
+fn convert_xml(path: &Path, data: &[u8], pbf_path: &Path, check_sorted: bool) -> Result<(), Error> {
+    info!(
+        "Converting OSM XML input {} to temporary PBF file: {}",
+        path.display(),
+        pbf_path.display()
+    );
+    let out = io::BufWriter::new(File::create(pbf_path)?);
+    osmxml::write_pbf(|| osmxml::decompress(data), check_sorted, out)?;
+    Ok(())
+}
+
+/// Returns the path of a temporary file next to the output archive.
+fn temp_path(output: &Path, suffix: &str) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Rewrites resources of the archive at `output` with `rewrite`, which reads
+/// the archive and writes the rewritten resources to a builder.
+///
+/// The resources are written to a temporary archive next to the output
+/// archive, since the latter is still mapped while reading it, and moved to
+/// the output archive afterwards.
+fn rewrite_archive(
+    output: &Path,
+    suffix: &str,
+    rewrite: impl FnOnce(&osmflat::Osm, &osmflat::OsmBuilder) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let rewritten = temp_path(output, suffix);
+    {
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output.to_owned()))?;
+        let builder = osmflat::OsmBuilder::new(FileResourceStorage::new(rewritten.clone()))?;
+        rewrite(&archive, &builder)?;
+    }
+    move_files(&rewritten, output)?;
+    Ok(())
+}
+
+/// Moves the files in the directory `from` recursively to the directory `to`,
+/// replacing existing files, and removes `from`.
+fn move_files(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            move_files(&entry.path(), &target)?;
+        } else {
+            std::fs::rename(entry.path(), target)?;
+        }
+    }
+    std::fs::remove_dir(from)
+}
+
+/// Spools the input from stdin to a temporary file next to the output archive,
+/// whose path is returned.
+///
+/// PBF data is copied block by block, so that a truncated stream is detected
+/// before the compilation starts. Other data, i.e. OSM XML, is copied as is.
+fn spool_stdin(output: &Path) -> Result<PathBuf, Error> {
+    let path = temp_path(output, ".stdin");
+    info!(
+        "Reading input from stdin, writing temporary file: {}",
+        path.display()
+    );
+
+    let stdin = io::stdin();
+    let mut reader = io::BufReader::new(stdin.lock());
+    let mut out = io::BufWriter::new(File::create(&path)?);
+    if osmxml::is_osm_xml(Path::new("-"), reader.fill_buf()?) {
+        io::copy(&mut reader, &mut out)?;
+        out.flush()?;
+    } else {
+        let num_blocks = osmpbf::copy_blocks(reader, out)?;
+        info!("Read {} PBF blocks from stdin.", num_blocks);
+    }
+    Ok(path)
+}
+
+fn serialize_header(
+    header_block: &osmpbf::HeaderBlock,
+    coord_scale: i32,
+    tag_policy: Option<&TagPolicy>,
+    builder: &osmflat::OsmBuilder,
+    stringtable: &mut StringTable,
+) -> io::Result<()> {
+    let mut header = osmflat::Header::new();
+
+    header.set_coord_scale(coord_scale);
+
+    if let Some(ref bbox) = header_block.bbox {
+        header.set_bbox_left((bbox.left / (1000000000 / coord_scale) as i64) as i32);
+        header.set_bbox_right((bbox.right / (1000000000 / coord_scale) as i64) as i32);
+        header.set_bbox_top((bbox.top / (1000000000 / coord_scale) as i64) as i32);
+        header.set_bbox_bottom((bbox.bottom / (1000000000 / coord_scale) as i64) as i32);
+    };
+
+    header.set_writingprogram_idx(stringtable.insert("osmflatc")?);
+
+    if let Some(ref source) = header_block.source {
+        header.set_source_idx(stringtable.insert(source)?);
+    }
+
+    if let Some(timestamp) = header_block.osmosis_replication_timestamp {
+        header.set_replication_timestamp(timestamp);
+    }
+
+    if let Some(number) = header_block.osmosis_replication_sequence_number {
+        header.set_replication_sequence_number(number);
+    }
+
+    if let Some(ref url) = header_block.osmosis_replication_base_url {
+        header.set_replication_base_url_idx(stringtable.insert(url)?);
+    }
+
+    header.set_tag_policy_idx(
+        tag_policy
+            .map(|policy| stringtable.insert(&policy.to_string()))
+            .transpose()?,
+    );
+
+    builder.set_header(&header)?;
+    Ok(())
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+struct I40 {
+    x: [u8; 5],
+}
+
+impl I40 {
+    fn from_u64(x: u64) -> Self {
+        let x = x.to_le_bytes();
+        debug_assert_eq!((x[5], x[6], x[7]), (0, 0, 0));
+        Self {
+            x: [x[0], x[1], x[2], x[3], x[4]],
+        }
+    }
+
+    fn to_u64(self) -> u64 {
+        let extented = [
+            self.x[0], self.x[1], self.x[2], self.x[3], self.x[4], 0, 0, 0,
+        ];
+        u64::from_le_bytes(extented)
+    }
+}
+
+#[allow(clippy::derived_hash_with_manual_eq)]
+impl std::hash::Hash for I40 {
+    fn hash<H>(&self, h: &mut H)
+    where
+        H: std::hash::Hasher,
+    {
+        // We manually implement Hash like this, since [u8; 5] is slower to hash
+        // than u64 for some/many hash functions
+        self.to_u64().hash(h)
+    }
+}
+
+/// Holds tags external vector and deduplicates tags.
+struct TagSerializer<'a> {
+    tags: flatdata::ExternalVector<'a, osmflat::Tag>,
+    tags_index: flatdata::ExternalVector<'a, osmflat::TagIndex>,
+    dedup: AHashMap<(I40, I40), I40>, // deduplication table: (key_idx, val_idx) -> pos
+    policy: Option<&'a TagPolicy>,
+}
+
+impl<'a> TagSerializer<'a> {
+    fn new(builder: &'a osmflat::OsmBuilder, policy: Option<&'a TagPolicy>) -> io::Result<Self> {
+        Ok(Self {
+            tags: builder.start_tags()?,
+            tags_index: builder.start_tags_index()?,
+            dedup: AHashMap::new(),
+            policy,
+        })
+    }
+
+    /// Serializes the tag with the key and value at the given indexes in the
+    /// block strings, unless it is dropped by the tag policy.
+    fn serialize(
+        &mut self,
+        strings: &mut BlockStrings,
+        key: usize,
+        val: usize,
+    ) -> Result<(), Error> {
+        if !self
+            .policy
+            .is_none_or(|policy| policy.keeps(strings.bytes(key)))
+        {
+            return Ok(());
+        }
+        let key_idx = strings.get(key)?;
+        let val_idx = strings.get(val)?;
+        let idx = match self
+            .dedup
+            .entry((I40::from_u64(key_idx), I40::from_u64(val_idx)))
+        {
+            hash_map::Entry::Occupied(entry) => entry.get().to_u64(),
+            hash_map::Entry::Vacant(entry) => {
+                let idx = self.tags.len() as u64;
+                let tag = self.tags.grow()?;
+                tag.set_key_idx(key_idx);
+                tag.set_value_idx(val_idx);
+                entry.insert(I40::from_u64(idx));
+                idx
+            }
+        };
+
+        let tag_index = self.tags_index.grow()?;
+        tag_index.set_value(idx);
+
+        Ok(())
+    }
+
+    fn next_index(&self) -> u64 {
+        self.tags_index.len() as u64
+    }
+
+    fn close(self) {
+        if let Err(e) = self.tags.close() {
+            panic!("failed to close tags: {}", e);
+        }
+        if let Err(e) = self.tags_index.close() {
+            panic!("failed to close tags index: {}", e);
+        }
+    }
+}
+
+/// Strings of a PBF block, which are added to the string table on first use.
+///
+/// This way, strings which are only used by dropped tags are not compiled.
+struct BlockStrings<'a> {
+    pbf_stringtable: &'a osmpbf::StringTable,
+    stringtable: &'a mut StringTable,
+    refs: Vec<Option<u64>>,
+}
+
+impl<'a> BlockStrings<'a> {
+    fn new(pbf_stringtable: &'a osmpbf::StringTable, stringtable: &'a mut StringTable) -> Self {
+        Self {
+            pbf_stringtable,
+            stringtable,
+            refs: vec![None; pbf_stringtable.s.len()],
+        }
+    }
+
+    fn bytes(&self, idx: usize) -> &'a [u8] {
+        &self.pbf_stringtable.s[idx]
+    }
+
+    /// Returns the index of the string in the string table.
+    fn get(&mut self, idx: usize) -> Result<u64, Error> {
+        if let Some(string_ref) = self.refs[idx] {
+            return Ok(string_ref);
+        }
+        let string = str::from_utf8(&self.pbf_stringtable.s[idx])?;
+        let string_ref = self.stringtable.insert(string)?;
+        self.refs[idx] = Some(string_ref);
+        Ok(string_ref)
+    }
+}
+
+/// Serializes the metadata of a node, way or relation.
+///
+/// Missing metadata is serialized with the default values of the PBF format.
+fn serialize_info(
+    pbf_info: Option<&osmpbf::Info>,
+    date_granularity: i64,
+    strings: &mut BlockStrings,
+    infos: &mut flatdata::ExternalVector<osmflat::Info>,
+) -> Result<(), Error> {
+    let default_info = osmpbf::Info::default();
+    let pbf_info = pbf_info.unwrap_or(&default_info);
+    let info = infos.grow()?;
+    info.set_version(pbf_info.version());
+    info.set_timestamp(pbf_info.timestamp() * date_granularity / 1000);
+    info.set_changeset(pbf_info.changeset());
+    info.set_uid(pbf_info.uid());
+    info.set_user_idx(
+        pbf_info
+            .user_sid
+            .map(|sid| strings.get(sid as usize))
+            .transpose()?,
+    );
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_dense_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    granularity: i32,
+    nodes: &mut flatdata::ExternalVector<osmflat::Node>,
+    node_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    node_infos: &mut Option<flatdata::ExternalVector<osmflat::Info>>,
+    nodes_id_to_idx: &mut ids::IdTableBuilder,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    selection: Option<&extract::Selection>,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let num_nodes = nodes.len();
+    let mut strings = BlockStrings::new(&block.stringtable, stringtable);
+    for group in block.primitivegroup.iter() {
+        let dense_nodes = group.dense.as_ref().unwrap();
+
+        let pbf_granularity = block.granularity.unwrap_or(100);
+        let lat_offset = block.lat_offset.unwrap_or(0);
+        let lon_offset = block.lon_offset.unwrap_or(0);
+        let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+        let mut lat = 0;
+        let mut lon = 0;
+
+        // delta coded metadata
+        let mut timestamp = 0;
+        let mut changeset = 0;
+        let mut uid = 0;
+        let mut user_sid = 0;
+
+        let mut tags_offset = 0;
+
+        let mut id = 0;
+        for i in 0..dense_nodes.id.len() {
+            id += dense_nodes.id[i];
+            lat += dense_nodes.lat[i];
+            lon += dense_nodes.lon[i];
+            let dense_info = dense_nodes
+                .denseinfo
+                .as_ref()
+                .filter(|dense_info| i < dense_info.version.len());
+            if let Some(dense_info) = dense_info {
+                timestamp += dense_info.timestamp[i];
+                changeset += dense_info.changeset[i];
+                uid += dense_info.uid[i];
+                user_sid += dense_info.user_sid[i];
+            }
+
+            if !selection.is_none_or(|selection| selection.contains_node(id)) {
+                // skip the tags of the node
+                while tags_offset < dense_nodes.keys_vals.len() {
+                    let k = dense_nodes.keys_vals[tags_offset];
+                    tags_offset += 1;
+                    if k == 0 {
+                        break; // separator
+                    }
+                    tags_offset += 1; // value
+                }
+                continue;
+            }
+
+            let index = nodes_id_to_idx
+                .insert(id as u64)
+                .map_err(|e| unsorted_input("node", e))?;
+            assert_eq!(index as usize, nodes.len());
+
+            let node = nodes.grow()?;
+            if let Some(ids) = node_ids {
+                ids.grow()?.set_value(id as u64);
+            }
+            if let Some(infos) = node_infos {
+                let info = infos.grow()?;
+                match dense_info {
+                    Some(dense_info) => {
+                        info.set_version(dense_info.version[i]);
+                        info.set_timestamp(timestamp * date_granularity / 1000);
+                        info.set_changeset(changeset);
+                        info.set_uid(uid);
+                        info.set_user_idx(Some(strings.get(user_sid as usize)?));
+                    }
+                    None => {
+                        // metadata is omitted, use the defaults of the PBF format
+                        info.set_version(-1);
+                        info.set_user_idx(None);
+                    }
+                }
+            }
+
+            node.set_lat(
+                ((lat_offset + (i64::from(pbf_granularity) * lat)) / granularity as i64) as i32,
+            );
+            node.set_lon(
+                ((lon_offset + (i64::from(pbf_granularity) * lon)) / granularity as i64) as i32,
+            );
+
+            // an empty `keys_vals` means that no node of the block has tags
+            node.set_tag_first_idx(tags.next_index());
+            while tags_offset < dense_nodes.keys_vals.len() {
+                let k = dense_nodes.keys_vals[tags_offset];
+                tags_offset += 1;
+
+                if k == 0 {
+                    break; // separator
+                }
+
+                let v = dense_nodes.keys_vals[tags_offset];
+                tags_offset += 1;
+
+                tags.serialize(&mut strings, k as usize, v as usize)?;
+            }
+        }
+        assert_eq!(tags_offset, dense_nodes.keys_vals.len());
+    }
+    stats.num_nodes += nodes.len() - num_nodes;
+    Ok(stats)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    granularity: i32,
+    nodes: &mut flatdata::ExternalVector<osmflat::Node>,
+    node_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    node_infos: &mut Option<flatdata::ExternalVector<osmflat::Info>>,
+    nodes_id_to_idx: &mut ids::IdTableBuilder,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    selection: Option<&extract::Selection>,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let num_nodes = nodes.len();
+    let mut strings = BlockStrings::new(&block.stringtable, stringtable);
+    let pbf_granularity = block.granularity.unwrap_or(100);
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+    for group in &block.primitivegroup {
+        for pbf_node in &group.nodes {
+            if !selection.is_none_or(|selection| selection.contains_node(pbf_node.id)) {
+                continue;
+            }
+
+            let index = nodes_id_to_idx
+                .insert(pbf_node.id as u64)
+                .map_err(|e| unsorted_input("node", e))?;
+            assert_eq!(index as usize, nodes.len());
+
+            let node = nodes.grow()?;
+            if let Some(ids) = node_ids {
+                ids.grow()?.set_value(pbf_node.id as u64);
+            }
+            if let Some(infos) = node_infos {
+                serialize_info(
+                    pbf_node.info.as_ref(),
+                    date_granularity,
+                    &mut strings,
+                    infos,
+                )?;
+            }
+
+            // in contrast to dense nodes, coordinates are not delta coded
+            node.set_lat(
+                ((lat_offset + (i64::from(pbf_granularity) * pbf_node.lat)) / granularity as i64)
+                    as i32,
+            );
+            node.set_lon(
+                ((lon_offset + (i64::from(pbf_granularity) * pbf_node.lon)) / granularity as i64)
+                    as i32,
+            );
+
+            debug_assert_eq!(
+                pbf_node.keys.len(),
+                pbf_node.vals.len(),
+                "invalid input data"
+            );
+            node.set_tag_first_idx(tags.next_index());
+            for i in 0..pbf_node.keys.len() {
+                tags.serialize(
+                    &mut strings,
+                    pbf_node.keys[i] as usize,
+                    pbf_node.vals[i] as usize,
+                )?;
+            }
+        }
+    }
+    stats.num_nodes += nodes.len() - num_nodes;
+    Ok(stats)
+}
+
+fn resolve_ways(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &ids::IdTable,
+    selection: Option<&extract::Selection>,
+) -> (Vec<Option<u64>>, Stats) {
+    let mut result = Vec::new();
+    let mut stats = Stats::default();
+    for group in &block.primitivegroup {
+        for pbf_way in &group.ways {
+            if !selection.is_none_or(|selection| selection.contains_way(pbf_way.id)) {
+                continue;
+            }
+            let mut node_ref = 0;
+            for delta in &pbf_way.refs {
+                node_ref += delta;
+                let idx = nodes_id_to_idx.get(node_ref as u64);
+                stats.num_unresolved_node_ids += idx.is_none() as usize;
+
+                result.push(idx);
+            }
+        }
+    }
+    (result, stats)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_ways(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &[Option<u64>],
+    ways: &mut flatdata::ExternalVector<osmflat::Way>,
+    way_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    way_infos: &mut Option<flatdata::ExternalVector<osmflat::Info>>,
+    ways_id_to_idx: &mut ids::IdTableBuilder,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    nodes_index: &mut flatdata::ExternalVector<osmflat::NodeIndex>,
+    selection: Option<&extract::Selection>,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let num_ways = ways.len();
+    let mut strings = BlockStrings::new(&block.stringtable, stringtable);
+    let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+    let mut nodes_idx = nodes_id_to_idx.iter().cloned();
+    for group in &block.primitivegroup {
+        for pbf_way in &group.ways {
+            if !selection.is_none_or(|selection| selection.contains_way(pbf_way.id)) {
+                continue;
+            }
+
+            let index = ways_id_to_idx
+                .insert(pbf_way.id as u64)
+                .map_err(|e| unsorted_input("way", e))?;
+            assert_eq!(index as usize, ways.len());
+
+            let way = ways.grow()?;
+            if let Some(ids) = way_ids {
+                ids.grow()?.set_value(pbf_way.id as u64);
+            }
+            if let Some(infos) = way_infos {
+                serialize_info(pbf_way.info.as_ref(), date_granularity, &mut strings, infos)?;
+            }
+
+            debug_assert_eq!(pbf_way.keys.len(), pbf_way.vals.len(), "invalid input data");
+            way.set_tag_first_idx(tags.next_index());
+
+            for i in 0..pbf_way.keys.len() {
+                tags.serialize(
+                    &mut strings,
+                    pbf_way.keys[i] as usize,
+                    pbf_way.vals[i] as usize,
+                )?;
+            }
+
+            way.set_ref_first_idx(nodes_index.len() as u64);
+            for _ in &pbf_way.refs {
+                nodes_index.grow()?.set_value(nodes_idx.next().unwrap());
+            }
+        }
+    }
+    stats.num_ways += ways.len() - num_ways;
+    Ok(stats)
+}
+
+fn build_relations_index<I>(
+    data: &[u8],
+    block_index: I,
+    selection: Option<&extract::Selection>,
+    lenient: bool,
+    progress: Option<&ProgressCallback>,
+) -> Result<ids::IdTable, Error>
+where
+    I: ExactSizeIterator<Item = BlockIndex> + Send + 'static,
+{
+    let mut result = ids::IdTableBuilder::new();
+    let mut pb = ProgressReporter::new(
+        progress,
+        "Building relations index...",
+        block_index.len() as u64,
+    );
+    parallel::parallel_process(
+        block_index,
+        |idx| read_block(data, &idx),
+        |block: Result<osmpbf::PrimitiveBlock, _>| -> Result<(), Error> {
+            let block = match block {
+                Ok(block) => block,
+                // corrupt blocks are reported when the relations are serialized
+                Err(_) if lenient => {
+                    pb.inc();
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            for group in &block.primitivegroup {
+                for relation in &group.relations {
+                    if selection.is_none_or(|selection| selection.contains_relation(relation.id)) {
+                        result
+                            .insert(relation.id as u64)
+                            .map_err(|e| unsorted_input("relation", e))?;
+                    }
+                }
+            }
+            pb.inc();
+            Ok(())
+        },
+    )?;
+
+    Ok(result.build())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_relations(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+    relations_id_to_idx: &ids::IdTable,
+    stringtable: &mut StringTable,
+    relations: &mut flatdata::ExternalVector<osmflat::Relation>,
+    relation_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    relation_infos: &mut Option<flatdata::ExternalVector<osmflat::Info>>,
+    relation_members: &mut flatdata::MultiVector<osmflat::RelationMembers>,
+    tags: &mut TagSerializer,
+    selection: Option<&extract::Selection>,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut strings = BlockStrings::new(&block.stringtable, stringtable);
+    let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+    for group in &block.primitivegroup {
+        for pbf_relation in &group.relations {
+            if !selection.is_none_or(|selection| selection.contains_relation(pbf_relation.id)) {
+                continue;
+            }
+
+            let relation = relations.grow()?;
+            if let Some(ids) = relation_ids {
+                ids.grow()?.set_value(pbf_relation.id as u64);
+            }
+            if let Some(infos) = relation_infos {
+                serialize_info(
+                    pbf_relation.info.as_ref(),
+                    date_granularity,
+                    &mut strings,
+                    infos,
+                )?;
+            }
+
+            debug_assert_eq!(
+                pbf_relation.keys.len(),
+                pbf_relation.vals.len(),
+                "invalid input data"
+            );
+            relation.set_tag_first_idx(tags.next_index());
+            for i in 0..pbf_relation.keys.len() {
+                tags.serialize(
+                    &mut strings,
+                    pbf_relation.keys[i] as usize,
+                    pbf_relation.vals[i] as usize,
+                )?;
+            }
+
+            debug_assert!(
+                pbf_relation.roles_sid.len() == pbf_relation.memids.len()
+                    && pbf_relation.memids.len() == pbf_relation.types.len(),
+                "invalid input data"
+            );
+
+            let mut memid = 0;
+            let mut members = relation_members.grow()?;
+            for i in 0..pbf_relation.roles_sid.len() {
+                memid += pbf_relation.memids[i];
+
+                let member_type = osmpbf::relation::MemberType::from_i32(pbf_relation.types[i]);
+                debug_assert!(member_type.is_some());
+
+                match member_type.unwrap() {
+                    osmpbf::relation::MemberType::Node => {
+                        let idx = nodes_id_to_idx.get(memid as u64);
+                        stats.num_unresolved_node_ids = idx.is_none() as usize;
+
+                        let member = members.add_node_member();
+                        member.set_node_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as usize)?);
+                    }
+                    osmpbf::relation::MemberType::Way => {
+                        let idx = ways_id_to_idx.get(memid as u64);
+                        stats.num_unresolved_way_ids = idx.is_none() as usize;
+
+                        let member = members.add_way_member();
+                        member.set_way_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as usize)?);
+                    }
+                    osmpbf::relation::MemberType::Relation => {
+                        let idx = relations_id_to_idx.get(memid as u64);
+                        stats.num_unresolved_rel_ids = idx.is_none() as usize;
+
+                        let member = members.add_relation_member();
+                        member.set_relation_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as usize)?);
+                    }
+                }
+            }
+            stats.num_relations += 1;
+        }
+    }
+    Ok(stats)
+}
+
+/// Returns the error of an element whose id is not greater than the id of the
+/// previous element of the same type.
+fn unsorted_input(element_type: &str, e: ids::UnsortedIds) -> Error {
+    format!(
+        "input is not sorted: {} {} follows {} {}, compile with sorting enabled (--sort)",
+        element_type, e.id, element_type, e.last_id
+    )
+    .into()
+}
+
+/// Returns the read block, or `None` if it is corrupt and skipped in lenient
+/// mode.
+fn skip_corrupt_block<T>(
+    block: Result<T, PbfError>,
+    lenient: bool,
+    stats: &mut Stats,
+) -> Result<Option<T>, PbfError> {
+    match block {
+        Ok(block) => Ok(Some(block)),
+        Err(e) if lenient => {
+            warn!("Skipping corrupt block: {}", e);
+            stats.num_skipped_blocks += 1;
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+type PrimitiveBlockWithType = (BlockType, osmpbf::PrimitiveBlock);
+
+/// Serializes dense and non-dense node blocks.
+///
+/// Blocks are expected in the order of their appearance in the input, such
+/// that node ids are ascending also when both block types are mixed.
+#[allow(clippy::too_many_arguments)]
+fn serialize_node_blocks(
+    builder: &osmflat::OsmBuilder,
+    granularity: i32,
+    mut node_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    mut node_infos: Option<flatdata::ExternalVector<osmflat::Info>>,
+    blocks: Vec<BlockIndex>,
+    data: &[u8],
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: Option<&extract::Selection>,
+    lenient: bool,
+    progress: Option<&ProgressCallback>,
+) -> Result<ids::IdTable, Error> {
+    let mut nodes_id_to_idx = ids::IdTableBuilder::new();
+    let mut nodes = builder.start_nodes()?;
+    let mut pb = ProgressReporter::new(progress, "Converting nodes...", blocks.len() as u64);
+
+    parallel::parallel_process(
+        blocks.into_iter(),
+        |idx| read_block(data, &idx).map(|block| (idx.block_type, block)),
+        |block: Result<PrimitiveBlockWithType, PbfError>| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let (block_type, block) = match skip_corrupt_block(block, lenient, stats)? {
+                Some(block) => block,
+                None => {
+                    pb.inc();
+                    return Ok(Default::default());
+                }
+            };
+            let serialize = match block_type {
+                BlockType::DenseNodes => serialize_dense_nodes,
+                BlockType::Nodes => serialize_nodes,
+                _ => unreachable!("not a node block: {:?}", block_type),
+            };
+            *stats += serialize(
+                &block,
+                granularity,
+                &mut nodes,
+                &mut node_ids,
+                &mut node_infos,
+                &mut nodes_id_to_idx,
+                stringtable,
+                tags,
+                selection,
+            )?;
+
+            pb.inc();
+            Ok(block)
+        },
+    )?;
+
+    // fill tag_first_idx of the sentry, since it contains the end of the tag range
+    // of the last node
+    nodes.grow()?.set_tag_first_idx(tags.next_index());
+    nodes.close()?;
+    if let Some(ids) = node_ids {
+        ids.close()?;
+    }
+    if let Some(infos) = node_infos {
+        infos.close()?;
+    }
+    info!("Nodes converted.");
+    info!("Building nodes index...");
+    let nodes_id_to_idx = nodes_id_to_idx.build();
+    info!("Nodes index built.");
+    Ok(nodes_id_to_idx)
+}
+
+type PrimitiveBlockWithIds = (osmpbf::PrimitiveBlock, (Vec<Option<u64>>, Stats));
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_way_blocks(
+    builder: &osmflat::OsmBuilder,
+    mut way_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    mut way_infos: Option<flatdata::ExternalVector<osmflat::Info>>,
+    blocks: Vec<BlockIndex>,
+    data: &[u8],
+    nodes_id_to_idx: &ids::IdTable,
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: Option<&extract::Selection>,
+    lenient: bool,
+    progress: Option<&ProgressCallback>,
+) -> Result<ids::IdTable, Error> {
+    let mut ways_id_to_idx = ids::IdTableBuilder::new();
+    let mut ways = builder.start_ways()?;
+    let mut pb = ProgressReporter::new(progress, "Converting ways...", blocks.len() as u64);
+    let mut nodes_index = builder.start_nodes_index()?;
+    parallel::parallel_process(
+        blocks.into_iter(),
+        |idx| {
+            let block: osmpbf::PrimitiveBlock = read_block(data, &idx)?;
+            let ids = resolve_ways(&block, nodes_id_to_idx, selection);
+            Ok((block, ids))
+        },
+        |block: Result<PrimitiveBlockWithIds, PbfError>| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let (block, (ids, stats_resolve)) = match skip_corrupt_block(block, lenient, stats)? {
+                Some(block) => block,
+                None => {
+                    pb.inc();
+                    return Ok(Default::default());
+                }
+            };
+            *stats += stats_resolve;
+            *stats += serialize_ways(
+                &block,
+                &ids,
+                &mut ways,
+                &mut way_ids,
+                &mut way_infos,
+                &mut ways_id_to_idx,
+                stringtable,
+                tags,
+                &mut nodes_index,
+                selection,
+            )?;
+            pb.inc();
+
+            Ok(block)
+        },
+    )?;
+
+    {
+        let sentinel = ways.grow()?;
+        sentinel.set_tag_first_idx(tags.next_index());
+        sentinel.set_ref_first_idx(nodes_index.len() as u64);
+    }
+    ways.close()?;
+    if let Some(ids) = way_ids {
+        ids.close()?;
+    }
+    if let Some(infos) = way_infos {
+        infos.close()?;
+    }
+    nodes_index.close()?;
+
+    info!("Ways converted.");
+    info!("Building ways index...");
+    let ways_id_to_idx = ways_id_to_idx.build();
+    info!("Way index built.");
+    Ok(ways_id_to_idx)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_relation_blocks(
+    builder: &osmflat::OsmBuilder,
+    mut relation_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    mut relation_infos: Option<flatdata::ExternalVector<osmflat::Info>>,
+    blocks: Vec<BlockIndex>,
+    data: &[u8],
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: Option<&extract::Selection>,
+    lenient: bool,
+    progress: Option<&ProgressCallback>,
+) -> Result<ids::IdTable, Error> {
+    // We need to build the index of relation ids first, since relations can refer
+    // again to relations.
+    let relations_id_to_idx = build_relations_index(
+        data,
+        blocks.clone().into_iter(),
+        selection,
+        lenient,
+        progress,
+    )?;
+
+    let mut relations = builder.start_relations()?;
+    let mut relation_members = builder.start_relation_members()?;
+
+    let mut pb = ProgressReporter::new(progress, "Converting relations...", blocks.len() as u64);
+    parallel::parallel_process(
+        blocks.into_iter(),
+        |idx| read_block(data, &idx),
+        |block| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let block = match skip_corrupt_block(block, lenient, stats)? {
+                Some(block) => block,
+                None => {
+                    pb.inc();
+                    return Ok(Default::default());
+                }
+            };
+            *stats += serialize_relations(
+                &block,
+                nodes_id_to_idx,
+                ways_id_to_idx,
+                &relations_id_to_idx,
+                stringtable,
+                &mut relations,
+                &mut relation_ids,
+                &mut relation_infos,
+                &mut relation_members,
+                tags,
+                selection,
+            )?;
+            pb.inc();
+            Ok(block)
+        },
+    )?;
+
+    {
+        let sentinel = relations.grow()?;
+        sentinel.set_tag_first_idx(tags.next_index());
+    }
+
+    relations.close()?;
+    if let Some(ids) = relation_ids {
+        ids.close()?;
+    }
+    if let Some(infos) = relation_infos {
+        infos.close()?;
+    }
+    relation_members.close()?;
+
+    info!("Relations converted.");
+
+    Ok(relations_id_to_idx)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    use byteorder::{NetworkEndian, WriteBytesExt};
+    use prost::Message;
+
+    use std::ops::Deref;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Archive compiled into a temporary directory, which is removed on drop.
+    pub struct TestArchive {
+        dir: PathBuf,
+        archive: Option<osmflat::Osm>,
+        pub stats: Stats,
+    }
+
+    impl TestArchive {
+        /// Compiles the input file `name` with the contents `data` by the
+        /// compiler configured with `configure`.
+        pub fn compile(
+            name: &str,
+            data: &[u8],
+            configure: impl FnOnce(Compiler) -> Compiler,
+        ) -> Result<Self, Error> {
+            static NUM_ARCHIVES: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "osmflatc-test-{}-{}",
+                std::process::id(),
+                NUM_ARCHIVES.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::create_dir_all(&dir)?;
+            // constructed first, such that the directory is removed on errors
+            let mut archive = Self {
+                dir,
+                archive: None,
+                stats: Stats::default(),
+            };
+            let input = archive.dir.join(name);
+            std::fs::write(&input, data)?;
+            archive.stats = configure(Compiler::new(input)).compile(archive.path())?;
+            archive.archive = Some(osmflat::Osm::open(FileResourceStorage::new(
+                archive.path(),
+            ))?);
+            Ok(archive)
+        }
+
+        /// Compiles the OSM XML `xml`.
+        pub fn compile_xml(
+            xml: &str,
+            configure: impl FnOnce(Compiler) -> Compiler,
+        ) -> Result<Self, Error> {
+            Self::compile("input.osm", xml.as_bytes(), configure)
+        }
+
+        /// Returns the path of the archive.
+        pub fn path(&self) -> PathBuf {
+            self.dir.join("archive.osm.flatdata")
+        }
+
+        /// Returns the names of the files next to the archive.
+        pub fn files(&self) -> Vec<String> {
+            let mut files: Vec<_> = std::fs::read_dir(&self.dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Deref for TestArchive {
+        type Target = osmflat::Osm;
+
+        fn deref(&self) -> &osmflat::Osm {
+            self.archive.as_ref().unwrap()
+        }
+    }
+
+    impl Drop for TestArchive {
+        fn drop(&mut self) {
+            // the archive is unmapped first, otherwise its files cannot be
+            // removed on all platforms
+            self.archive = None;
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Writes a message as an uncompressed blob including its blob header.
+    fn write_blob(out: &mut Vec<u8>, blob_type: &str, message: &impl Message) {
+        let raw = message.encode_to_vec();
+        let blob = osmpbf::Blob {
+            raw_size: Some(raw.len() as i32),
+            raw: Some(raw),
+            ..Default::default()
+        }
+        .encode_to_vec();
+        let blob_header = osmpbf::BlobHeader {
+            r#type: blob_type.into(),
+            indexdata: None,
+            datasize: blob.len() as i32,
+        }
+        .encode_to_vec();
+        out.write_i32::<NetworkEndian>(blob_header.len() as i32)
+            .unwrap();
+        out.extend(blob_header);
+        out.extend(blob);
+    }
+
+    /// Writes a block of nodes given as id, lat, lon and an optional name.
+    fn write_nodes(out: &mut Vec<u8>, dense: bool, nodes: &[(i64, i64, i64, &str)]) {
+        // index 0 is reserved as delimiter
+        let mut strings = vec![Vec::new(), b"name".to_vec()];
+        let mut group = osmpbf::PrimitiveGroup::default();
+        let mut dense_nodes = osmpbf::DenseNodes::default();
+        let mut last = (0, 0, 0);
+        for &(id, lat, lon, name) in nodes {
+            let (keys, vals) = if name.is_empty() {
+                (Vec::new(), Vec::new())
+            } else {
+                strings.push(name.as_bytes().to_vec());
+                (vec![1], vec![strings.len() as u32 - 1])
+            };
+            if dense {
+                dense_nodes.id.push(id - last.0);
+                dense_nodes.lat.push(lat - last.1);
+                dense_nodes.lon.push(lon - last.2);
+                last = (id, lat, lon);
+                for (key, val) in keys.into_iter().zip(vals) {
+                    dense_nodes.keys_vals.extend([key as i32, val as i32]);
+                }
+                dense_nodes.keys_vals.push(0);
+            } else {
+                group.nodes.push(osmpbf::Node {
+                    id,
+                    keys,
+                    vals,
+                    info: None,
+                    lat,
+                    lon,
+                });
+            }
+        }
+        if dense {
+            group.dense = Some(dense_nodes);
+        }
+        let block = osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable { s: strings },
+            primitivegroup: vec![group],
+            ..Default::default()
+        };
+        write_blob(out, "OSMData", &block);
+    }
+
+    #[test]
+    fn test_dense_and_non_dense_nodes() {
+        let header = osmpbf::HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".into(), "DenseNodes".into()],
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        write_blob(&mut data, "OSMHeader", &header);
+        write_nodes(
+            &mut data,
+            false,
+            &[(1, 525_100_000, 133_100_000, ""), (2, -10, 20, "first")],
+        );
+        write_nodes(
+            &mut data,
+            true,
+            &[(5, 30, -40, "second"), (7, 525_300_000, 133_300_000, "")],
+        );
+
+        let archive =
+            TestArchive::compile("input.osm.pbf", &data, |compiler| compiler.with_ids(true))
+                .unwrap();
+        assert_eq!(archive.header().coord_scale(), 10_000_000);
+        let ids: Vec<_> = archive
+            .ids()
+            .unwrap()
+            .nodes()
+            .iter()
+            .map(|id| id.value())
+            .collect();
+        assert_eq!(ids, vec![1, 2, 5, 7]);
+        let coords: Vec<_> = archive
+            .nodes()
+            .iter()
+            .map(|node| (node.lat(), node.lon()))
+            .collect();
+        assert_eq!(
+            coords,
+            vec![
+                (525_100_000, 133_100_000),
+                (-10, 20),
+                (30, -40),
+                (525_300_000, 133_300_000)
+            ]
+        );
+        let tags: Vec<Vec<_>> = archive
+            .nodes()
+            .iter()
+            .map(|node| osmflat::iter_tags(&archive, node.tags()).collect())
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                vec![],
+                vec![(&b"name"[..], &b"first"[..])],
+                vec![(&b"name"[..], &b"second"[..])],
+                vec![]
+            ]
+        );
+    }
+
+    const XML: &str = r#"<osm>
+      <node id="1" lat="52.5" lon="13.3"/>
+      <node id="2" lat="52.6" lon="13.4"><tag k="amenity" v="pub"/></node>
+      <way id="10"><nd ref="1"/><nd ref="2"/></way>
+    </osm>"#;
+
+    #[test]
+    fn test_xml_input() {
+        let archive = TestArchive::compile_xml(XML, |compiler| compiler).unwrap();
+        assert_eq!(archive.nodes().len(), 2);
+        assert_eq!(archive.ways().len(), 1);
+        // the temporary PBF file is removed
+        assert_eq!(archive.files(), vec!["archive.osm.flatdata", "input.osm"]);
+
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(XML.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let archive = TestArchive::compile("input", &compressed, |compiler| compiler).unwrap();
+        assert_eq!(archive.nodes().len(), 2);
+        assert_eq!(archive.files(), vec!["archive.osm.flatdata", "input"]);
+    }
+
+    #[test]
+    fn test_sort_xml_input() {
+        let xml = r#"<osm>
+          <way id="10"><nd ref="2"/><nd ref="1"/></way>
+          <node id="2" lat="52.6" lon="13.4"/>
+          <relation id="100"><member type="way" ref="10" role=""/></relation>
+          <node id="1" lat="52.5" lon="13.3"/>
+        </osm>"#;
+        assert!(TestArchive::compile_xml(xml, |compiler| compiler).is_err());
+
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_sort(true).with_ids(true))
+                .unwrap();
+        let ids = archive.ids().unwrap();
+        let node_ids: Vec<_> = ids.nodes().iter().map(|id| id.value()).collect();
+        assert_eq!(node_ids, vec![1, 2]);
+        assert_eq!(ids.ways()[0].value(), 10);
+        assert_eq!(ids.relations()[0].value(), 100);
+        let nodes_index = archive.nodes_index();
+        let refs: Vec<_> = archive.ways()[0]
+            .refs()
+            .map(|idx| nodes_index[idx as usize].value())
+            .collect();
+        assert_eq!(refs, vec![Some(1), Some(0)]);
+        assert_eq!(archive.files(), vec!["archive.osm.flatdata", "input.osm"]);
+    }
+
+    #[test]
+    fn test_josm_xml_input() {
+        let xml = r#"<osm>
+          <node id="1" lat="52.5" lon="13.3"/>
+          <node id="-1" action="modify" lat="52.6" lon="13.4"/>
+          <node id="2" action="delete" lat="52.7" lon="13.5"/>
+          <way id="10"><nd ref="1"/><nd ref="-1"/></way>
+          <way id="-1" action="modify"><nd ref="-1"/><nd ref="1"/></way>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler.with_ids(true)).unwrap();
+        let ids = archive.ids().unwrap();
+        let node_ids: Vec<_> = ids.nodes().iter().map(|id| id.value()).collect();
+        assert_eq!(node_ids, vec![1, 3]);
+        let way_ids: Vec<_> = ids.ways().iter().map(|id| id.value()).collect();
+        assert_eq!(way_ids, vec![10, 11]);
+        let nodes_index = archive.nodes_index();
+        let refs: Vec<Vec<_>> = archive
+            .ways()
+            .iter()
+            .map(|way| {
+                way.refs()
+                    .map(|idx| nodes_index[idx as usize].value())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(refs, vec![vec![Some(0), Some(1)], vec![Some(1), Some(0)]]);
+    }
+
+    #[test]
+    fn test_info() {
+        let xml = r#"<osm>
+          <node id="1" lat="0" lon="0" version="3" timestamp="2020-01-02T03:04:05Z"
+                changeset="42" uid="7" user="mapper"/>
+          <node id="2" lat="0" lon="0"/>
+          <way id="10" version="2" user="other"><nd ref="1"/></way>
+          <relation id="100" version="1"><member type="way" ref="10" role=""/></relation>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler).unwrap();
+        assert!(archive.info().is_none());
+
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler.with_info(true)).unwrap();
+        let infos = archive.info().unwrap();
+        let strings = archive.stringtable();
+        let info = |info: &osmflat::Info| {
+            (
+                info.version(),
+                info.timestamp(),
+                info.changeset(),
+                info.uid(),
+                info.user_idx()
+                    .map(|idx| strings.substring(idx as usize).unwrap()),
+            )
+        };
+        assert_eq!(infos.nodes().len(), 2);
+        assert_eq!(
+            info(&infos.nodes()[0]),
+            (3, 1_577_934_245, 42, 7, Some("mapper"))
+        );
+        assert_eq!(info(&infos.nodes()[1]), (-1, 0, 0, 0, None));
+        assert_eq!(info(&infos.ways()[0]), (2, 0, 0, 0, Some("other")));
+        assert_eq!(info(&infos.relations()[0]), (1, 0, 0, 0, None));
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let base = TestArchive::compile_xml(XML, |compiler| compiler.with_ids(true)).unwrap();
+        let osc = r#"<osmChange version="0.6">
+          <modify>
+            <node id="2" lat="52.7" lon="13.5"><tag k="amenity" v="cafe"/></node>
+          </modify>
+          <create><node id="3" lat="52.8" lon="13.6"/></create>
+          <delete><way id="10"/></delete>
+        </osmChange>"#;
+        let state = base.dir.join("state.txt");
+        std::fs::write(
+            &state,
+            "sequenceNumber=4711\ntimestamp=2020-01-02T03\\:04\\:05Z\n",
+        )
+        .unwrap();
+        let archive = TestArchive::compile("changes.osc", osc.as_bytes(), |compiler| {
+            Compiler::new(base.path())
+                .with_changes(vec![compiler.input])
+                .with_state(&state)
+                .with_ids(true)
+                .with_id_index(true)
+        })
+        .unwrap();
+
+        let ids = archive.ids().unwrap();
+        let node_ids: Vec<_> = ids.nodes().iter().map(|id| id.value()).collect();
+        assert_eq!(node_ids, vec![1, 2, 3]);
+        assert_eq!(archive.ways().len(), 0);
+        let node = archive.node_by_id(2).unwrap();
+        assert_eq!(node.tag(b"amenity"), Some(&b"cafe"[..]));
+        assert_eq!(node.lat_lon(), (52.7, 13.5));
+        let header = archive.header();
+        assert_eq!(header.replication_sequence_number(), 4711);
+        assert_eq!(header.replication_timestamp(), 1_577_934_245);
+    }
+
+    #[test]
+    fn test_tag_policy() {
+        let xml = r#"<osm>
+          <node id="1" lat="0" lon="0">
+            <tag k="amenity" v="pub"/>
+            <tag k="created_by" v="JOSM"/>
+            <tag k="source:name" v="survey"/>
+          </node>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler).unwrap();
+        assert_eq!(archive.header().tag_policy_idx(), None);
+
+        let mut policy = TagPolicy::default();
+        policy.add(&[], &["created_by,source:*".into()]);
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_tag_policy(policy)).unwrap();
+        let policy_idx = archive.header().tag_policy_idx().unwrap();
+        assert_eq!(
+            archive
+                .stringtable()
+                .substring(policy_idx as usize)
+                .unwrap(),
+            "drop=created_by,source:*"
+        );
+        let tags: Vec<_> = archive.node(0).unwrap().tags().collect();
+        assert_eq!(tags, vec![(&b"amenity"[..], &b"pub"[..])]);
+    }
+
+    #[test]
+    fn test_sort_strings() {
+        let xml = r#"<osm generator="test">
+          <node id="1" lat="0" lon="0" user="mapper"><tag k="name" v="yes"/></node>
+          <node id="2" lat="0" lon="0" user="mapper"><tag k="amenity" v="yes"/></node>
+          <way id="10" user="other">
+            <nd ref="1"/>
+            <tag k="building" v="yes"/>
+            <tag k="oneway" v="yes"/>
+          </way>
+          <relation id="100">
+            <member type="node" ref="1" role="label"/>
+            <member type="way" ref="10" role="outer"/>
+            <tag k="type" v="multipolygon"/>
+          </relation>
+        </osm>"#;
+        let unsorted = TestArchive::compile_xml(xml, |compiler| compiler.with_info(true)).unwrap();
+        let sorted = TestArchive::compile_xml(xml, |compiler| {
+            compiler.with_info(true).with_sort_strings(true)
+        })
+        .unwrap();
+
+        // the most used string comes first
+        assert_eq!(sorted.stringtable().substring(0).unwrap(), "yes");
+        assert_ne!(
+            unsorted.stringtable().as_bytes(),
+            sorted.stringtable().as_bytes()
+        );
+        let mut unsorted_strings: Vec<_> = unsorted
+            .stringtable()
+            .as_bytes()
+            .split(|&c| c == 0)
+            .collect();
+        let mut sorted_strings: Vec<_> =
+            sorted.stringtable().as_bytes().split(|&c| c == 0).collect();
+        unsorted_strings.sort();
+        sorted_strings.sort();
+        assert_eq!(unsorted_strings, sorted_strings);
+
+        /// Strings of the header, tags, roles and users of an archive
+        type Contents<'a> = (
+            &'a [u8],
+            Vec<Vec<(&'a [u8], &'a [u8])>>,
+            Vec<&'a [u8]>,
+            Vec<Option<&'a [u8]>>,
+        );
+        fn contents(archive: &osmflat::Osm) -> Contents<'_> {
+            let strings = archive.stringtable();
+            let string = |idx: u64| strings.substring_raw(idx as usize);
+            let tags = archive
+                .node_refs()
+                .map(|node| node.tags().collect())
+                .chain(archive.way_refs().map(|way| way.tags().collect()))
+                .chain(
+                    archive
+                        .relation_refs()
+                        .map(|relation| relation.tags().collect()),
+                )
+                .collect();
+            let roles = archive
+                .relation(0)
+                .unwrap()
+                .members()
+                .map(|member| member.role())
+                .collect();
+            let infos = archive.info().unwrap();
+            let users = infos
+                .nodes()
+                .iter()
+                .chain(infos.ways())
+                .chain(infos.relations())
+                .map(|info| info.user_idx().map(string))
+                .collect();
+            (
+                string(archive.header().writingprogram_idx()),
+                tags,
+                roles,
+                users,
+            )
+        }
+        assert_eq!(contents(&unsorted), contents(&sorted));
+    }
+}
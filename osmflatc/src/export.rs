@@ -0,0 +1,499 @@
+//! Export of an osmflat archive to GeoJSON.
+//!
+//! Tagged nodes are exported as points, and tagged ways as line strings, or as
+//! polygons if they are closed and describe an area. Relations of type
+//! `multipolygon` or `boundary` are assembled into multipolygons, all other
+//! relations have no geometry and are not exported. The tags of an element are
+//! the properties of its feature.
+
+use crate::extract::Area;
+use crate::filter::TagFilter;
+use crate::osmxml::ElementType;
+use crate::Error;
+
+use log::debug;
+use osmflat::{FileResourceStorage, GeometryError, Osm, Polygon, WayRef};
+use serde_json::{json, Map, Value};
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Keys of linear features, whose closed ways are only areas if they are
+/// tagged with `area=yes`.
+const LINEAR_KEYS: [&[u8]; 6] = [
+    b"highway",
+    b"barrier",
+    b"railway",
+    b"waterway",
+    b"power",
+    b"aerialway",
+];
+
+/// Output format of the export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Single GeoJSON feature collection
+    GeoJson,
+    /// Newline-delimited GeoJSON features (GeoJSONSeq)
+    GeoJsonSeq,
+}
+
+/// Statistics of an export.
+#[derive(Debug, Default)]
+pub struct ExportStats {
+    pub num_nodes: usize,
+    pub num_ways: usize,
+    pub num_relations: usize,
+    /// Number of selected ways and relations without a valid geometry, e.g.
+    /// due to missing nodes or broken rings
+    pub num_skipped: usize,
+}
+
+impl fmt::Display for ExportStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            r#"Exported:
+  nodes:        {}
+  ways:         {}
+  relations:    {}
+Skipped invalid geometries: {}"#,
+            self.num_nodes, self.num_ways, self.num_relations, self.num_skipped
+        )
+    }
+}
+
+/// Exporter of an osmflat archive to GeoJSON.
+///
+/// The selection of the exported elements is configured with the builder
+/// methods:
+///
+/// ```no_run
+/// # fn main() -> Result<(), osmflatc::Error> {
+/// let filter = osmflatc::TagFilter::parse(["w/building"])?;
+/// let output = std::fs::File::create("buildings.geojson")?;
+/// let stats = osmflatc::Exporter::new("input.osm.flatdata")
+///     .with_filter(filter)
+///     .with_ids(true)
+///     .export(std::io::BufWriter::new(output))?;
+/// println!("{}", stats);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Exporter {
+    input: PathBuf,
+    format: ExportFormat,
+    area: Option<Area>,
+    filter: Option<TagFilter>,
+    ids: bool,
+}
+
+impl Exporter {
+    /// Creates an exporter of all tagged elements of the archive to a GeoJSON
+    /// feature collection without ids.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            format: ExportFormat::GeoJson,
+            area: None,
+            filter: None,
+            ids: false,
+        }
+    }
+
+    /// Sets the output format.
+    pub fn with_format(mut self, format: ExportFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Only exports the elements with any of their nodes inside the area.
+    pub fn with_area(mut self, area: Area) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    /// Only exports the elements matching the tag filter.
+    pub fn with_filter(mut self, filter: TagFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Sets the ids of the features to the OSM ids of the elements prefixed
+    /// with their types, e.g. `w42`. Requires an archive compiled with
+    /// `--ids`.
+    pub fn with_ids(mut self, ids: bool) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Exports the selected elements to the output.
+    pub fn export(&self, output: impl Write) -> Result<ExportStats, Error> {
+        let archive = Osm::open(FileResourceStorage::new(&self.input))?;
+        if self.ids && archive.ids().is_none() {
+            return Err("exporting ids requires an archive compiled with --ids".into());
+        }
+
+        let mut writer = FeatureWriter::new(output, self.format)?;
+        let mut stats = ExportStats::default();
+
+        for node in archive.node_refs() {
+            if !self.matches(ElementType::Node, node.tags())
+                || !self.in_area(std::iter::once(node.lat_lon()))
+            {
+                continue;
+            }
+            let geometry = json!({ "type": "Point", "coordinates": position(node.lat_lon()) });
+            let id = self.id("n", node.id());
+            writer.write(&feature(id, geometry, node.tags()))?;
+            stats.num_nodes += 1;
+        }
+
+        for way in archive.way_refs() {
+            if !self.matches(ElementType::Way, way.tags())
+                || !self.in_area(way.nodes().flatten().map(|node| node.lat_lon()))
+            {
+                continue;
+            }
+            match way_geometry(way) {
+                Some(geometry) => {
+                    let id = self.id("w", way.id());
+                    writer.write(&feature(id, geometry, way.tags()))?;
+                    stats.num_ways += 1;
+                }
+                None => {
+                    debug!("skipping way {} with missing nodes", way.idx());
+                    stats.num_skipped += 1;
+                }
+            }
+        }
+
+        for relation in archive.relation_refs() {
+            if !self.matches(ElementType::Relation, relation.tags()) {
+                continue;
+            }
+            let polygons = match osmflat::relation_polygons(relation) {
+                Ok(polygons) => polygons,
+                Err(GeometryError::NotAnArea) => continue,
+                Err(e) => {
+                    debug!("skipping relation {}: {}", relation.idx(), e);
+                    stats.num_skipped += 1;
+                    continue;
+                }
+            };
+            let in_area = self.in_area(
+                polygons
+                    .iter()
+                    .flat_map(|polygon| polygon.outer.iter().cloned()),
+            );
+            if !in_area {
+                continue;
+            }
+            let geometry = json!({
+                "type": "MultiPolygon",
+                "coordinates": polygons.iter().map(polygon_coordinates).collect::<Vec<_>>(),
+            });
+            let id = self.id("r", relation.id());
+            writer.write(&feature(id, geometry, relation.tags()))?;
+            stats.num_relations += 1;
+        }
+
+        writer.finish()?;
+        Ok(stats)
+    }
+
+    /// Checks whether an element is tagged and matches the filter.
+    fn matches<'a>(
+        &self,
+        element_type: ElementType,
+        tags: impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone,
+    ) -> bool {
+        let is_tagged = tags.clone().next().is_some();
+        is_tagged
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(element_type, tags))
+    }
+
+    /// Checks whether any of the `(lat, lon)` coordinates is inside the area.
+    fn in_area(&self, mut coords: impl Iterator<Item = (f64, f64)>) -> bool {
+        match self.area {
+            Some(ref area) => coords.any(|(lat, lon)| area.contains(lon, lat)),
+            None => true,
+        }
+    }
+
+    fn id(&self, prefix: &str, id: Option<u64>) -> Option<String> {
+        if self.ids {
+            id.map(|id| format!("{}{}", prefix, id))
+        } else {
+            None
+        }
+    }
+}
+
+/// Writes features as a feature collection or as a sequence.
+struct FeatureWriter<W: Write> {
+    output: W,
+    format: ExportFormat,
+    num_features: usize,
+}
+
+impl<W: Write> FeatureWriter<W> {
+    fn new(mut output: W, format: ExportFormat) -> Result<Self, Error> {
+        if format == ExportFormat::GeoJson {
+            write!(output, r#"{{"type":"FeatureCollection","features":["#)?;
+        }
+        Ok(Self {
+            output,
+            format,
+            num_features: 0,
+        })
+    }
+
+    fn write(&mut self, feature: &Value) -> Result<(), Error> {
+        match self.format {
+            ExportFormat::GeoJson => {
+                let separator = if self.num_features == 0 { "\n" } else { ",\n" };
+                self.output.write_all(separator.as_bytes())?;
+                serde_json::to_writer(&mut self.output, feature)?;
+            }
+            ExportFormat::GeoJsonSeq => {
+                serde_json::to_writer(&mut self.output, feature)?;
+                self.output.write_all(b"\n")?;
+            }
+        }
+        self.num_features += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        if self.format == ExportFormat::GeoJson {
+            self.output.write_all(b"\n]}\n")?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+fn feature<'a>(
+    id: Option<String>,
+    geometry: Value,
+    tags: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+) -> Value {
+    let properties: Map<String, Value> = tags
+        .map(|(key, value)| {
+            (
+                String::from_utf8_lossy(key).into_owned(),
+                Value::String(String::from_utf8_lossy(value).into_owned()),
+            )
+        })
+        .collect();
+    let mut feature = json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    });
+    if let Some(id) = id {
+        feature["id"] = Value::String(id);
+    }
+    feature
+}
+
+/// Returns a line string, or a polygon if the way describes an area, or
+/// `None` if any of its nodes is missing.
+fn way_geometry(way: WayRef) -> Option<Value> {
+    let nodes: Vec<_> = way.nodes().collect::<Option<_>>()?;
+    let is_closed = nodes.len() >= 4 && nodes[0].idx() == nodes[nodes.len() - 1].idx();
+    if is_area(is_closed, way.tags()) {
+        let polygon = osmflat::way_polygon(way).ok()?;
+        Some(json!({ "type": "Polygon", "coordinates": polygon_coordinates(&polygon) }))
+    } else if nodes.len() >= 2 {
+        let coordinates: Vec<_> = nodes.iter().map(|node| position(node.lat_lon())).collect();
+        Some(json!({ "type": "LineString", "coordinates": coordinates }))
+    } else {
+        None
+    }
+}
+
+/// Checks whether a way with the given tags describes an area.
+///
+/// Closed ways are areas, unless they are tagged with `area=no`, or with a key
+/// of a linear feature and not with `area=yes`.
+fn is_area<'a>(
+    is_closed: bool,
+    mut tags: impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone,
+) -> bool {
+    if !is_closed {
+        return false;
+    }
+    match tags.clone().find(|&(key, _)| key == b"area") {
+        Some((_, value)) => value != b"no",
+        None => !tags.any(|(key, _)| LINEAR_KEYS.contains(&key)),
+    }
+}
+
+/// GeoJSON position `[lon, lat]` of `(lat, lon)` coordinates.
+fn position((lat, lon): (f64, f64)) -> Value {
+    json!([lon, lat])
+}
+
+fn polygon_coordinates(polygon: &Polygon) -> Value {
+    let ring = |ring: &Vec<(f64, f64)>| -> Value {
+        ring.iter()
+            .cloned()
+            .map(position)
+            .collect::<Vec<_>>()
+            .into()
+    };
+    std::iter::once(&polygon.outer)
+        .chain(&polygon.inners)
+        .map(ring)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::test::TestArchive;
+
+    fn tags<'a>(
+        tags: &'a [(&'a str, &'a str)],
+    ) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone {
+        tags.iter()
+            .map(|(key, value)| (key.as_bytes(), value.as_bytes()))
+    }
+
+    #[test]
+    fn test_is_area() {
+        assert!(is_area(true, tags(&[("building", "yes")])));
+        assert!(!is_area(false, tags(&[("building", "yes")])));
+        assert!(!is_area(true, tags(&[("highway", "residential")])));
+        assert!(is_area(
+            true,
+            tags(&[("highway", "pedestrian"), ("area", "yes")])
+        ));
+        assert!(!is_area(
+            true,
+            tags(&[("leisure", "track"), ("area", "no")])
+        ));
+    }
+
+    #[test]
+    fn test_feature() {
+        let geometry = json!({ "type": "Point", "coordinates": position((52.5, 13.4)) });
+        let feature = feature(Some("n1".into()), geometry, tags(&[("amenity", "pub")]));
+        assert_eq!(
+            feature,
+            json!({
+                "type": "Feature",
+                "id": "n1",
+                "geometry": { "type": "Point", "coordinates": [13.4, 52.5] },
+                "properties": { "amenity": "pub" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_polygon_coordinates() {
+        let polygon = Polygon {
+            outer: vec![(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (0.0, 0.0)],
+            inners: vec![vec![(1.0, 1.0), (1.5, 1.5), (1.0, 1.5), (1.0, 1.0)]],
+        };
+        assert_eq!(
+            polygon_coordinates(&polygon),
+            json!([
+                [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 0.0]],
+                [[1.0, 1.0], [1.5, 1.5], [1.5, 1.0], [1.0, 1.0]],
+            ])
+        );
+    }
+
+    #[test]
+    fn test_feature_writer() {
+        let features = [json!({ "type": "Feature" }), json!({ "type": "Feature" })];
+
+        let mut output = Vec::new();
+        let mut writer = FeatureWriter::new(&mut output, ExportFormat::GeoJson).unwrap();
+        for feature in &features {
+            writer.write(feature).unwrap();
+        }
+        writer.finish().unwrap();
+        let collection: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(collection["type"], "FeatureCollection");
+        assert_eq!(collection["features"], json!(features));
+
+        let mut output = Vec::new();
+        let mut writer = FeatureWriter::new(&mut output, ExportFormat::GeoJsonSeq).unwrap();
+        for feature in &features {
+            writer.write(feature).unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"type\":\"Feature\"}\n{\"type\":\"Feature\"}\n"
+        );
+
+        let mut output = Vec::new();
+        FeatureWriter::new(&mut output, ExportFormat::GeoJson)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let collection: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(collection["features"], json!([]));
+    }
+
+    #[test]
+    fn test_export() {
+        let xml = r#"<osm>
+          <node id="1" lat="0.0" lon="0.0"><tag k="amenity" v="pub"/></node>
+          <node id="2" lat="0.0" lon="1.0"/>
+          <node id="3" lat="1.0" lon="1.0"><tag k="amenity" v="cafe"/></node>
+          <way id="10"><nd ref="1"/><nd ref="2"/><tag k="highway" v="path"/></way>
+          <way id="11">
+            <nd ref="1"/><nd ref="2"/><nd ref="3"/><nd ref="1"/>
+            <tag k="building" v="yes"/>
+          </way>
+          <relation id="100">
+            <member type="way" ref="11" role="outer"/>
+            <tag k="type" v="multipolygon"/>
+            <tag k="landuse" v="grass"/>
+          </relation>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler.with_ids(true)).unwrap();
+
+        let mut output = Vec::new();
+        let stats = Exporter::new(archive.path())
+            .with_format(ExportFormat::GeoJsonSeq)
+            .with_ids(true)
+            .export(&mut output)
+            .unwrap();
+        assert_eq!(
+            (
+                stats.num_nodes,
+                stats.num_ways,
+                stats.num_relations,
+                stats.num_skipped
+            ),
+            (2, 2, 1, 0)
+        );
+        let features: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ids: Vec<_> = features.iter().map(|feature| &feature["id"]).collect();
+        assert_eq!(ids, vec!["n1", "n3", "w10", "w11", "r100"]);
+        let types: Vec<_> = features
+            .iter()
+            .map(|feature| &feature["geometry"]["type"])
+            .collect();
+        assert_eq!(
+            types,
+            vec!["Point", "Point", "LineString", "Polygon", "MultiPolygon"]
+        );
+    }
+}
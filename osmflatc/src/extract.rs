@@ -0,0 +1,632 @@
+//! Extraction of the elements inside an area given by a bounding box or a
+//! polygon, and of the elements matching a tag filter.
+//!
+//! The selection of elements is computed in a pass over the input before the
+//! compilation:
+//!
+//! * nodes inside the area and matching the filter are selected,
+//! * ways matching the filter and referencing at least one node inside the
+//!   area are selected; for complete ways, all their nodes are selected as
+//!   well, when keeping way nodes, their nodes inside the area,
+//! * relations matching the filter and having at least one selected node,
+//!   way or relation as member are selected. Without an area, all relations
+//!   matching the filter are selected.
+//!
+//! Without an area all elements are inside, without a filter all elements
+//! match.
+//!
+//! References to elements which are not selected are unresolved in the
+//! compiled archive, i.e. `INVALID_IDX`.
+
+use crate::filter::TagFilter;
+use crate::ids::IdSet;
+use crate::osmpbf::{self, read_block, BlockIndex, BlockType};
+use crate::osmxml::ElementType;
+use crate::parallel;
+use crate::Error;
+
+use log::info;
+
+use std::io;
+use std::path::Path;
+
+/// Closed ring of `(lon, lat)` coordinates in degrees.
+type Ring = Vec<(f64, f64)>;
+
+/// Area given by a bounding box and optionally a polygon, in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Area {
+    left: f64,
+    right: f64,
+    top: f64,
+    bottom: f64,
+    /// Rings of the polygon (outer rings and holes), empty for a bounding box
+    rings: Vec<Ring>,
+}
+
+impl Area {
+    /// Parses a bounding box of the form `left,bottom,right,top`.
+    pub fn from_bbox(s: &str) -> Result<Self, Error> {
+        let coords = s
+            .split(',')
+            .map(|x| x.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid bounding box '{}'", s))?;
+        match coords[..] {
+            [left, bottom, right, top] if left <= right && bottom <= top => Ok(Self {
+                left,
+                right,
+                top,
+                bottom,
+                rings: Vec::new(),
+            }),
+            _ => Err(format!(
+                "invalid bounding box '{}', expected left,bottom,right,top",
+                s
+            )
+            .into()),
+        }
+    }
+
+    /// Reads a polygon from an osmosis polygon file (`.poly`) or a GeoJSON
+    /// file with polygons or multipolygons.
+    pub fn from_polygon_file(path: &Path) -> Result<Self, Error> {
+        let data = std::fs::read_to_string(path)?;
+        let is_poly = path.extension().is_some_and(|ext| ext == "poly");
+        let rings = if is_poly {
+            parse_poly(&data)?
+        } else {
+            parse_geojson(&data)?
+        };
+        Self::from_rings(rings)
+    }
+
+    fn from_rings(rings: Vec<Ring>) -> Result<Self, Error> {
+        let mut points = rings.iter().flatten();
+        let &(lon, lat) = points.next().ok_or("polygon is empty")?;
+        let mut area = Self {
+            left: lon,
+            right: lon,
+            top: lat,
+            bottom: lat,
+            rings: Vec::new(),
+        };
+        for &(lon, lat) in points {
+            area.left = area.left.min(lon);
+            area.right = area.right.max(lon);
+            area.top = area.top.max(lat);
+            area.bottom = area.bottom.min(lat);
+        }
+        area.rings = rings;
+        Ok(area)
+    }
+
+    /// Returns the bounding box of the area as a PBF header bounding box.
+    pub fn header_bbox(&self) -> osmpbf::HeaderBBox {
+        let nano = |degrees: f64| (degrees * 1e9).round() as i64;
+        osmpbf::HeaderBBox {
+            left: nano(self.left),
+            right: nano(self.right),
+            top: nano(self.top),
+            bottom: nano(self.bottom),
+        }
+    }
+
+    /// Checks whether the point is inside the area (including its boundary
+    /// for bounding boxes).
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        if lon < self.left || self.right < lon || lat < self.bottom || self.top < lat {
+            return false;
+        }
+        if self.rings.is_empty() {
+            return true;
+        }
+
+        // even-odd rule over all rings, which handles holes and multiple
+        // outer rings
+        let mut inside = false;
+        for ring in &self.rings {
+            for (&(x0, y0), &(x1, y1)) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                if (y0 > lat) != (y1 > lat) && lon < x0 + (lat - y0) * (x1 - x0) / (y1 - y0) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+/// Parses an osmosis polygon file.
+///
+/// See <https://wiki.openstreetmap.org/wiki/Osmosis/Polygon_Filter_File_Format>.
+fn parse_poly(data: &str) -> Result<Vec<Ring>, Error> {
+    let mut lines = data.lines().map(str::trim).filter(|line| !line.is_empty());
+    lines.next().ok_or("empty polygon file")?; // name of the polygon
+    let mut rings = Vec::new();
+    loop {
+        // name of the ring or end of file, holes are prefixed with '!', which
+        // does not matter for the even-odd rule
+        match lines.next() {
+            Some("END") | None => break,
+            Some(_) => (),
+        }
+        let mut ring = Vec::new();
+        loop {
+            let line = lines.next().ok_or("unexpected end of polygon file")?;
+            if line == "END" {
+                break;
+            }
+            let mut coords = line.split_whitespace().map(str::parse::<f64>);
+            match (coords.next(), coords.next()) {
+                (Some(Ok(lon)), Some(Ok(lat))) => ring.push((lon, lat)),
+                _ => return Err(format!("invalid line in polygon file: '{}'", line).into()),
+            }
+        }
+        rings.push(ring);
+    }
+    Ok(rings)
+}
+
+/// Parses the polygons and multipolygons of a GeoJSON geometry, feature or
+/// feature collection, other geometries are ignored.
+fn parse_geojson(data: &str) -> Result<Vec<Ring>, Error> {
+    let json: serde_json::Value = serde_json::from_str(data)?;
+    let mut rings = Vec::new();
+    collect_rings(&json, &mut rings)?;
+    Ok(rings)
+}
+
+fn collect_rings(json: &serde_json::Value, rings: &mut Vec<Ring>) -> Result<(), Error> {
+    match json["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in json_array(&json["features"])? {
+                collect_rings(feature, rings)?;
+            }
+        }
+        Some("Feature") => collect_rings(&json["geometry"], rings)?,
+        Some("GeometryCollection") => {
+            for geometry in json_array(&json["geometries"])? {
+                collect_rings(geometry, rings)?;
+            }
+        }
+        Some("Polygon") => rings.extend(parse_polygon(&json["coordinates"])?),
+        Some("MultiPolygon") => {
+            for polygon in json_array(&json["coordinates"])? {
+                rings.extend(parse_polygon(polygon)?);
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn json_array(value: &serde_json::Value) -> Result<&Vec<serde_json::Value>, Error> {
+    value.as_array().ok_or_else(|| "invalid GeoJSON".into())
+}
+
+fn parse_polygon(coordinates: &serde_json::Value) -> Result<Vec<Ring>, Error> {
+    let invalid = || -> Error { "invalid GeoJSON polygon coordinates".into() };
+    coordinates
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|ring| -> Result<Ring, Error> {
+            ring.as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|point| match (point[0].as_f64(), point[1].as_f64()) {
+                    (Some(lon), Some(lat)) => Ok((lon, lat)),
+                    _ => Err(invalid()),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Ids of the selected elements.
+#[derive(Debug, Default)]
+pub struct Selection {
+    nodes: IdSet,
+    ways: IdSet,
+    relations: IdSet,
+}
+
+impl Selection {
+    pub fn contains_node(&self, id: i64) -> bool {
+        self.nodes.contains(id as u64)
+    }
+
+    pub fn contains_way(&self, id: i64) -> bool {
+        self.ways.contains(id as u64)
+    }
+
+    pub fn contains_relation(&self, id: i64) -> bool {
+        self.relations.contains(id as u64)
+    }
+}
+
+/// Criteria for the selection of elements.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Criteria<'a> {
+    /// Area containing the selected elements, all elements if not set
+    pub area: Option<&'a Area>,
+    /// Whether to select all nodes of the selected ways
+    pub complete_ways: bool,
+    /// Filter of the selected elements by their tags
+    pub filter: Option<&'a TagFilter>,
+    /// Whether to select the nodes inside the area of the selected ways, even if they
+    /// don't match the filter
+    pub keep_way_nodes: bool,
+}
+
+/// Selects the elements matching `criteria` from the blocks of the input.
+pub fn select(
+    data: &[u8],
+    node_blocks: &[BlockIndex],
+    way_blocks: &[BlockIndex],
+    relation_blocks: &[BlockIndex],
+    criteria: Criteria,
+) -> Result<Selection, Error> {
+    let mut selection = Selection::default();
+    // nodes inside the area, only needed separately if nodes are filtered by tags
+    let mut inside_nodes = IdSet::new();
+
+    info!("Selecting nodes...");
+    parallel::parallel_process(
+        node_blocks.iter(),
+        |idx| -> io::Result<(Vec<i64>, Vec<i64>)> {
+            let block: osmpbf::PrimitiveBlock = read_block(data, idx)?;
+            Ok(select_nodes(&block, idx.block_type, criteria))
+        },
+        |ids| -> Result<(), Error> {
+            let (selected, inside) = ids?;
+            for id in selected {
+                selection.nodes.insert(id as u64);
+            }
+            for id in inside {
+                inside_nodes.insert(id as u64);
+            }
+            Ok(())
+        },
+    )?;
+
+    info!("Selecting ways...");
+    let mut way_nodes = Vec::new();
+    {
+        let (nodes, inside_nodes) = (&selection.nodes, &inside_nodes);
+        let selected_ways = &mut selection.ways;
+        let is_inside = |id: i64| match (criteria.area, criteria.filter) {
+            (None, _) => true,
+            (Some(_), None) => nodes.contains(id as u64),
+            (Some(_), Some(_)) => inside_nodes.contains(id as u64),
+        };
+        parallel::parallel_process(
+            way_blocks.iter(),
+            |idx| -> io::Result<Vec<(i64, Vec<i64>)>> {
+                let block: osmpbf::PrimitiveBlock = read_block(data, idx)?;
+                let strings = &block.stringtable.s;
+                let mut result = Vec::new();
+                for way in block.primitivegroup.iter().flat_map(|g| &g.ways) {
+                    let matches = criteria.filter.is_none_or(|filter| {
+                        filter.matches(ElementType::Way, tags(strings, &way.keys, &way.vals))
+                    });
+                    if !matches {
+                        continue;
+                    }
+                    let mut refs = decode_deltas(&way.refs);
+                    if criteria.area.is_some() && !refs.iter().any(|&id| is_inside(id)) {
+                        continue;
+                    }
+                    if !criteria.complete_ways {
+                        refs.retain(|&id| is_inside(id));
+                    }
+                    result.push((way.id, refs));
+                }
+                Ok(result)
+            },
+            |ways| -> Result<(), Error> {
+                for (id, refs) in ways? {
+                    selected_ways.insert(id as u64);
+                    if criteria.complete_ways || criteria.keep_way_nodes {
+                        way_nodes.extend(refs);
+                    }
+                }
+                Ok(())
+            },
+        )?;
+    }
+    drop(inside_nodes);
+
+    info!("Selecting relations...");
+    // relations which are not selected yet, with their relation members
+    let mut candidates = Vec::new();
+    {
+        let (nodes, ways) = (&selection.nodes, &selection.ways);
+        let selected_relations = &mut selection.relations;
+        parallel::parallel_process(
+            relation_blocks.iter(),
+            |idx| -> io::Result<Vec<(i64, bool, Vec<i64>)>> {
+                let block: osmpbf::PrimitiveBlock = read_block(data, idx)?;
+                let strings = &block.stringtable.s;
+                let mut result = Vec::new();
+                for relation in block.primitivegroup.iter().flat_map(|g| &g.relations) {
+                    let matches = criteria.filter.is_none_or(|filter| {
+                        let tags = tags(strings, &relation.keys, &relation.vals);
+                        filter.matches(ElementType::Relation, tags)
+                    });
+                    if !matches {
+                        continue;
+                    }
+                    if criteria.area.is_none() {
+                        result.push((relation.id, true, Vec::new()));
+                        continue;
+                    }
+                    let mut selected = false;
+                    let mut relation_members = Vec::new();
+                    let memids = decode_deltas(&relation.memids);
+                    for (&member_type, id) in relation.types.iter().zip(memids) {
+                        use osmpbf::relation::MemberType;
+                        match MemberType::from_i32(member_type) {
+                            Some(MemberType::Node) => selected |= nodes.contains(id as u64),
+                            Some(MemberType::Way) => selected |= ways.contains(id as u64),
+                            Some(MemberType::Relation) => relation_members.push(id),
+                            None => (),
+                        }
+                    }
+                    result.push((relation.id, selected, relation_members));
+                }
+                Ok(result)
+            },
+            |relations| -> Result<(), Error> {
+                for (id, selected, relation_members) in relations? {
+                    if selected {
+                        selected_relations.insert(id as u64);
+                    } else if !relation_members.is_empty() {
+                        candidates.push((id, relation_members));
+                    }
+                }
+                Ok(())
+            },
+        )?;
+    }
+    // select relations with selected relation members until nothing changes
+    loop {
+        let relations = &mut selection.relations;
+        let num_selected = relations.len();
+        candidates.retain(|(id, members)| {
+            let selected = members.iter().any(|&id| relations.contains(id as u64));
+            if selected {
+                relations.insert(*id as u64);
+            }
+            !selected
+        });
+        if relations.len() == num_selected {
+            break;
+        }
+    }
+
+    for id in way_nodes {
+        selection.nodes.insert(id as u64);
+    }
+    info!(
+        "Selected {} nodes, {} ways and {} relations.",
+        selection.nodes.len(),
+        selection.ways.len(),
+        selection.relations.len()
+    );
+    Ok(selection)
+}
+
+/// Returns the tags of an element as key-value pairs.
+fn tags<'a>(
+    strings: &'a [Vec<u8>],
+    keys: &'a [u32],
+    vals: &'a [u32],
+) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone {
+    keys.iter()
+        .zip(vals)
+        .map(move |(&k, &v)| (&strings[k as usize][..], &strings[v as usize][..]))
+}
+
+/// Returns the ids of the selected nodes of the block, and the ids of the nodes
+/// inside the area if they are needed separately (area and filter given).
+fn select_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    block_type: BlockType,
+    criteria: Criteria,
+) -> (Vec<i64>, Vec<i64>) {
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    let degrees = |offset: i64, value: i64| (offset + granularity * value) as f64 * 1e-9;
+    let strings = &block.stringtable.s;
+
+    let mut selected = Vec::new();
+    let mut inside = Vec::new();
+    let mut add = |id: i64, lon: i64, lat: i64, matches: &dyn Fn() -> bool| {
+        let is_inside = criteria
+            .area
+            .is_none_or(|area| area.contains(degrees(lon_offset, lon), degrees(lat_offset, lat)));
+        if !is_inside {
+            return;
+        }
+        if criteria.filter.is_some() && criteria.area.is_some() {
+            inside.push(id);
+        }
+        if criteria.filter.is_none() || matches() {
+            selected.push(id);
+        }
+    };
+    for group in &block.primitivegroup {
+        if block_type == BlockType::DenseNodes {
+            let dense = match group.dense {
+                Some(ref dense) => dense,
+                None => continue,
+            };
+            let (mut id, mut lat, mut lon) = (0, 0, 0);
+            let mut tags_offset = 0;
+            for i in 0..dense.id.len() {
+                id += dense.id[i];
+                lat += dense.lat[i];
+                lon += dense.lon[i];
+                // keys and values of the node, terminated by a 0
+                let tags_start = tags_offset;
+                while tags_offset < dense.keys_vals.len() && dense.keys_vals[tags_offset] != 0 {
+                    tags_offset += 2;
+                }
+                let keys_vals = dense.keys_vals.get(tags_start..tags_offset).unwrap_or(&[]);
+                tags_offset += 1;
+                add(id, lon, lat, &|| {
+                    let tags = keys_vals
+                        .chunks_exact(2)
+                        .map(|kv| (&strings[kv[0] as usize][..], &strings[kv[1] as usize][..]));
+                    criteria
+                        .filter
+                        .is_none_or(|filter| filter.matches(ElementType::Node, tags))
+                });
+            }
+        } else {
+            for node in &group.nodes {
+                add(node.id, node.lon, node.lat, &|| {
+                    let tags = tags(strings, &node.keys, &node.vals);
+                    criteria
+                        .filter
+                        .is_none_or(|filter| filter.matches(ElementType::Node, tags))
+                });
+            }
+        }
+    }
+    (selected, inside)
+}
+
+fn decode_deltas(deltas: &[i64]) -> Vec<i64> {
+    deltas
+        .iter()
+        .scan(0, |value, delta| {
+            *value += delta;
+            Some(*value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const POLY: &str = "test
+outer
+   0.0 0.0
+   10.0 0.0
+   10.0 10.0
+   0.0 10.0
+END
+!hole
+   4.0 4.0
+   6.0 4.0
+   6.0 6.0
+   4.0 6.0
+END
+END
+";
+
+    #[test]
+    fn test_bbox() {
+        let area = Area::from_bbox("13.3,52.5,13.4,52.6").unwrap();
+        assert!(area.contains(13.35, 52.55));
+        assert!(area.contains(13.3, 52.5));
+        assert!(!area.contains(13.45, 52.55));
+        assert!(Area::from_bbox("13.3,52.5,13.4").is_err());
+        assert!(Area::from_bbox("13.4,52.5,13.3,52.6").is_err());
+        assert!(Area::from_bbox("a,b,c,d").is_err());
+    }
+
+    #[test]
+    fn test_poly() {
+        let rings = parse_poly(POLY).unwrap();
+        assert_eq!(rings.len(), 2);
+        let area = Area::from_rings(rings).unwrap();
+        assert_eq!(
+            (area.left, area.right, area.top, area.bottom),
+            (0., 10., 10., 0.)
+        );
+        assert!(area.contains(2., 2.));
+        assert!(!area.contains(5., 5.)); // in hole
+        assert!(!area.contains(11., 5.));
+        assert!(parse_poly("test\nouter\n 0.0 0.0\n").is_err());
+    }
+
+    #[test]
+    fn test_geojson() {
+        let json = r#"{
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]],
+                        [[[2, 2], [3, 2], [3, 3], [2, 3], [2, 2]]]
+                    ]
+                }
+            }]
+        }"#;
+        let area = Area::from_rings(parse_geojson(json).unwrap()).unwrap();
+        assert!(area.contains(0.5, 0.5));
+        assert!(area.contains(2.5, 2.5));
+        assert!(!area.contains(1.5, 1.5));
+        assert!(parse_geojson(r#"{"type": "Polygon", "coordinates": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_select_dense_nodes() {
+        let strings = ["", "amenity", "pub", "name", "x"];
+        let block = osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            },
+            primitivegroup: vec![osmpbf::PrimitiveGroup {
+                dense: Some(osmpbf::DenseNodes {
+                    id: vec![1, 1, 1, 1],
+                    lat: vec![0, 0, 0, 200_000_000],
+                    lon: vec![0, 0, 0, 0],
+                    keys_vals: vec![3, 4, 1, 2, 0, 0, 1, 2, 0, 1, 2, 0],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let area = Area::from_bbox("-1,-1,1,1").unwrap();
+        let filter = TagFilter::parse(vec!["n/amenity=pub"]).unwrap();
+        let criteria = Criteria {
+            filter: Some(&filter),
+            ..Default::default()
+        };
+        let (selected, inside) = select_nodes(&block, BlockType::DenseNodes, criteria);
+        assert_eq!((selected, inside), (vec![1, 3, 4], vec![]));
+
+        let criteria = Criteria {
+            area: Some(&area),
+            ..criteria
+        };
+        let (selected, inside) = select_nodes(&block, BlockType::DenseNodes, criteria);
+        assert_eq!((selected, inside), (vec![1, 3], vec![1, 2, 3]));
+
+        // tagless block
+        let mut block = block;
+        block.primitivegroup[0]
+            .dense
+            .as_mut()
+            .unwrap()
+            .keys_vals
+            .clear();
+        let (selected, _) = select_nodes(&block, BlockType::DenseNodes, criteria);
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn test_decode_deltas() {
+        assert_eq!(decode_deltas(&[5, 2, -5]), vec![5, 7, 2]);
+    }
+}
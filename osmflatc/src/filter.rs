@@ -0,0 +1,329 @@
+//! Filter of elements by their tags, and policy for dropping tags.
+//!
+//! A filter consists of expressions in the style of `osmium tags-filter`:
+//!
+//! ```text
+//! [TYPES/]KEY[=VALUE[,VALUE...]]
+//! [TYPES/]KEY!=VALUE[,VALUE...]
+//! ```
+//!
+//! `TYPES` is any combination of `n` (nodes), `w` (ways) and `r` (relations)
+//! and defaults to all types. Keys and values ending with `*` match by
+//! prefix. An element matches the filter if any of its tags matches any of
+//! the expressions for its type. For example, `w/highway n/amenity=pub,bar
+//! r/type=route` matches all highways, pubs and bars, and route relations.
+//!
+//! A tag policy consists of key patterns of tags to keep and to drop, e.g.
+//! `created_by,source:*,note,fixme`. It can also be read from a JSON file:
+//!
+//! ```json
+//! { "keep": ["highway", "name*"], "drop": ["created_by", "source:*"] }
+//! ```
+
+use crate::osmxml::ElementType;
+use crate::Error;
+
+use std::fmt;
+use std::path::Path;
+
+/// Pattern matching a key or value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pattern {
+    Exact(String),
+    Prefix(String),
+}
+
+impl Pattern {
+    fn new(s: &str) -> Self {
+        match s.strip_suffix('*') {
+            Some(prefix) => Pattern::Prefix(prefix.into()),
+            None => Pattern::Exact(s.into()),
+        }
+    }
+
+    fn matches(&self, s: &[u8]) -> bool {
+        match self {
+            Pattern::Exact(pattern) => pattern.as_bytes() == s,
+            Pattern::Prefix(prefix) => s.starts_with(prefix.as_bytes()),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Exact(pattern) => write!(f, "{}", pattern),
+            Pattern::Prefix(prefix) => write!(f, "{}*", prefix),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expression {
+    types: Vec<ElementType>,
+    key: Pattern,
+    /// Whether the values are negated (`!=`)
+    negated: bool,
+    /// Values of the tag, empty to match any value
+    values: Vec<Pattern>,
+}
+
+impl Expression {
+    fn parse(s: &str) -> Result<Self, Error> {
+        let invalid = || format!("invalid filter expression '{}'", s);
+        let (types, tag) = match s.split_once('/') {
+            Some((types, tag)) => {
+                let types = types
+                    .chars()
+                    .map(|c| match c {
+                        'n' => Ok(ElementType::Node),
+                        'w' => Ok(ElementType::Way),
+                        'r' => Ok(ElementType::Relation),
+                        _ => Err(invalid()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (types, tag)
+            }
+            None => (
+                vec![ElementType::Node, ElementType::Way, ElementType::Relation],
+                s,
+            ),
+        };
+        let (key, negated, values) = match tag.split_once('=') {
+            Some((key, values)) => match key.strip_suffix('!') {
+                Some(key) => (key, true, values.split(',').map(Pattern::new).collect()),
+                None => (key, false, values.split(',').map(Pattern::new).collect()),
+            },
+            None => (tag, false, Vec::new()),
+        };
+        if types.is_empty() || key.is_empty() {
+            return Err(invalid().into());
+        }
+        Ok(Self {
+            types,
+            key: Pattern::new(key),
+            negated,
+            values,
+        })
+    }
+
+    fn matches(&self, key: &[u8], value: &[u8]) -> bool {
+        if !self.key.matches(key) {
+            return false;
+        }
+        if self.values.is_empty() {
+            return true;
+        }
+        self.values.iter().any(|pattern| pattern.matches(value)) != self.negated
+    }
+}
+
+/// Filter of elements by their tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    expressions: Vec<Expression>,
+}
+
+impl TagFilter {
+    /// Parses a filter from whitespace separated expressions.
+    pub fn parse<'a>(expressions: impl IntoIterator<Item = &'a str>) -> Result<Self, Error> {
+        let expressions = expressions
+            .into_iter()
+            .flat_map(str::split_whitespace)
+            .map(Expression::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { expressions })
+    }
+
+    /// Checks whether an element with the given tags matches the filter.
+    pub fn matches<'a>(
+        &self,
+        element_type: ElementType,
+        tags: impl Iterator<Item = (&'a [u8], &'a [u8])> + Clone,
+    ) -> bool {
+        self.expressions
+            .iter()
+            .filter(|expression| expression.types.contains(&element_type))
+            .any(|expression| {
+                tags.clone()
+                    .any(|(key, value)| expression.matches(key, value))
+            })
+    }
+}
+
+/// Policy for keeping and dropping tags by their keys.
+///
+/// If any keys to keep are given, only the tags with these keys are kept.
+/// Tags with keys to drop are always dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagPolicy {
+    keep: Vec<Pattern>,
+    drop: Vec<Pattern>,
+}
+
+impl TagPolicy {
+    /// Reads a policy from a JSON file with the optional lists `keep` and
+    /// `drop` of key patterns.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let data = std::fs::read_to_string(path)?;
+        let json: serde_json::Value = serde_json::from_str(&data)?;
+        let invalid = || format!("invalid tag policy file '{}'", path.display());
+        let patterns = |name: &str| -> Result<Vec<String>, Error> {
+            match &json[name] {
+                serde_json::Value::Null => Ok(Vec::new()),
+                serde_json::Value::Array(values) => values
+                    .iter()
+                    .map(|value| value.as_str().map(String::from).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()
+                    .map_err(Error::from),
+                _ => Err(invalid().into()),
+            }
+        };
+        if !json.is_object() {
+            return Err(invalid().into());
+        }
+        let mut policy = Self::default();
+        policy.add(&patterns("keep")?, &patterns("drop")?);
+        Ok(policy)
+    }
+
+    /// Adds comma separated patterns of keys to keep and to drop.
+    pub fn add(&mut self, keep: &[String], drop: &[String]) {
+        let parse = |patterns: &[String]| -> Vec<Pattern> {
+            patterns
+                .iter()
+                .flat_map(|patterns| patterns.split(','))
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(Pattern::new)
+                .collect()
+        };
+        self.keep.extend(parse(keep));
+        self.drop.extend(parse(drop));
+    }
+
+    /// Checks whether tags with the key are kept.
+    pub fn keeps(&self, key: &[u8]) -> bool {
+        (self.keep.is_empty() || self.keep.iter().any(|pattern| pattern.matches(key)))
+            && !self.drop.iter().any(|pattern| pattern.matches(key))
+    }
+
+    /// Checks whether all tags are kept.
+    pub fn is_empty(&self) -> bool {
+        self.keep.is_empty() && self.drop.is_empty()
+    }
+}
+
+/// Formats the policy as `keep=PATTERNS;drop=PATTERNS`, omitting empty lists.
+impl fmt::Display for TagPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lists = [("keep", &self.keep), ("drop", &self.drop)];
+        let lists = lists.iter().filter(|(_, patterns)| !patterns.is_empty());
+        for (i, (name, patterns)) in lists.enumerate() {
+            if i > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}=", name)?;
+            for (j, pattern) in patterns.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", pattern)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(filter: &TagFilter, element_type: ElementType, tags: &[(&str, &str)]) -> bool {
+        filter.matches(
+            element_type,
+            tags.iter().map(|(k, v)| (k.as_bytes(), v.as_bytes())),
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        let filter = TagFilter::parse(vec!["w/highway n/amenity=pub,bar", "r/type=route"]).unwrap();
+        assert_eq!(filter.expressions.len(), 3);
+        assert_eq!(filter.expressions[0].types, vec![ElementType::Way]);
+        assert_eq!(
+            filter.expressions[1].values,
+            vec![Pattern::Exact("pub".into()), Pattern::Exact("bar".into())]
+        );
+        assert_eq!(
+            TagFilter::parse(vec!["name"]).unwrap().expressions[0]
+                .types
+                .len(),
+            3
+        );
+        assert!(TagFilter::parse(vec!["x/highway"]).is_err());
+        assert!(TagFilter::parse(vec!["w/=primary"]).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter = TagFilter::parse(vec![
+            "w/highway n/amenity=pub,bar r/type!=multipolygon nw/addr:*",
+        ])
+        .unwrap();
+
+        assert!(matches(
+            &filter,
+            ElementType::Way,
+            &[("highway", "primary")]
+        ));
+        assert!(!matches(
+            &filter,
+            ElementType::Node,
+            &[("highway", "crossing")]
+        ));
+        assert!(matches(
+            &filter,
+            ElementType::Node,
+            &[("name", "x"), ("amenity", "bar")]
+        ));
+        assert!(!matches(&filter, ElementType::Node, &[("amenity", "cafe")]));
+        assert!(matches(
+            &filter,
+            ElementType::Relation,
+            &[("type", "route")]
+        ));
+        assert!(!matches(
+            &filter,
+            ElementType::Relation,
+            &[("type", "multipolygon")]
+        ));
+        assert!(!matches(&filter, ElementType::Relation, &[("name", "x")]));
+        assert!(matches(&filter, ElementType::Node, &[("addr:street", "x")]));
+        assert!(!matches(&filter, ElementType::Way, &[]));
+    }
+
+    #[test]
+    fn test_tag_policy() {
+        let mut policy = TagPolicy::default();
+        assert!(policy.is_empty());
+        assert!(policy.keeps(b"created_by"));
+
+        policy.add(&[], &["created_by,source:*".into(), "note".into()]);
+        assert!(!policy.keeps(b"created_by"));
+        assert!(!policy.keeps(b"source:geometry"));
+        assert!(policy.keeps(b"source"));
+        assert!(policy.keeps(b"highway"));
+        assert_eq!(policy.to_string(), "drop=created_by,source:*,note");
+
+        policy.add(&["highway, name*".into()], &["name:de".into()]);
+        assert!(policy.keeps(b"highway"));
+        assert!(policy.keeps(b"name:en"));
+        assert!(!policy.keeps(b"name:de"));
+        assert!(!policy.keeps(b"amenity"));
+        assert_eq!(
+            policy.to_string(),
+            "keep=highway,name*;drop=created_by,source:*,note,name:de"
+        );
+    }
+}
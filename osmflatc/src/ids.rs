@@ -1,5 +1,14 @@
+use crate::Error;
+
+use ahash::{AHashMap, AHashSet};
+use itertools::Either;
+
 const ID_BLOCK_SIZE: usize = 1 << 24;
 const DENSE_LOOKUP_BLOCK_SIZE: usize = 1 << 4;
+const ID_PAGE_BITS: u32 = 16;
+const ID_PAGE_SIZE: usize = 1 << ID_PAGE_BITS;
+/// Max number of ids in a sparse page (about the size of a dense page)
+const SPARSE_PAGE_MAX_LEN: usize = ID_PAGE_SIZE / 32;
 
 /// An IdBlock can either be Sparse or Dense
 /// Sparse: A sorted list of ids, the position determines the index
@@ -23,7 +32,7 @@ impl IdBlock {
             IdBlock::Dense { offsets, includes } => {
                 let last_bits: u32 = includes[includes.len() - DENSE_LOOKUP_BLOCK_SIZE..]
                     .iter()
-                    .map(|x| x.count_ones() as u32)
+                    .map(|x| x.count_ones())
                     .sum();
                 *offsets.last().unwrap() + last_bits
             }
@@ -60,7 +69,7 @@ impl IdBlock {
                 offsets[block + 1] = includes
                     [block * DENSE_LOOKUP_BLOCK_SIZE..(block + 1) * DENSE_LOOKUP_BLOCK_SIZE]
                     .iter()
-                    .map(|x| x.count_ones() as u32)
+                    .map(|x| x.count_ones())
                     .sum();
             }
             for block in 0..offsets.len() - 1 {
@@ -69,6 +78,17 @@ impl IdBlock {
         }
     }
 
+    // iterate over the truncated ids in the block in ascending order
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        match self {
+            IdBlock::Sparse(ids) => Either::Left(ids.iter().copied()),
+            IdBlock::Dense { includes, .. } => Either::Right(
+                (0..ID_BLOCK_SIZE as u32)
+                    .filter(move |&x| (includes[x as usize / 8] & (1 << (x % 8))) != 0),
+            ),
+        }
+    }
+
     // find the positions/index of a truncated id (if it is in the block)
     fn pos(&self, x: u32) -> Option<u32> {
         match self {
@@ -82,7 +102,7 @@ impl IdBlock {
                     let rest = x as usize % (8 * DENSE_LOOKUP_BLOCK_SIZE);
                     let mut result = offsets[offset_pos];
                     for i in start_block..start_block + rest {
-                        result += ((includes[i as usize / 8] & (1 << (i % 8))) != 0) as u32;
+                        result += ((includes[i / 8] & (1 << (i % 8))) != 0) as u32;
                     }
                     Some(result)
                 }
@@ -98,6 +118,14 @@ pub struct IdTable {
     data: Vec<(u64, IdBlock)>,
 }
 
+/// Error of inserting an id which is not greater than the previously inserted
+/// id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsortedIds {
+    pub id: u64,
+    pub last_id: u64,
+}
+
 #[derive(Debug, Default)]
 pub struct IdTableBuilder {
     // stored the same data as IdTable, but still in process of being build
@@ -112,9 +140,14 @@ impl IdTableBuilder {
     }
 
     /// Inserts an Id and returns a mapped index
-    pub fn insert(&mut self, x: u64) -> u64 {
+    ///
+    /// Ids are expected to be inserted in ascending order, otherwise an error
+    /// with the previously inserted id is returned.
+    pub fn insert(&mut self, x: u64) -> Result<u64, UnsortedIds> {
         if let Some(last_id) = self.last_id {
-            assert!(last_id < x, "Ids are expected to be sorted");
+            if last_id >= x {
+                return Err(UnsortedIds { id: x, last_id });
+            }
         }
         self.last_id = Some(x);
         let id_set = (x >> 24) as usize;
@@ -124,7 +157,7 @@ impl IdTableBuilder {
         self.data[id_set].insert((x % (1u64 << 24)) as u32);
         let result = self.next_id;
         self.next_id += 1;
-        result
+        Ok(result)
     }
 
     pub fn build(mut self) -> IdTable {
@@ -147,7 +180,7 @@ impl IdTableBuilder {
 impl IdTable {
     pub fn get(&self, x: u64) -> Option<u64> {
         let id_set = (x >> 24) as usize;
-        if id_set > self.data.len() {
+        if id_set >= self.data.len() {
             return None;
         }
         self.data[id_set]
@@ -155,11 +188,106 @@ impl IdTable {
             .pos((x % (1u64 << 24)) as u32)
             .map(|pos| self.data[id_set].0 + pos as u64)
     }
+
+    /// Writes the table into the blocks and id offsets of an `osmflat::IdIndex`
+    pub fn write(
+        &self,
+        mut blocks: flatdata::ExternalVector<osmflat::IdBlock>,
+        mut ids: flatdata::ExternalVector<osmflat::IdOffset>,
+    ) -> Result<(), Error> {
+        let mut count = 0;
+        for (offset, block) in &self.data {
+            blocks.grow()?.set_id_first_idx(*offset);
+            for x in block.iter() {
+                ids.grow()?.set_value(x);
+                count += 1;
+            }
+        }
+        // sentinel
+        blocks.grow()?.set_id_first_idx(count);
+        blocks.close()?;
+        ids.close()?;
+        Ok(())
+    }
+}
+
+/// An IdPage can either be Sparse or Dense
+/// Sparse: A set of truncated ids
+/// Dense: A bitset of the whole range
+#[derive(Debug)]
+enum IdPage {
+    Sparse(AHashSet<u16>),
+    Dense(Vec<u64>),
+}
+
+/// Set of ids, which can be inserted in any order
+///
+/// Ids are grouped into pages of ID_PAGE_SIZE consecutive ids, which
+/// are stored sparse or dense depending on the number of ids in the page.
+#[derive(Debug, Default)]
+pub struct IdSet {
+    pages: AHashMap<u64, IdPage>,
+    len: usize,
+}
+
+impl IdSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Inserts an id and returns whether it was not in the set before
+    pub fn insert(&mut self, x: u64) -> bool {
+        let page = self
+            .pages
+            .entry(x >> ID_PAGE_BITS)
+            .or_insert_with(|| IdPage::Sparse(AHashSet::new()));
+        let x = (x % ID_PAGE_SIZE as u64) as u16;
+        let inserted = match page {
+            IdPage::Sparse(ids) => {
+                let inserted = ids.insert(x);
+                if ids.len() > SPARSE_PAGE_MAX_LEN {
+                    let mut bits = vec![0; ID_PAGE_SIZE / 64];
+                    for id in ids.iter() {
+                        bits[*id as usize / 64] |= 1 << (id % 64);
+                    }
+                    *page = IdPage::Dense(bits);
+                }
+                inserted
+            }
+            IdPage::Dense(bits) => {
+                let inserted = (bits[x as usize / 64] & (1 << (x % 64))) == 0;
+                bits[x as usize / 64] |= 1 << (x % 64);
+                inserted
+            }
+        };
+        self.len += inserted as usize;
+        inserted
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        let page = match self.pages.get(&(x >> ID_PAGE_BITS)) {
+            Some(page) => page,
+            None => return false,
+        };
+        let x = (x % ID_PAGE_SIZE as u64) as u16;
+        match page {
+            IdPage::Sparse(ids) => ids.contains(&x),
+            IdPage::Dense(bits) => (bits[x as usize / 64] & (1 << (x % 64))) != 0,
+        }
+    }
+
+    /// Amount of ids in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::compiler::test::TestArchive;
+    use flatdata::MemoryResourceStorage;
+    use osmflat::{IdIndex, IdIndexBuilder};
 
     #[test]
     fn test_mapping_of_small_ints() {
@@ -167,7 +295,7 @@ mod test {
         let mut data = [9, 8, 7, 4, 3, 10, 13];
         data.sort_unstable();
         for x in data.iter() {
-            builder.insert(*x);
+            builder.insert(*x).unwrap();
         }
 
         let lookup = builder.build();
@@ -188,7 +316,7 @@ mod test {
         let mut data = [2, 1, 1_u64 << 33, 1_u64 << 34];
         data.sort_unstable();
         for x in data.iter() {
-            builder.insert(*x);
+            builder.insert(*x).unwrap();
         }
 
         let lookup = builder.build();
@@ -209,7 +337,7 @@ mod test {
         let mut data = [2, 1, 1_u64 << 33, 1_u64 << 34];
         data.sort_unstable();
         for x in data.iter() {
-            builder.insert(*x);
+            builder.insert(*x).unwrap();
         }
 
         let lookup = builder.build();
@@ -224,6 +352,52 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_unsorted() {
+        let mut builder = IdTableBuilder::new();
+        assert_eq!(builder.insert(5), Ok(0));
+        assert_eq!(builder.insert(3), Err(UnsortedIds { id: 3, last_id: 5 }));
+        assert_eq!(builder.insert(5), Err(UnsortedIds { id: 5, last_id: 5 }));
+        assert_eq!(builder.insert(7), Ok(1));
+    }
+
+    #[test]
+    fn test_write() {
+        let mut builder = IdTableBuilder::new();
+        let data = [1, 2, (1_u64 << 25) + 5, (1_u64 << 25) + 7];
+        for x in data.iter() {
+            builder.insert(*x).unwrap();
+        }
+
+        let storage = MemoryResourceStorage::new("/root/id_index");
+        let id_index = IdIndexBuilder::new(storage.clone()).unwrap();
+        let lookup = builder.build();
+        lookup
+            .write(
+                id_index.start_node_blocks().unwrap(),
+                id_index.start_node_ids().unwrap(),
+            )
+            .unwrap();
+        lookup
+            .write(
+                id_index.start_way_blocks().unwrap(),
+                id_index.start_way_ids().unwrap(),
+            )
+            .unwrap();
+        lookup
+            .write(
+                id_index.start_relation_blocks().unwrap(),
+                id_index.start_relation_ids().unwrap(),
+            )
+            .unwrap();
+
+        let id_index = IdIndex::open(storage).unwrap();
+        let blocks: Vec<_> = id_index.node_blocks().iter().map(|b| b.ids()).collect();
+        assert_eq!(blocks, vec![0..2, 2..2, 2..4]);
+        let ids: Vec<_> = id_index.node_ids().iter().map(|x| x.value()).collect();
+        assert_eq!(ids, vec![1, 2, 5, 7]);
+    }
+
     #[test]
     fn test_dense() {
         let mut builder = IdTableBuilder::new();
@@ -233,7 +407,7 @@ mod test {
         }
         data.sort_unstable();
         for x in data.iter() {
-            builder.insert(*x);
+            builder.insert(*x).unwrap();
         }
 
         let lookup = builder.build();
@@ -246,4 +420,53 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_id_set() {
+        let mut set = IdSet::new();
+        assert_eq!(set.len(), 0);
+        // unordered, with a dense page and a sparse page
+        let mut data: Vec<u64> = (0..ID_PAGE_SIZE as u64).step_by(7).collect();
+        data.extend(&[1_u64 << 34, 5, 1_u64 << 40]);
+        data.reverse();
+        for x in data.iter() {
+            set.insert(*x);
+        }
+        assert!(!set.insert(5));
+        assert_eq!(set.len(), data.len());
+        for x in data.iter() {
+            assert!(set.contains(*x));
+        }
+        for x in [1, 6, (1_u64 << 34) + 1, ID_PAGE_SIZE as u64].iter() {
+            assert!(!set.contains(*x));
+        }
+    }
+
+    #[test]
+    fn test_index_by_id() {
+        let xml = r#"<osm>
+          <node id="1" lat="0.0" lon="0.0"/>
+          <node id="16777221" lat="1.0" lon="1.0"/>
+          <node id="33554439" lat="2.0" lon="2.0"/>
+          <way id="10"><nd ref="1"/><nd ref="33554439"/></way>
+          <way id="16777226"><nd ref="16777221"/></way>
+          <relation id="100"><member type="way" ref="10" role=""/></relation>
+        </osm>"#;
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_id_index(true)).unwrap();
+        assert_eq!(archive.node_index_by_id(1), Some(0));
+        assert_eq!(archive.node_index_by_id((1 << 24) + 5), Some(1));
+        assert_eq!(archive.node_index_by_id((2 << 24) + 7), Some(2));
+        assert_eq!(archive.way_index_by_id(10), Some(0));
+        assert_eq!(archive.way_index_by_id((1 << 24) + 10), Some(1));
+        assert_eq!(archive.relation_index_by_id(100), Some(0));
+
+        for id in [0, 2, (2 << 24) + 8, 3 << 24] {
+            assert_eq!(archive.node_index_by_id(id), None);
+        }
+        assert_eq!(archive.way_index_by_id(1), None);
+        assert_eq!(archive.way_index_by_id(2 << 24), None);
+        assert_eq!(archive.relation_index_by_id(101), None);
+        assert_eq!(archive.relation_index_by_id(1 << 24), None);
+    }
 }
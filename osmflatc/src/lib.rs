@@ -0,0 +1,42 @@
+//! Compiler of OpenStreetMap (OSM) data from osm.pbf format to osm.flatdata
+//! format.
+//!
+//! The compilation is configured and run with the [`Compiler`] builder. The
+//! `osmflatc` binary is a thin command line wrapper around it. Similarly, the
+//! [`Exporter`] converts an archive to GeoJSON and the [`PbfWriter`] writes
+//! it back to OSM PBF, both wrapped by the `osmflat-export` binary.
+
+mod compiler;
+mod export;
+mod extract;
+mod filter;
+mod ids;
+mod merge;
+mod osmchange;
+mod osmpbf;
+mod osmxml;
+mod parallel;
+mod pbfwriter;
+mod reverse;
+mod spatial;
+mod stats;
+mod strings;
+
+pub use crate::compiler::{Compiler, Progress};
+pub use crate::export::{ExportFormat, ExportStats, Exporter};
+pub use crate::extract::Area;
+pub use crate::filter::{TagFilter, TagPolicy};
+pub use crate::pbfwriter::PbfWriter;
+pub use crate::stats::Stats;
+
+/// Error of the compilation.
+pub type Error = Box<dyn std::error::Error>;
+
+fn gcd(a: i32, b: i32) -> i32 {
+    let (mut x, mut y) = (a.min(b), a.max(b));
+    while x > 1 {
+        y %= x;
+        std::mem::swap(&mut x, &mut y);
+    }
+    y
+}
@@ -0,0 +1,692 @@
+//! Merging of multiple inputs with overlapping elements, e.g. regional
+//! extracts with overlapping borders.
+//!
+//! The elements of all inputs are merged by their OSM ids and written as PBF
+//! data, which is then fed into the same compilation pipeline as a regular
+//! PBF input. If an element is contained in several inputs, the element with
+//! the highest version is kept. Without metadata, the element of the first
+//! input containing it is kept. Each input is expected to be sorted by type
+//! and id.
+//!
+//! The bounding box of the merged header covers the bounding boxes of all
+//! inputs. It is omitted if any of the inputs does not have one.
+//!
+//! Unsorted inputs are sorted with an external merge sort: The elements are
+//! sorted in memory in runs of limited size, which are written as temporary
+//! sorted inputs and then merged.
+
+use crate::osmchange::delta_encode;
+use crate::osmpbf::{self, build_block_index, read_block, BlockIndex, BlockType};
+use crate::osmxml::{self, BlockWriter, ElementType};
+use crate::Error;
+
+use log::{info, warn};
+
+use std::io::{self, Write};
+
+/// Element of an input independent of the string table and granularity of
+/// its block.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Element {
+    id: i64,
+    /// Metadata without `user_sid` with the timestamp in seconds
+    info: Option<osmpbf::Info>,
+    user: Option<Vec<u8>>,
+    tags: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Latitude and longitude of a node in nanodegrees
+    coord: (i64, i64),
+    /// Node ids of a way
+    refs: Vec<i64>,
+    /// Types, ids and roles of the members of a relation
+    members: Vec<(i32, i64, Vec<u8>)>,
+}
+
+impl Element {
+    fn version(&self) -> Option<i32> {
+        self.info.as_ref().and_then(|info| info.version)
+    }
+}
+
+/// Input with its indexed blocks.
+struct Input<'a> {
+    data: &'a [u8],
+    header: osmpbf::HeaderBlock,
+    blocks: Vec<BlockIndex>,
+}
+
+impl<'a> Input<'a> {
+    /// Indexes the blocks of the input. Corrupt blocks are skipped with a
+    /// warning if `lenient` is set, otherwise they are an error.
+    fn new(data: &'a [u8], lenient: bool, num_skipped_blocks: &mut usize) -> Result<Self, Error> {
+        let (blocks, errors) = build_block_index(data);
+        for error in errors {
+            if !lenient {
+                return Err(error.into());
+            }
+            warn!("Skipping corrupt block: {}", error);
+            *num_skipped_blocks += 1;
+        }
+        let headers: Vec<_> = blocks
+            .iter()
+            .filter(|block| block.block_type == BlockType::Header)
+            .collect();
+        if headers.len() != 1 {
+            return Err(format!(
+                "Require exactly one header block, but found {}",
+                headers.len()
+            )
+            .into());
+        }
+        let header = read_block(data, headers[0])?;
+        Ok(Self {
+            data,
+            header,
+            blocks,
+        })
+    }
+}
+
+/// Returns a granularity which can represent the coordinates of all inputs.
+fn granularity(inputs: &[Input]) -> i32 {
+    inputs
+        .iter()
+        .flat_map(|input| &input.blocks)
+        .filter_map(|block| block.granularity)
+        .fold(1_000_000_000, |acc, granularity| {
+            crate::gcd(acc, granularity as i32)
+        })
+}
+
+/// Merges the inputs and writes them as PBF data.
+///
+/// Corrupt blocks are skipped with a warning if `lenient` is set, otherwise
+/// they are an error. Returns the number of skipped blocks.
+pub fn write_pbf(inputs: &[&[u8]], lenient: bool, mut out: impl Write) -> Result<usize, Error> {
+    let mut num_skipped_blocks = 0;
+    let inputs = inputs
+        .iter()
+        .map(|data| Input::new(data, lenient, &mut num_skipped_blocks))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    osmxml::write_blob(&mut out, "OSMHeader", &merge_headers(&inputs))?;
+    let mut writer = ElementWriter::new(out, granularity(&inputs), lenient);
+    writer.num_skipped_blocks = num_skipped_blocks;
+
+    info!("Merging nodes...");
+    writer.merge(&inputs, ElementType::Node)?;
+    info!("Merging ways...");
+    writer.merge(&inputs, ElementType::Way)?;
+    info!("Merging relations...");
+    writer.merge(&inputs, ElementType::Relation)?;
+
+    info!(
+        "Merged {} inputs, dropped {} duplicate elements",
+        inputs.len(),
+        writer.num_duplicates
+    );
+    writer.blocks.finish()?.flush()?;
+    Ok(writer.num_skipped_blocks)
+}
+
+/// Sorts the elements of an unsorted input by type and id in runs of at most
+/// `run_len` elements, and writes each run as PBF data to a writer returned by
+/// `create_run`.
+///
+/// The runs are sorted inputs, which are merged into the sorted input with
+/// [`write_pbf`]. Elements contained several times in a run are deduplicated
+/// like when merging. Returns the number of skipped corrupt blocks.
+pub fn write_sorted_runs<W: Write>(
+    data: &[u8],
+    lenient: bool,
+    run_len: usize,
+    mut create_run: impl FnMut() -> io::Result<W>,
+) -> Result<usize, Error> {
+    let mut num_skipped_blocks = 0;
+    let input = Input::new(data, lenient, &mut num_skipped_blocks)?;
+    let granularity = granularity(std::slice::from_ref(&input));
+
+    let mut blocks: Vec<_> = input
+        .blocks
+        .iter()
+        .filter(|block| block.block_type != BlockType::Header)
+        .collect();
+    blocks.sort_unstable_by_key(|block| block.blob_start);
+
+    let mut run = Vec::new();
+    let mut num_runs = 0;
+    for block in blocks {
+        let element_type = match block.block_type {
+            BlockType::Header => unreachable!(),
+            BlockType::Nodes | BlockType::DenseNodes => ElementType::Node,
+            BlockType::Ways => ElementType::Way,
+            BlockType::Relations => ElementType::Relation,
+        };
+        match read_block(data, block) {
+            Ok(block) => run.extend(
+                decode_block(&block)
+                    .into_iter()
+                    .map(|element| (element_type, element)),
+            ),
+            Err(e) if lenient => {
+                warn!("Skipping corrupt block: {}", e);
+                num_skipped_blocks += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+        if run.len() >= run_len {
+            write_run(&input.header, granularity, &mut run, create_run()?)?;
+            num_runs += 1;
+        }
+    }
+    if !run.is_empty() || num_runs == 0 {
+        write_run(&input.header, granularity, &mut run, create_run()?)?;
+        num_runs += 1;
+    }
+    info!("Sorted input in {} runs", num_runs);
+    Ok(num_skipped_blocks)
+}
+
+/// Sorts the elements of a run and writes them as PBF data, emptying the run.
+fn write_run(
+    header: &osmpbf::HeaderBlock,
+    granularity: i32,
+    run: &mut Vec<(ElementType, Element)>,
+    mut out: impl Write,
+) -> Result<(), Error> {
+    // the sort is stable, so the first of several equal elements comes first
+    run.sort_by_key(|(element_type, element)| (*element_type, element.id));
+
+    osmxml::write_blob(&mut out, "OSMHeader", header)?;
+    let mut writer = ElementWriter::new(out, granularity, false);
+    let mut elements = run.drain(..).peekable();
+    while let Some((element_type, mut element)) = elements.next() {
+        let id = element.id;
+        while let Some((_, duplicate)) =
+            elements.next_if(|(next_type, next)| (*next_type, next.id) == (element_type, id))
+        {
+            writer.num_duplicates += 1;
+            element = newest(element, duplicate);
+        }
+        writer.write(element_type, element)?;
+    }
+    writer.blocks.finish()?.flush()?;
+    Ok(())
+}
+
+/// Returns the element with the higher version, or the first one if the
+/// versions are equal or missing.
+fn newest(first: Element, second: Element) -> Element {
+    if second.version() > first.version() {
+        second
+    } else {
+        first
+    }
+}
+
+/// Returns the header of the first input with a bounding box covering the
+/// bounding boxes of all inputs.
+fn merge_headers(inputs: &[Input]) -> osmpbf::HeaderBlock {
+    let bbox = inputs
+        .iter()
+        .map(|input| input.header.bbox.clone())
+        .reduce(|a, b| {
+            let (a, b) = (a?, b?);
+            Some(osmpbf::HeaderBBox {
+                left: a.left.min(b.left),
+                right: a.right.max(b.right),
+                top: a.top.max(b.top),
+                bottom: a.bottom.min(b.bottom),
+            })
+        })
+        .flatten();
+    let first = &inputs[0].header;
+    osmpbf::HeaderBlock {
+        bbox,
+        required_features: vec!["OsmSchema-V0.6".into()],
+        writingprogram: first.writingprogram.clone(),
+        source: first.source.clone(),
+        // the merged data is only as recent as the oldest input
+        osmosis_replication_timestamp: inputs
+            .iter()
+            .filter_map(|input| input.header.osmosis_replication_timestamp)
+            .min(),
+        ..Default::default()
+    }
+}
+
+/// Iterator over the elements of a type in an input, decoding one block at a
+/// time.
+struct Elements<'a> {
+    data: &'a [u8],
+    blocks: std::vec::IntoIter<BlockIndex>,
+    elements: std::vec::IntoIter<Element>,
+}
+
+impl<'a> Elements<'a> {
+    fn new(input: &Input<'a>, element_type: ElementType) -> Self {
+        let mut blocks: Vec<_> = input
+            .blocks
+            .iter()
+            .filter(|block| match block.block_type {
+                BlockType::Header => false,
+                BlockType::Nodes | BlockType::DenseNodes => element_type == ElementType::Node,
+                BlockType::Ways => element_type == ElementType::Way,
+                BlockType::Relations => element_type == ElementType::Relation,
+            })
+            .cloned()
+            .collect();
+        blocks.sort_unstable_by_key(|block| block.blob_start);
+        Self {
+            data: input.data,
+            blocks: blocks.into_iter(),
+            elements: Vec::new().into_iter(),
+        }
+    }
+
+    /// Returns the next element, skipping corrupt blocks if `lenient` is set.
+    fn next(
+        &mut self,
+        lenient: bool,
+        num_skipped_blocks: &mut usize,
+    ) -> Result<Option<Element>, Error> {
+        loop {
+            if let Some(element) = self.elements.next() {
+                return Ok(Some(element));
+            }
+            let block = match self.blocks.next() {
+                Some(block) => block,
+                None => return Ok(None),
+            };
+            match read_block(self.data, &block) {
+                Ok(block) => self.elements = decode_block(&block).into_iter(),
+                Err(e) if lenient => {
+                    warn!("Skipping corrupt block: {}", e);
+                    *num_skipped_blocks += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Decodes all elements of a primitive block.
+fn decode_block(block: &osmpbf::PrimitiveBlock) -> Vec<Element> {
+    let strings = &block.stringtable.s;
+    let string = |idx: i64| strings.get(idx as usize).cloned().unwrap_or_default();
+    let granularity = i64::from(block.granularity.unwrap_or(100));
+    let lat_offset = block.lat_offset.unwrap_or(0);
+    let lon_offset = block.lon_offset.unwrap_or(0);
+    let date_granularity = i64::from(block.date_granularity.unwrap_or(1000));
+    let coord = |lat: i64, lon: i64| {
+        (
+            lat_offset + granularity * lat,
+            lon_offset + granularity * lon,
+        )
+    };
+    let tags = |keys: &[u32], vals: &[u32]| -> Vec<(Vec<u8>, Vec<u8>)> {
+        keys.iter()
+            .zip(vals)
+            .map(|(&key, &val)| (string(key.into()), string(val.into())))
+            .collect()
+    };
+    let info = |info: Option<&osmpbf::Info>| match info {
+        Some(info) => {
+            let user = info.user_sid.map(|sid| string(sid.into()));
+            let info = osmpbf::Info {
+                timestamp: info
+                    .timestamp
+                    .map(|timestamp| timestamp * date_granularity / 1000),
+                user_sid: None,
+                ..info.clone()
+            };
+            (Some(info), user)
+        }
+        None => (None, None),
+    };
+
+    let mut elements = Vec::new();
+    for group in &block.primitivegroup {
+        for node in &group.nodes {
+            let (info, user) = info(node.info.as_ref());
+            elements.push(Element {
+                id: node.id,
+                info,
+                user,
+                tags: tags(&node.keys, &node.vals),
+                coord: coord(node.lat, node.lon),
+                ..Default::default()
+            });
+        }
+
+        if let Some(dense) = &group.dense {
+            let (mut id, mut lat, mut lon) = (0, 0, 0);
+            let (mut timestamp, mut changeset, mut uid, mut user_sid) = (0, 0, 0, 0);
+            let mut keys_vals = dense.keys_vals.iter();
+            for i in 0..dense.id.len() {
+                id += dense.id[i];
+                lat += dense.lat[i];
+                lon += dense.lon[i];
+
+                let (info, user) = match &dense.denseinfo {
+                    Some(dense_info) if i < dense_info.version.len() => {
+                        timestamp += dense_info.timestamp[i];
+                        changeset += dense_info.changeset[i];
+                        uid += dense_info.uid[i];
+                        user_sid += dense_info.user_sid[i];
+                        let info = osmpbf::Info {
+                            version: Some(dense_info.version[i]),
+                            timestamp: Some(timestamp * date_granularity / 1000),
+                            changeset: Some(changeset),
+                            uid: Some(uid),
+                            user_sid: None,
+                            visible: dense_info.visible.get(i).copied(),
+                        };
+                        (Some(info), Some(string(user_sid.into())))
+                    }
+                    _ => (None, None),
+                };
+
+                let mut tags = Vec::new();
+                while let Some(&key) = keys_vals.next() {
+                    if key == 0 {
+                        break;
+                    }
+                    let val = keys_vals.next().copied().unwrap_or_default();
+                    tags.push((string(key.into()), string(val.into())));
+                }
+
+                elements.push(Element {
+                    id,
+                    info,
+                    user,
+                    tags,
+                    coord: coord(lat, lon),
+                    ..Default::default()
+                });
+            }
+        }
+
+        for way in &group.ways {
+            let (info, user) = info(way.info.as_ref());
+            let mut node_id = 0;
+            elements.push(Element {
+                id: way.id,
+                info,
+                user,
+                tags: tags(&way.keys, &way.vals),
+                refs: way
+                    .refs
+                    .iter()
+                    .map(|delta| {
+                        node_id += delta;
+                        node_id
+                    })
+                    .collect(),
+                ..Default::default()
+            });
+        }
+
+        for relation in &group.relations {
+            let (info, user) = info(relation.info.as_ref());
+            let mut member_id = 0;
+            let members = relation
+                .types
+                .iter()
+                .zip(&relation.memids)
+                .zip(&relation.roles_sid)
+                .map(|((&member_type, delta), &role_sid)| {
+                    member_id += delta;
+                    (member_type, member_id, string(role_sid.into()))
+                })
+                .collect();
+            elements.push(Element {
+                id: relation.id,
+                info,
+                user,
+                tags: tags(&relation.keys, &relation.vals),
+                members,
+                ..Default::default()
+            });
+        }
+    }
+    elements
+}
+
+struct ElementWriter<W> {
+    blocks: BlockWriter<W>,
+    /// Granularity of the written coordinates in nanodegrees
+    granularity: i64,
+    lenient: bool,
+    num_skipped_blocks: usize,
+    num_duplicates: usize,
+}
+
+impl<W: Write> ElementWriter<W> {
+    fn new(out: W, granularity: i32, lenient: bool) -> Self {
+        Self {
+            blocks: BlockWriter::new(out, granularity),
+            granularity: i64::from(granularity),
+            lenient,
+            num_skipped_blocks: 0,
+            num_duplicates: 0,
+        }
+    }
+
+    /// Merges the elements of a type of all inputs by id.
+    fn merge(&mut self, inputs: &[Input], element_type: ElementType) -> Result<(), Error> {
+        let lenient = self.lenient;
+        let mut elements: Vec<_> = inputs
+            .iter()
+            .map(|input| Elements::new(input, element_type))
+            .collect();
+        let mut heads = elements
+            .iter_mut()
+            .map(|elements| elements.next(lenient, &mut self.num_skipped_blocks))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        while let Some(id) = heads.iter().flatten().map(|element| element.id).min() {
+            let mut merged: Option<Element> = None;
+            for (head, elements) in heads.iter_mut().zip(&mut elements) {
+                if head.as_ref().map(|element| element.id) != Some(id) {
+                    continue;
+                }
+                let next = elements.next(lenient, &mut self.num_skipped_blocks)?;
+                let element = std::mem::replace(head, next).unwrap();
+                merged = match merged {
+                    Some(merged) => {
+                        self.num_duplicates += 1;
+                        Some(newest(merged, element))
+                    }
+                    None => Some(element),
+                };
+            }
+            self.write(element_type, merged.unwrap())?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, element_type: ElementType, element: Element) -> io::Result<()> {
+        // prepare before adding strings, which might flush the string table
+        self.blocks.prepare(element_type)?;
+        let (keys, vals): (Vec<u32>, Vec<u32>) = element
+            .tags
+            .iter()
+            .map(|(key, value)| (self.blocks.string_id(key), self.blocks.string_id(value)))
+            .unzip();
+        let mut info = element.info;
+        if let Some(info) = &mut info {
+            info.user_sid = element.user.map(|user| self.blocks.string_id(&user));
+        }
+
+        match element_type {
+            ElementType::Node => {
+                let (lat, lon) = element.coord;
+                let node = osmpbf::Node {
+                    id: element.id,
+                    keys,
+                    vals,
+                    info,
+                    lat: lat / self.granularity,
+                    lon: lon / self.granularity,
+                };
+                self.blocks.push(osmxml::Element::Node(node))
+            }
+            ElementType::Way => {
+                let way = osmpbf::Way {
+                    id: element.id,
+                    keys,
+                    vals,
+                    info,
+                    refs: delta_encode(element.refs),
+                };
+                self.blocks.push(osmxml::Element::Way(way, 0))
+            }
+            ElementType::Relation => {
+                let mut relation = osmpbf::Relation {
+                    id: element.id,
+                    keys,
+                    vals,
+                    info,
+                    ..Default::default()
+                };
+                let mut memids = Vec::with_capacity(element.members.len());
+                for (member_type, id, role) in element.members {
+                    relation.types.push(member_type);
+                    relation.roles_sid.push(self.blocks.string_id(&role) as i32);
+                    memids.push(id);
+                }
+                relation.memids = delta_encode(memids);
+                self.blocks.push(osmxml::Element::Relation(relation, 0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const FIRST: &str = r#"<osm>
+      <bounds minlat="0" minlon="0" maxlat="1" maxlon="1"/>
+      <node id="1" lat="0.5" lon="0.5" version="1"/>
+      <node id="2" lat="1" lon="1" version="1"><tag k="name" v="old"/></node>
+      <way id="10"><nd ref="1"/><nd ref="2"/></way>
+    </osm>"#;
+
+    const SECOND: &str = r#"<osm>
+      <bounds minlat="1" minlon="1" maxlat="2" maxlon="2"/>
+      <node id="2" lat="1" lon="1" version="2"><tag k="name" v="new"/></node>
+      <node id="3" lat="1.5" lon="1.5" version="1"/>
+      <way id="10"><nd ref="2"/><nd ref="3"/></way>
+      <relation id="100"><member type="way" ref="10" role="outer"/></relation>
+    </osm>"#;
+
+    fn merge(inputs: &[&str]) -> Vec<u8> {
+        let pbfs: Vec<_> = inputs
+            .iter()
+            .map(|xml| osmxml::to_pbf(xml.as_bytes()).unwrap())
+            .collect();
+        let inputs: Vec<&[u8]> = pbfs.iter().map(Vec::as_slice).collect();
+        let mut out = Vec::new();
+        assert_eq!(write_pbf(&inputs, false, &mut out).unwrap(), 0);
+        out
+    }
+
+    fn read_elements(data: &[u8]) -> (osmpbf::HeaderBlock, Vec<Element>) {
+        let (index, errors) = build_block_index(data);
+        assert!(errors.is_empty());
+        let header = read_block(data, &index[0]).unwrap();
+        let elements = index[1..]
+            .iter()
+            .flat_map(|block| decode_block(&read_block(data, block).unwrap()))
+            .collect();
+        (header, elements)
+    }
+
+    #[test]
+    fn test_merge() {
+        let (header, elements) = read_elements(&merge(&[FIRST, SECOND]));
+
+        let bbox = header.bbox.unwrap();
+        assert_eq!((bbox.left, bbox.bottom), (0, 0));
+        assert_eq!((bbox.right, bbox.top), (2_000_000_000, 2_000_000_000));
+
+        let ids: Vec<_> = elements.iter().map(|element| element.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 10, 100]);
+        // the node with the highest version wins
+        assert_eq!(elements[1].tags, vec![(b"name".to_vec(), b"new".to_vec())]);
+        assert_eq!(elements[2].coord, (1_500_000_000, 1_500_000_000));
+        // without versions, the first input wins
+        assert_eq!(elements[3].refs, vec![1, 2]);
+        assert_eq!(elements[4].members, vec![(1, 10, b"outer".to_vec())]);
+    }
+
+    /// Writer into a buffer shared with the test.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut out = Vec::new();
+        osmxml::write_blob(&mut out, "OSMHeader", &osmpbf::HeaderBlock::default()).unwrap();
+        let mut blocks = BlockWriter::new(out, 100);
+        let node = |id| {
+            osmxml::Element::Node(osmpbf::Node {
+                id,
+                lat: id,
+                lon: id,
+                ..Default::default()
+            })
+        };
+        blocks.push(node(3)).unwrap();
+        blocks.push(node(1)).unwrap();
+        let way = osmpbf::Way {
+            id: 10,
+            refs: vec![1, 1],
+            ..Default::default()
+        };
+        blocks.push(osmxml::Element::Way(way, 0)).unwrap();
+        blocks.push(node(2)).unwrap();
+        let data = blocks.finish().unwrap();
+
+        let mut runs = Vec::new();
+        let num_skipped_blocks = write_sorted_runs(&data, false, 2, || {
+            let run = SharedBuf::default();
+            runs.push(run.clone());
+            Ok(run)
+        })
+        .unwrap();
+        assert_eq!(num_skipped_blocks, 0);
+        assert_eq!(runs.len(), 2);
+
+        let runs: Vec<_> = runs.iter().map(|run| run.0.borrow().clone()).collect();
+        let inputs: Vec<&[u8]> = runs.iter().map(Vec::as_slice).collect();
+        let mut out = Vec::new();
+        write_pbf(&inputs, false, &mut out).unwrap();
+        let (_, elements) = read_elements(&out);
+        let ids: Vec<_> = elements.iter().map(|element| element.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 10]);
+        assert_eq!(elements[1].coord, (200, 200));
+        assert_eq!(elements[3].refs, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_merge_without_bbox() {
+        let (header, elements) = read_elements(&merge(&[FIRST, "<osm></osm>"]));
+        assert_eq!(header.bbox, None);
+        assert_eq!(elements.len(), 3);
+    }
+}
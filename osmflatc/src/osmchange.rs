@@ -0,0 +1,542 @@
+//! Application of OSM change files to an existing osmflat archive.
+//!
+//! The elements of the archive are merged with the changed elements and
+//! written as PBF data, which is then fed into the same compilation pipeline
+//! as a regular PBF input. Since elements are identified by their OSM ids,
+//! the archive must contain the `ids` sub-archive (compiled with `--ids`).
+//!
+//! The archive does not store the ids of unresolved references of ways and
+//! relations, therefore these references are dropped.
+//!
+//! See <https://wiki.openstreetmap.org/wiki/OsmChange>.
+
+use crate::osmpbf;
+use crate::osmxml::{self, invalid_data, BlockWriter, ElementType};
+use crate::pbfwriter::{ElementConverter, ElementIds};
+use crate::Error;
+
+use itertools::{Either, EitherOrBoth, Itertools};
+use log::info;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Write};
+
+/// Changed element independent of a PBF string table.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Element {
+    /// Metadata without `user_sid`
+    info: Option<osmpbf::Info>,
+    user: Option<String>,
+    tags: Vec<(String, String)>,
+    /// Longitude and latitude of a node in degrees
+    coord: (f64, f64),
+    /// Node ids of a way
+    refs: Vec<i64>,
+    /// Types, ids and roles of the members of a relation
+    members: Vec<(ElementType, i64, String)>,
+}
+
+/// Changed elements by id, deleted elements are `None`.
+type ElementChanges = BTreeMap<i64, Option<Element>>;
+
+/// Changes of nodes, ways and relations read from OSM change files.
+#[derive(Debug, Default)]
+pub struct Changes {
+    nodes: ElementChanges,
+    ways: ElementChanges,
+    relations: ElementChanges,
+    timestamp: Option<i64>,
+}
+
+impl Changes {
+    /// Reads the changes of an OSM change file.
+    ///
+    /// Gzip and bzip2 compressed data is decompressed transparently. A change
+    /// of an element overrides all changes of the element read before.
+    pub fn read(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut reader = Reader::from_reader(BufReader::new(osmxml::decompress(data)));
+        reader.trim_text(true);
+
+        // whether the current action is a deletion, `None` outside of actions
+        let mut deleting = None;
+        let mut element: Option<(ElementType, i64, Element)> = None;
+
+        let mut buf = Vec::new();
+        loop {
+            let event = reader.read_event_into(&mut buf).map_err(invalid_data)?;
+            let (e, is_empty) = match event {
+                Event::Start(ref e) => (e, false),
+                Event::Empty(ref e) => (e, true),
+                Event::End(ref e) => {
+                    let name = e.name();
+                    if let Some(element_type) = ElementType::from_name(name.as_ref()) {
+                        let (_, id, element) = element
+                            .take()
+                            .filter(|(t, ..)| *t == element_type)
+                            .ok_or_else(|| invalid_data("unexpected closing tag"))?;
+                        self.insert(element_type, id, element, deleting == Some(true));
+                    } else if is_action(name.as_ref()) {
+                        deleting = None;
+                    }
+                    buf.clear();
+                    continue;
+                }
+                Event::Eof => break,
+                _ => {
+                    buf.clear();
+                    continue;
+                }
+            };
+
+            match e.name().as_ref() {
+                b"create" | b"modify" => deleting = Some(false),
+                b"delete" => deleting = Some(true),
+                b"node" | b"way" | b"relation" => {
+                    let deleting = deleting.ok_or_else(|| {
+                        invalid_data("found element outside of create, modify or delete")
+                    })?;
+                    let element_type = ElementType::from_name(e.name().as_ref()).unwrap();
+                    let id: i64 = osmxml::required_attribute(e, "id")?;
+                    let (info, user) = parse_info(e)?;
+                    let mut new_element = Element {
+                        info,
+                        user,
+                        ..Default::default()
+                    };
+                    if element_type == ElementType::Node && !deleting {
+                        new_element.coord = (
+                            osmxml::required_attribute(e, "lon")?,
+                            osmxml::required_attribute(e, "lat")?,
+                        );
+                    }
+                    if is_empty {
+                        self.insert(element_type, id, new_element, deleting);
+                    } else {
+                        element = Some((element_type, id, new_element));
+                    }
+                }
+                b"tag" => match element {
+                    Some((_, _, ref mut element)) => element.tags.push((
+                        osmxml::required_attribute(e, "k")?,
+                        osmxml::required_attribute(e, "v")?,
+                    )),
+                    None => return Err(invalid_data("found tag outside of an element")),
+                },
+                b"nd" => match element {
+                    Some((ElementType::Way, _, ref mut way)) => {
+                        way.refs.push(osmxml::required_attribute(e, "ref")?)
+                    }
+                    _ => return Err(invalid_data("found nd outside of a way")),
+                },
+                b"member" => match element {
+                    Some((ElementType::Relation, _, ref mut relation)) => {
+                        let member_type: String = osmxml::required_attribute(e, "type")?;
+                        let member_type = ElementType::from_name(member_type.as_bytes())
+                            .ok_or_else(|| invalid_data("invalid member type"))?;
+                        relation.members.push((
+                            member_type,
+                            osmxml::required_attribute(e, "ref")?,
+                            osmxml::attribute(e, "role")?.unwrap_or_default(),
+                        ));
+                    }
+                    _ => return Err(invalid_data("found member outside of a relation")),
+                },
+                _ => (),
+            }
+            buf.clear();
+        }
+
+        if element.is_some() {
+            return Err(invalid_data("unexpected end of file"));
+        }
+        Ok(())
+    }
+
+    /// Latest timestamp of all changed elements in seconds since the epoch.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    fn insert(&mut self, element_type: ElementType, id: i64, element: Element, deleted: bool) {
+        let timestamp = element.info.as_ref().and_then(|info| info.timestamp);
+        self.timestamp = self.timestamp.max(timestamp);
+        let changes = match element_type {
+            ElementType::Node => &mut self.nodes,
+            ElementType::Way => &mut self.ways,
+            ElementType::Relation => &mut self.relations,
+        };
+        changes.insert(id, Some(element).filter(|_| !deleted));
+    }
+}
+
+fn is_action(name: &[u8]) -> bool {
+    matches!(name, b"create" | b"modify" | b"delete")
+}
+
+/// Parses the metadata attributes of an element into the metadata without
+/// `user_sid` and the user name.
+fn parse_info(e: &BytesStart) -> io::Result<(Option<osmpbf::Info>, Option<String>)> {
+    let timestamp = match osmxml::attribute::<String>(e, "timestamp")? {
+        Some(timestamp) => Some(
+            osmxml::parse_timestamp(&timestamp)
+                .ok_or_else(|| invalid_data(format!("invalid timestamp '{}'", timestamp)))?,
+        ),
+        None => None,
+    };
+    let info = osmpbf::Info {
+        version: osmxml::attribute(e, "version")?,
+        timestamp,
+        changeset: osmxml::attribute(e, "changeset")?,
+        uid: osmxml::attribute(e, "uid")?,
+        ..Default::default()
+    };
+    let info = Some(info).filter(|info| *info != osmpbf::Info::default());
+    Ok((info, osmxml::attribute(e, "user")?))
+}
+
+/// Parses a replication state file (`state.txt`) into its sequence number
+/// and timestamp in seconds since the epoch.
+///
+/// See <https://wiki.openstreetmap.org/wiki/Planet.osm/diffs>.
+pub fn read_state(data: &str) -> io::Result<(i64, i64)> {
+    let mut sequence_number = None;
+    let mut timestamp = None;
+    for line in data.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            // the file is in Java properties format, which escapes colons
+            let value = value.trim().replace('\\', "");
+            match key.trim() {
+                "sequenceNumber" => sequence_number = value.parse().ok(),
+                "timestamp" => timestamp = osmxml::parse_timestamp(&value),
+                _ => (),
+            }
+        }
+    }
+    match (sequence_number, timestamp) {
+        (Some(sequence_number), Some(timestamp)) => Ok((sequence_number, timestamp)),
+        _ => Err(invalid_data(
+            "state file is missing a valid sequenceNumber or timestamp",
+        )),
+    }
+}
+
+/// Returns a PBF header with the contents of the header of `archive`.
+pub fn header(archive: &osmflat::Osm) -> osmpbf::HeaderBlock {
+    let header = archive.header();
+    let strings = archive.stringtable();
+    // index 0 is the writing program, which is always stored first, so
+    // optional strings referencing it are missing
+    let string = |idx: u64| {
+        Some(idx)
+            .filter(|&idx| idx != 0)
+            .map(|idx| String::from_utf8_lossy(strings.substring_raw(idx as usize)).into_owned())
+    };
+
+    let nano = i64::from(1_000_000_000 / header.coord_scale());
+    let bbox = (
+        header.bbox_left(),
+        header.bbox_right(),
+        header.bbox_top(),
+        header.bbox_bottom(),
+    );
+    osmpbf::HeaderBlock {
+        bbox: Some(bbox)
+            .filter(|&bbox| bbox != (0, 0, 0, 0))
+            .map(|(left, right, top, bottom)| osmpbf::HeaderBBox {
+                left: i64::from(left) * nano,
+                right: i64::from(right) * nano,
+                top: i64::from(top) * nano,
+                bottom: i64::from(bottom) * nano,
+            }),
+        required_features: vec!["OsmSchema-V0.6".into()],
+        writingprogram: string(header.writingprogram_idx()),
+        source: string(header.source_idx()),
+        osmosis_replication_timestamp: Some(header.replication_timestamp()),
+        osmosis_replication_sequence_number: Some(header.replication_sequence_number()),
+        osmosis_replication_base_url: string(header.replication_base_url_idx()),
+        ..Default::default()
+    }
+}
+
+/// Writes the elements of `archive` with `changes` applied as PBF data.
+pub fn write_pbf(
+    archive: &osmflat::Osm,
+    changes: &Changes,
+    header: &osmpbf::HeaderBlock,
+    mut out: impl Write,
+) -> Result<(), Error> {
+    let ids = archive
+        .ids()
+        .ok_or("applying changes requires an archive compiled with --ids")?;
+    let sorted = |ids: &[osmflat::Id]| ids.windows(2).all(|w| w[0].value() < w[1].value());
+    if !sorted(ids.nodes()) || !sorted(ids.ways()) {
+        return Err(
+            "applying changes requires an archive with elements ordered by id, \
+             i.e. compiled without --spatial-order"
+                .into(),
+        );
+    }
+
+    // use a granularity which can represent the coordinates of the archive
+    // as well as the coordinates of changes (in the default granularity)
+    let archive_granularity = 1_000_000_000 / archive.header().coord_scale();
+    let granularity = crate::gcd(archive_granularity, 100);
+
+    osmxml::write_blob(&mut out, "OSMHeader", header)?;
+    let element_ids = ElementIds::new(archive);
+    let mut writer = ElementWriter {
+        converter: ElementConverter::new(
+            archive,
+            &element_ids,
+            i64::from(archive_granularity / granularity),
+        ),
+        blocks: BlockWriter::new(out, granularity),
+        granularity: f64::from(granularity),
+    };
+
+    info!("Writing nodes with changes applied...");
+    for element in merge(ids.nodes(), &changes.nodes) {
+        writer.write_node(element)?;
+    }
+    info!("Writing ways with changes applied...");
+    for element in merge(ids.ways(), &changes.ways) {
+        writer.write_way(element)?;
+    }
+    info!("Writing relations with changes applied...");
+    for element in merge(ids.relations(), &changes.relations) {
+        writer.write_relation(element)?;
+    }
+
+    if writer.converter.num_dropped_refs > 0 {
+        info!(
+            "Dropped {} unresolved references of ways and relations",
+            writer.converter.num_dropped_refs
+        );
+    }
+    writer.blocks.finish()?.flush()?;
+    Ok(())
+}
+
+/// Merges the ids of the elements in the archive with the changed elements,
+/// both sorted by id. Yields either the index of an unchanged element in the
+/// archive or a changed element, deleted elements are skipped.
+fn merge<'a>(
+    ids: &'a [osmflat::Id],
+    changes: &'a ElementChanges,
+) -> impl Iterator<Item = Either<usize, (i64, &'a Element)>> + 'a {
+    ids.iter()
+        .map(|id| id.value() as i64)
+        .enumerate()
+        .merge_join_by(changes, |(_, id), (change_id, _)| id.cmp(change_id))
+        .filter_map(|entry| match entry {
+            EitherOrBoth::Left((idx, _)) => Some(Either::Left(idx)),
+            EitherOrBoth::Right((&id, element)) | EitherOrBoth::Both(_, (&id, element)) => {
+                element.as_ref().map(|element| Either::Right((id, element)))
+            }
+        })
+}
+
+struct ElementWriter<'a, W> {
+    converter: ElementConverter<'a>,
+    blocks: BlockWriter<W>,
+    /// Granularity of the written coordinates in nanodegrees
+    granularity: f64,
+}
+
+impl<'a, W: Write> ElementWriter<'a, W> {
+    fn write_node(&mut self, element: Either<usize, (i64, &Element)>) -> io::Result<()> {
+        self.blocks.prepare(ElementType::Node)?;
+        let node = match element {
+            Either::Left(idx) => self.converter.node(idx, &mut self.blocks)?,
+            Either::Right((id, node)) => {
+                let (keys, vals) = self.changed_tags(node);
+                let (lon, lat) = node.coord;
+                osmpbf::Node {
+                    id,
+                    keys,
+                    vals,
+                    info: self.changed_info(node),
+                    lat: (lat * 1e9 / self.granularity).round() as i64,
+                    lon: (lon * 1e9 / self.granularity).round() as i64,
+                }
+            }
+        };
+        self.blocks.push(osmxml::Element::Node(node))
+    }
+
+    fn write_way(&mut self, element: Either<usize, (i64, &Element)>) -> io::Result<()> {
+        self.blocks.prepare(ElementType::Way)?;
+        let way = match element {
+            Either::Left(idx) => self.converter.way(idx, &mut self.blocks)?,
+            Either::Right((id, way)) => {
+                let (keys, vals) = self.changed_tags(way);
+                osmpbf::Way {
+                    id,
+                    keys,
+                    vals,
+                    info: self.changed_info(way),
+                    refs: delta_encode(way.refs.clone()),
+                }
+            }
+        };
+        self.blocks.push(osmxml::Element::Way(way, 0))
+    }
+
+    fn write_relation(&mut self, element: Either<usize, (i64, &Element)>) -> io::Result<()> {
+        self.blocks.prepare(ElementType::Relation)?;
+        let relation = match element {
+            Either::Left(idx) => self.converter.relation(idx, &mut self.blocks)?,
+            Either::Right((id, relation)) => {
+                let (keys, vals) = self.changed_tags(relation);
+                let mut pbf_relation = osmpbf::Relation {
+                    id,
+                    keys,
+                    vals,
+                    info: self.changed_info(relation),
+                    ..Default::default()
+                };
+                let mut memids = Vec::with_capacity(relation.members.len());
+                for (member_type, id, role) in &relation.members {
+                    pbf_relation.types.push(match member_type {
+                        ElementType::Node => osmpbf::relation::MemberType::Node,
+                        ElementType::Way => osmpbf::relation::MemberType::Way,
+                        ElementType::Relation => osmpbf::relation::MemberType::Relation,
+                    } as i32);
+                    pbf_relation
+                        .roles_sid
+                        .push(self.blocks.string_id(role.as_bytes()) as i32);
+                    memids.push(*id);
+                }
+                pbf_relation.memids = delta_encode(memids);
+                pbf_relation
+            }
+        };
+        self.blocks.push(osmxml::Element::Relation(relation, 0))
+    }
+
+    /// Returns the string ids of keys and values of tags of a changed element.
+    fn changed_tags(&mut self, element: &Element) -> (Vec<u32>, Vec<u32>) {
+        element
+            .tags
+            .iter()
+            .map(|(key, value)| {
+                (
+                    self.blocks.string_id(key.as_bytes()),
+                    self.blocks.string_id(value.as_bytes()),
+                )
+            })
+            .unzip()
+    }
+
+    /// Returns the metadata of a changed element.
+    fn changed_info(&mut self, element: &Element) -> Option<osmpbf::Info> {
+        let mut info = element.info.clone()?;
+        info.user_sid = element
+            .user
+            .as_ref()
+            .map(|user| self.blocks.string_id(user.as_bytes()));
+        Some(info)
+    }
+}
+
+/// Encodes ids as deltas to their predecessors (as in PBF).
+pub fn delta_encode(values: Vec<i64>) -> Vec<i64> {
+    let mut last = 0;
+    values
+        .into_iter()
+        .map(|value| {
+            let delta = value - last;
+            last = value;
+            delta
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OSC: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osmChange version="0.6" generator="test">
+  <modify>
+    <node id="1" lat="52.5" lon="13.4" version="2" timestamp="2020-01-02T03:04:05Z"
+          changeset="42" uid="7" user="mapper">
+      <tag k="amenity" v="cafe"/>
+    </node>
+  </modify>
+  <create>
+    <way id="10" version="1" timestamp="2020-01-02T03:04:06Z">
+      <nd ref="1"/>
+      <nd ref="2"/>
+    </way>
+    <relation id="100">
+      <member type="way" ref="10" role="outer"/>
+    </relation>
+  </create>
+  <delete>
+    <node id="2" version="3" timestamp="2020-01-02T03:04:07Z"/>
+  </delete>
+</osmChange>
+"#;
+
+    #[test]
+    fn test_read() {
+        let mut changes = Changes::default();
+        changes.read(OSC.as_bytes()).unwrap();
+
+        let node = changes.nodes[&1].as_ref().unwrap();
+        assert_eq!(node.coord, (13.4, 52.5));
+        assert_eq!(node.tags, vec![("amenity".into(), "cafe".into())]);
+        assert_eq!(node.info.as_ref().unwrap().version, Some(2));
+        assert_eq!(node.user.as_deref(), Some("mapper"));
+        assert_eq!(changes.nodes[&2], None);
+
+        assert_eq!(changes.ways[&10].as_ref().unwrap().refs, vec![1, 2]);
+        let relation = changes.relations[&100].as_ref().unwrap();
+        assert_eq!(
+            relation.members,
+            vec![(ElementType::Way, 10, "outer".into())]
+        );
+        assert_eq!(changes.timestamp(), Some(1_577_934_247));
+    }
+
+    #[test]
+    fn test_later_changes_override() {
+        let mut changes = Changes::default();
+        changes.read(OSC.as_bytes()).unwrap();
+        let osc = r#"<osmChange>
+          <create><node id="2" lat="1" lon="2"/></create>
+          <delete><node id="1"/></delete>
+        </osmChange>"#;
+        changes.read(osc.as_bytes()).unwrap();
+        assert_eq!(changes.nodes[&1], None);
+        assert_eq!(changes.nodes[&2].as_ref().unwrap().coord, (2., 1.));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let osc = r#"<osmChange><node id="1" lat="0" lon="0"/></osmChange>"#;
+        assert!(Changes::default().read(osc.as_bytes()).is_err());
+        let osc = r#"<osmChange><create><node id="1"/></create></osmChange>"#;
+        assert!(Changes::default().read(osc.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_read_state() {
+        let state = "#Mon Jan 02 03:04:05 UTC 2020\n\
+                     sequenceNumber=4711\n\
+                     timestamp=2020-01-02T03\\:04\\:05Z\n";
+        assert_eq!(read_state(state).unwrap(), (4711, 1_577_934_245));
+        assert!(read_state("sequenceNumber=1\n").is_err());
+    }
+
+    #[test]
+    fn test_delta_encode() {
+        assert_eq!(delta_encode(vec![5, 7, 2]), vec![5, 2, -5]);
+    }
+}
@@ -4,9 +4,11 @@ use byteorder::{ByteOrder, NetworkEndian};
 use flate2::read::ZlibDecoder;
 use log::info;
 use prost::{self, Message};
+use rayon::iter::Either;
 use rayon::prelude::*;
 
-use std::io::{self, Read};
+use std::fmt;
+use std::io::{self, Read, Write};
 
 include!(concat!(env!("OUT_DIR"), "/osmpbf.rs"));
 
@@ -19,6 +21,88 @@ pub enum BlockType {
     Relations,
 }
 
+/// Kind of an error while reading PBF data.
+#[derive(Debug)]
+pub enum PbfErrorKind {
+    /// The data ends within a block
+    Truncated,
+    /// Blob of an unknown type
+    UnknownBlobType(String),
+    /// Block containing changesets, which are not supported
+    Changesets,
+    /// Block without primitive groups
+    NoPrimitiveGroup,
+    /// Blob compressed with an unsupported compression, or with a compression
+    /// whose cargo feature is not enabled
+    UnsupportedCompression(&'static str),
+    /// Invalid protobuf or compressed data
+    InvalidData(io::Error),
+}
+
+impl From<io::Error> for PbfErrorKind {
+    fn from(e: io::Error) -> Self {
+        PbfErrorKind::InvalidData(e)
+    }
+}
+
+impl From<prost::DecodeError> for PbfErrorKind {
+    fn from(e: prost::DecodeError) -> Self {
+        PbfErrorKind::InvalidData(e.into())
+    }
+}
+
+impl fmt::Display for PbfErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PbfErrorKind::Truncated => write!(f, "unexpected end of data"),
+            PbfErrorKind::UnknownBlobType(blob_type) => {
+                write!(f, "unknown blob type '{}'", blob_type)
+            }
+            PbfErrorKind::Changesets => write!(f, "unsupported block containing changesets"),
+            PbfErrorKind::NoPrimitiveGroup => write!(f, "block without primitive group"),
+            PbfErrorKind::UnsupportedCompression(compression) => {
+                write!(f, "unsupported blob compression: {}", compression)
+            }
+            PbfErrorKind::InvalidData(e) => write!(f, "invalid data: {}", e),
+        }
+    }
+}
+
+/// Error while reading a block of PBF data.
+#[derive(Debug)]
+pub struct PbfError {
+    pub kind: PbfErrorKind,
+    /// Byte offset of the block in the input
+    pub offset: usize,
+    /// Number of the block in the input, starting at 0 with the header block
+    pub block_number: usize,
+}
+
+impl fmt::Display for PbfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} in block {} at byte offset {}",
+            self.kind, self.block_number, self.offset
+        )
+    }
+}
+
+impl std::error::Error for PbfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            PbfErrorKind::InvalidData(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PbfError> for io::Error {
+    fn from(e: PbfError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
 /// Decode block type from PrimitiveBlock protobuf message
 ///
 /// This does not decode any fields, it just checks which tags are present
@@ -29,7 +113,9 @@ pub enum BlockType {
 /// Note: We use public API of `prost` crate, which though is not exposed in
 /// the crate and marked with comment that it should be only used from
 /// `prost::Message`.
-pub fn type_and_granularity_from_osmdata_blob(mut blob: &[u8]) -> io::Result<(BlockType, u64)> {
+pub fn type_and_granularity_from_osmdata_blob(
+    mut blob: &[u8],
+) -> Result<(BlockType, u64), PbfErrorKind> {
     const PRIMITIVE_GROUP_TAG: u32 = 2;
     const GRANULARITY_TAG: u32 = 17;
     const NODES_TAG: u32 = 1;
@@ -58,11 +144,12 @@ pub fn type_and_granularity_from_osmdata_blob(mut blob: &[u8]) -> io::Result<(Bl
                 DENSE_NODES_TAG => Some(BlockType::DenseNodes),
                 WAY_STAG => Some(BlockType::Ways),
                 RELATIONS_TAG => Some(BlockType::Relations),
-                CHANGESETS_TAG => {
-                    panic!("found block containing unsupported changesets");
-                }
+                CHANGESETS_TAG => return Err(PbfErrorKind::Changesets),
                 _ => {
-                    panic!("invalid input data: malformed primitive block");
+                    return Err(PbfErrorKind::InvalidData(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "malformed primitive block",
+                    )))
                 }
             };
         } else if key == GRANULARITY_TAG {
@@ -77,7 +164,7 @@ pub fn type_and_granularity_from_osmdata_blob(mut blob: &[u8]) -> io::Result<(Bl
         )?;
     }
     match block_type {
-        None => panic!("Found block without primitive group"),
+        None => Err(PbfErrorKind::NoPrimitiveGroup),
         Some(x) => Ok((x, granularity)),
     }
 }
@@ -88,150 +175,395 @@ pub struct BlockIndex {
     pub granularity: Option<u64>,
     pub blob_start: usize,
     pub blob_len: usize,
+    pub block_number: usize,
 }
 
 struct BlockIndexIterator<'a> {
     data: &'a [u8],
     cursor: usize,
+    block_number: usize,
 }
 
-enum BlobInfo {
+enum BlobInfo<'a> {
     Header(BlockIndex),
-    Unknown(usize, usize, Vec<u8>),
+    Unknown(usize, usize, usize, &'a [u8]),
 }
 
 impl<'a> BlockIndexIterator<'a> {
     fn new(data: &'a [u8]) -> Self {
-        Self { data, cursor: 0 }
+        Self {
+            data,
+            cursor: 0,
+            block_number: 0,
+        }
     }
 
-    fn read(&mut self, len: usize) -> &[u8] {
-        let data = &self.data[self.cursor..self.cursor + len];
-        self.cursor += len;
-        data
+    fn read(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.cursor.checked_add(len)?;
+        let data = self.data.get(self.cursor..end)?;
+        self.cursor = end;
+        Some(data)
     }
 
-    fn next_blob(&mut self) -> Result<BlobInfo, io::Error> {
+    fn next_blob(&mut self) -> Result<BlobInfo<'a>, PbfError> {
+        let offset = self.cursor;
+        let block_number = self.block_number;
+        self.block_number += 1;
+        let error = |kind| PbfError {
+            kind,
+            offset,
+            block_number,
+        };
+
         // read size of blob header
-        let blob_header_len: i32 = NetworkEndian::read_i32(self.read(4));
+        let blob_header_len = self.read(4).ok_or_else(|| error(PbfErrorKind::Truncated))?;
+        let blob_header_len: i32 = NetworkEndian::read_i32(blob_header_len);
 
         // read blob header
-        let blob_header = BlobHeader::decode(self.read(blob_header_len as usize))?;
+        let blob_header = self
+            .read(blob_header_len as usize)
+            .ok_or_else(|| error(PbfErrorKind::Truncated))?;
+        let blob_header = BlobHeader::decode(blob_header).map_err(|e| error(e.into()))?;
 
         let blob_start = self.cursor;
         let blob_len = blob_header.datasize as usize;
+        let blob = self
+            .read(blob_len)
+            .ok_or_else(|| error(PbfErrorKind::Truncated))?;
 
         if blob_header.r#type == "OSMHeader" {
-            self.cursor += blob_len;
             Ok(BlobInfo::Header(BlockIndex {
                 block_type: BlockType::Header,
                 granularity: None,
                 blob_start,
                 blob_len,
+                block_number,
             }))
         } else if blob_header.r#type == "OSMData" {
-            // read blob
-            Ok(BlobInfo::Unknown(
-                blob_start,
-                blob_len,
-                self.read(blob_header.datasize as usize).to_vec(),
-            ))
+            Ok(BlobInfo::Unknown(blob_start, blob_len, block_number, blob))
         } else {
-            panic!("unknown blob type");
+            Err(PbfError {
+                kind: PbfErrorKind::UnknownBlobType(blob_header.r#type),
+                offset: blob_start,
+                block_number,
+            })
         }
     }
 }
 
 impl<'a> Iterator for BlockIndexIterator<'a> {
-    type Item = Result<BlobInfo, io::Error>;
+    type Item = Result<BlobInfo<'a>, PbfError>;
     fn next(&mut self) -> Option<Self::Item> {
         if self.cursor < self.data.len() {
-            Some(self.next_blob())
+            let result = self.next_blob();
+            if let Err(e) = &result {
+                if !matches!(e.kind, PbfErrorKind::UnknownBlobType(_)) {
+                    // the end of the block is unknown, so the next block cannot be found
+                    self.cursor = self.data.len();
+                }
+            }
+            Some(result)
         } else {
             None
         }
     }
 }
 
-pub fn read_block<T: prost::Message + Default>(
-    data: &[u8],
-    idx: &BlockIndex,
-) -> Result<T, io::Error> {
-    let blob = Blob::decode(&data[idx.blob_start..idx.blob_start + idx.blob_len])?;
+/// Copies PBF data block by block from a stream, e.g. stdin, to a writer.
+///
+/// The framing of the blocks is checked while copying, so that a stream
+/// ending within a block is reported as an error. Returns the number of
+/// copied blocks.
+pub fn copy_blocks(mut reader: impl Read, mut writer: impl Write) -> io::Result<usize> {
+    let mut buf = Vec::new();
+    let mut offset = 0;
+    let mut block_number = 0;
+    loop {
+        let error = |kind| PbfError {
+            kind,
+            offset,
+            block_number,
+        };
+
+        // read size of blob header
+        buf.clear();
+        let len = read_up_to(&mut reader, 4, &mut buf)?;
+        if len == 0 {
+            break;
+        } else if len < 4 {
+            return Err(error(PbfErrorKind::Truncated).into());
+        }
+        let blob_header_len = NetworkEndian::read_i32(&buf) as usize;
 
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
+        // read blob header
+        if read_up_to(&mut reader, blob_header_len, &mut buf)? < blob_header_len {
+            return Err(error(PbfErrorKind::Truncated).into());
+        }
+        let blob_header = BlobHeader::decode(&buf[4..]).map_err(|e| error(e.into()))?;
+
+        // read blob
+        let blob_len = blob_header.datasize as usize;
+        if read_up_to(&mut reader, blob_len, &mut buf)? < blob_len {
+            return Err(error(PbfErrorKind::Truncated).into());
+        }
+
+        writer.write_all(&buf)?;
+        offset += buf.len();
+        block_number += 1;
+    }
+    writer.flush()?;
+    Ok(block_number)
+}
+
+/// Appends at most `len` bytes from the reader to the buffer, and returns the
+/// number of appended bytes.
+fn read_up_to(reader: &mut impl Read, len: usize, buf: &mut Vec<u8>) -> io::Result<usize> {
+    reader.take(len as u64).read_to_end(buf)
+}
+
+/// Decodes a blob and returns its uncompressed data.
+fn decode_blob(blob: &[u8]) -> Result<Vec<u8>, PbfErrorKind> {
+    let blob = Blob::decode(blob)?;
+    let raw_size = blob.raw_size.map(|raw_size| raw_size.max(0) as usize);
+
+    let blob_data = if let Some(raw) = blob.raw {
+        raw
+    } else if let Some(data) = blob.zlib_data {
         // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
+        let mut blob_buf = Vec::with_capacity(raw_size.unwrap_or(0));
         let mut decoder = ZlibDecoder::new(&data[..]);
         decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
+        blob_buf
+    } else if let Some(data) = blob.zstd_data {
+        decompress_zstd(&data, raw_size)?
+    } else if let Some(data) = blob.lz4_data {
+        decompress_lz4(&data, raw_size)?
+    } else if let Some(data) = blob.lzma_data {
+        decompress_lzma(&data, raw_size)?
     } else {
-        return Err(io::Error::new(
+        return Err(PbfErrorKind::UnsupportedCompression("unknown"));
+    };
+    if blob_data.len() != raw_size.unwrap_or(blob_data.len()) {
+        return Err(PbfErrorKind::InvalidData(io::Error::new(
             io::ErrorKind::InvalidData,
-            "unknown compression",
-        ));
+            "size of uncompressed data does not match the raw size of the blob",
+        )));
+    }
+    Ok(blob_data)
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    let mut blob_buf = Vec::with_capacity(raw_size.unwrap_or(0));
+    zstd::stream::copy_decode(data, &mut blob_buf)?;
+    Ok(blob_buf)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8], _raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    Err(PbfErrorKind::UnsupportedCompression("zstd"))
+}
+
+/// Decompresses data in the LZ4 block format, which requires the raw size.
+#[cfg(feature = "lz4")]
+fn decompress_lz4(data: &[u8], raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    let invalid_data =
+        |msg: String| PbfErrorKind::InvalidData(io::Error::new(io::ErrorKind::InvalidData, msg));
+    let raw_size =
+        raw_size.ok_or_else(|| invalid_data("lz4 compressed blob without raw size".into()))?;
+    lz4_flex::block::decompress(data, raw_size).map_err(|e| invalid_data(e.to_string()))
+}
+
+#[cfg(not(feature = "lz4"))]
+fn decompress_lz4(_data: &[u8], _raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    Err(PbfErrorKind::UnsupportedCompression("lz4"))
+}
+
+/// Decompresses data in the xz or legacy lzma format.
+#[cfg(feature = "lzma")]
+fn decompress_lzma(data: &[u8], raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    let stream = xz2::stream::Stream::new_auto_decoder(u64::MAX, 0).map_err(io::Error::from)?;
+    let mut blob_buf = Vec::with_capacity(raw_size.unwrap_or(0));
+    xz2::read::XzDecoder::new_stream(data, stream).read_to_end(&mut blob_buf)?;
+    Ok(blob_buf)
+}
+
+#[cfg(not(feature = "lzma"))]
+fn decompress_lzma(_data: &[u8], _raw_size: Option<usize>) -> Result<Vec<u8>, PbfErrorKind> {
+    Err(PbfErrorKind::UnsupportedCompression("lzma"))
+}
+
+pub fn read_block<T: prost::Message + Default>(
+    data: &[u8],
+    idx: &BlockIndex,
+) -> Result<T, PbfError> {
+    let error = |kind| PbfError {
+        kind,
+        offset: idx.blob_start,
+        block_number: idx.block_number,
     };
-    Ok(T::decode(blob_data.as_slice())?)
+    let blob = data
+        .get(idx.blob_start..idx.blob_start + idx.blob_len)
+        .ok_or_else(|| error(PbfErrorKind::Truncated))?;
+    let blob_data = decode_blob(blob).map_err(error)?;
+    T::decode(blob_data.as_slice()).map_err(|e| error(e.into()))
 }
 
 fn blob_type_and_granularity_from_blob_info(
     blob_start: usize,
     blob_len: usize,
-    blob: Vec<u8>,
-) -> Result<BlockIndex, io::Error> {
-    let blob = Blob::decode(blob.as_slice())?;
-
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        // use raw bytes
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
-        // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
-        let mut decoder = ZlibDecoder::new(&data[..]);
-        decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
-    } else {
-        panic!("can only read raw or zlib compressed blob");
+    block_number: usize,
+    blob: &[u8],
+) -> Result<BlockIndex, PbfError> {
+    let error = |kind| PbfError {
+        kind,
+        offset: blob_start,
+        block_number,
     };
-    assert_eq!(
-        blob_data.len(),
-        blob.raw_size.unwrap_or(blob_data.len() as i32) as usize
-    );
-
-    let (block_type, granularity) = type_and_granularity_from_osmdata_blob(&blob_data[..])?;
+    let blob_data = decode_blob(blob).map_err(error)?;
+    let (block_type, granularity) =
+        type_and_granularity_from_osmdata_blob(&blob_data[..]).map_err(error)?;
     Ok(BlockIndex {
         block_type,
         granularity: Some(granularity),
         blob_start,
         blob_len,
+        block_number,
     })
 }
 
-pub fn build_block_index(pbf_data: &[u8]) -> Vec<BlockIndex> {
-    let mut result: Vec<BlockIndex> = BlockIndexIterator::new(pbf_data)
-        .par_bridge()
-        .filter_map(|blob| {
-            let block = match blob {
-                Ok(BlobInfo::Header(b)) => Ok(b),
-                Ok(BlobInfo::Unknown(start, len, blob)) => {
-                    blob_type_and_granularity_from_blob_info(start, len, blob)
-                }
-                Err(e) => Err(e),
-            };
-            match block {
-                Ok(b) => Some(b),
-                Err(e) => {
-                    eprintln!("Skipping block due to error: {}", e);
-                    None
+/// Builds the index of the blocks of the PBF data.
+///
+/// Corrupt blocks are not indexed, their errors are returned ordered by block
+/// number instead.
+pub fn build_block_index(pbf_data: &[u8]) -> (Vec<BlockIndex>, Vec<PbfError>) {
+    let (mut result, mut errors): (Vec<BlockIndex>, Vec<PbfError>) =
+        BlockIndexIterator::new(pbf_data)
+            .par_bridge()
+            .map(|blob| match blob? {
+                BlobInfo::Header(b) => Ok(b),
+                BlobInfo::Unknown(start, len, block_number, blob) => {
+                    blob_type_and_granularity_from_blob_info(start, len, block_number, blob)
                 }
-            }
-        })
-        .collect();
+            })
+            .partition_map(|block| match block {
+                Ok(b) => Either::Left(b),
+                Err(e) => Either::Right(e),
+            });
     result.par_sort_unstable();
+    errors.sort_unstable_by_key(|e| e.block_number);
     info!("Found {} blocks", result.len());
-    result
+    (result, errors)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::osmxml;
+
+    const XML: &str = r#"<osm>
+      <node id="1" lat="0" lon="0"/>
+      <way id="1"><nd ref="1"/></way>
+    </osm>"#;
+
+    #[test]
+    fn test_build_block_index() {
+        let data = osmxml::to_pbf(XML.as_bytes()).unwrap();
+        let (index, errors) = build_block_index(&data);
+        assert!(errors.is_empty());
+        let types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(
+            types,
+            vec![BlockType::Header, BlockType::Nodes, BlockType::Ways]
+        );
+        assert_eq!(index[2].block_number, 2);
+        let block: PrimitiveBlock = read_block(&data, &index[2]).unwrap();
+        assert_eq!(block.primitivegroup[0].ways[0].id, 1);
+    }
+
+    #[cfg(any(feature = "zstd", feature = "lz4", feature = "lzma"))]
+    fn test_fixture(data: &[u8]) {
+        let (index, errors) = build_block_index(data);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(index.len(), 2);
+        let header: HeaderBlock = read_block(data, &index[0]).unwrap();
+        assert_eq!(header.writingprogram.as_deref(), Some("fixture"));
+        let block: PrimitiveBlock = read_block(data, &index[1]).unwrap();
+        let node = &block.primitivegroup[0].nodes[0];
+        assert_eq!((node.id, node.lat, node.lon), (1, 525_000_000, 133_000_000));
+        assert_eq!(block.stringtable.s[node.vals[0] as usize], b"test");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        test_fixture(include_bytes!("../tests/fixtures/zstd.osm.pbf"));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_lz4() {
+        test_fixture(include_bytes!("../tests/fixtures/lz4.osm.pbf"));
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn test_lzma() {
+        test_fixture(include_bytes!("../tests/fixtures/lzma.osm.pbf"));
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_zstd_disabled() {
+        let data = include_bytes!("../tests/fixtures/zstd.osm.pbf");
+        let (_, errors) = build_block_index(data);
+        assert!(matches!(
+            errors[0].kind,
+            PbfErrorKind::UnsupportedCompression("zstd")
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = osmxml::to_pbf(XML.as_bytes()).unwrap();
+        let (index, _) = build_block_index(&data);
+        let end = index[2].blob_start + 1;
+        let (index, errors) = build_block_index(&data[..end]);
+        assert_eq!(index.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, PbfErrorKind::Truncated));
+        assert_eq!(errors[0].block_number, 2);
+    }
+
+    #[test]
+    fn test_copy_blocks() {
+        let data = osmxml::to_pbf(XML.as_bytes()).unwrap();
+        let mut copy = Vec::new();
+        assert_eq!(copy_blocks(&data[..], &mut copy).unwrap(), 3);
+        assert_eq!(copy, data);
+
+        let (index, _) = build_block_index(&data);
+        let end = index[2].blob_start + 1;
+        let error = copy_blocks(&data[..end], Vec::new()).unwrap_err();
+        let error = error.into_inner().unwrap().downcast::<PbfError>().unwrap();
+        assert!(matches!(error.kind, PbfErrorKind::Truncated));
+        assert_eq!(error.block_number, 2);
+    }
+
+    #[test]
+    fn test_corrupt_block() {
+        let mut data = osmxml::to_pbf(XML.as_bytes()).unwrap();
+        let (index, _) = build_block_index(&data);
+        // corrupt the blob of the nodes block, the other blocks are still indexed
+        let nodes = &index[1];
+        for byte in &mut data[nodes.blob_start..nodes.blob_start + nodes.blob_len] {
+            *byte = 0xff;
+        }
+        let (index, errors) = build_block_index(&data);
+        assert_eq!(index.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].block_number, 1);
+        assert_eq!(errors[0].offset, nodes.blob_start);
+        assert!(read_block::<PrimitiveBlock>(&data, nodes).is_err());
+    }
 }
@@ -0,0 +1,1036 @@
+//! Reader of the OSM XML format.
+//!
+//! An XML input is converted block by block into a temporary PBF file, which
+//! is then fed into the same compilation pipeline as a regular PBF input.
+//! Nodes are encoded as non-dense nodes, and all elements are grouped into
+//! primitive blocks of at most `MAX_BLOCK_ELEMENTS` elements of the same type.
+//!
+//! Files saved by editors like JOSM are supported: Negative ids, which are
+//! assigned to objects not uploaded yet, are mapped to new ids following the
+//! largest id of their type in the file. Elements marked with
+//! `action="delete"` are skipped.
+//!
+//! See <https://wiki.openstreetmap.org/wiki/OSM_XML>.
+
+use crate::osmpbf;
+
+use ahash::AHashMap;
+use byteorder::{NetworkEndian, WriteBytesExt};
+use bzip2::read::BzDecoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use prost::Message;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Maximum number of elements in a single primitive block (same as osmium)
+const MAX_BLOCK_ELEMENTS: usize = 8000;
+
+/// Granularity of coordinates in nanodegrees (default of the PBF format)
+const DEFAULT_GRANULARITY: i32 = 100;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// Checks whether the input is an (optionally compressed) OSM XML file.
+///
+/// The format is detected by the file extension, or if the extension is not
+/// conclusive, by the beginning of the data, which is decompressed first if
+/// it starts with the magic bytes of gzip or bzip2.
+pub fn is_osm_xml(path: &Path, data: &[u8]) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    if [".osm", ".osm.gz", ".osm.bz2", ".xml"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    {
+        return true;
+    }
+    if data.starts_with(GZIP_MAGIC) || data.starts_with(BZIP2_MAGIC) {
+        // the beginning suffices, also if the data is truncated
+        let mut prefix = Vec::new();
+        let _ = decompress(data).take(64).read_to_end(&mut prefix);
+        is_xml(&prefix)
+    } else {
+        is_xml(data)
+    }
+}
+
+/// Checks whether uncompressed data starts like an OSM XML document.
+fn is_xml(data: &[u8]) -> bool {
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data); // BOM
+    let start = data
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    data[start..].starts_with(b"<?xml") || data[start..].starts_with(b"<osm")
+}
+
+/// Converts uncompressed OSM XML data read from `input` into PBF data written
+/// to `out`.
+///
+/// The data is read twice from the readers returned by `input`: first to
+/// map the negative ids of new objects, then to convert the elements. New
+/// objects are written after all other elements, and elements marked with
+/// `action="delete"` are skipped.
+///
+/// With `check_sorted`, the input is expected to be sorted like a PBF file,
+/// i.e. nodes, then ways, then relations, each by ascending id, where new
+/// objects may follow each type. Otherwise, the elements are written in the
+/// order of the input, which has to be sorted afterwards. Each block is
+/// written as soon as it is full, therefore the memory usage only depends on
+/// the number of new objects, but not on the size of the input.
+pub fn write_pbf<R: Read>(
+    mut input: impl FnMut() -> R,
+    check_sorted: bool,
+    mut out: impl Write,
+) -> io::Result<()> {
+    let new_ids = NewIds::scan(input())?;
+
+    let mut reader = Reader::from_reader(BufReader::new(input()));
+    reader.trim_text(true);
+
+    // written before the first element, which follows the `bounds`
+    let mut header = Some(osmpbf::HeaderBlock {
+        required_features: vec!["OsmSchema-V0.6".into()],
+        ..Default::default()
+    });
+    // the blocks of new objects are buffered, and appended to the others
+    let mut new_data = Vec::new();
+    let mut writers: [BlockWriter<&mut dyn Write>; 2] = [
+        BlockWriter::new(&mut out, DEFAULT_GRANULARITY),
+        BlockWriter::new(&mut new_data, DEFAULT_GRANULARITY),
+    ];
+    // index of the writer of the current element, 1 for new objects
+    let mut current = 0;
+    let mut element: Option<Element> = None;
+    let mut last_elements = [(ElementType::Node, None); 2];
+
+    let mut buf = Vec::new();
+    let mut skipped_buf = Vec::new();
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(invalid_data)?;
+        let (e, is_empty) = match event {
+            Event::Start(ref e) => (e, false),
+            Event::Empty(ref e) => (e, true),
+            Event::End(ref e) => {
+                if let Some(element_type) = ElementType::from_name(e.name().as_ref()) {
+                    let element = element
+                        .take()
+                        .filter(|element| element.element_type() == element_type)
+                        .ok_or_else(|| invalid_data("unexpected closing tag"))?;
+                    writers[current].push(element)?;
+                }
+                buf.clear();
+                continue;
+            }
+            Event::Eof => break,
+            _ => {
+                buf.clear();
+                continue;
+            }
+        };
+
+        let blocks = &mut writers[current];
+        match (e.name().as_ref(), &mut header) {
+            (b"osm", Some(header)) => header.writingprogram = attribute(e, "generator")?,
+            (b"bounds", Some(header)) => header.bbox = Some(parse_bounds(e)?),
+            (b"node" | b"way" | b"relation", _) => {
+                let element_type = ElementType::from_name(e.name().as_ref()).unwrap();
+                if is_deleted(e)? {
+                    if !is_empty {
+                        reader
+                            .read_to_end_into(e.name(), &mut skipped_buf)
+                            .map_err(invalid_data)?;
+                    }
+                    buf.clear();
+                    continue;
+                }
+                let id: i64 = required_attribute(e, "id")?;
+                current = (id < 0) as usize;
+                let id = new_ids.get(element_type, id)?;
+                if check_sorted {
+                    check_order(last_elements[current], element_type, id)?;
+                }
+                last_elements[current] = (element_type, Some(id));
+                if let Some(header) = header.take() {
+                    writers[0].write_header(&header)?;
+                }
+
+                let blocks = &mut writers[current];
+                blocks.prepare(element_type)?;
+                let info = parse_info(e, blocks)?;
+                let new_element = match element_type {
+                    ElementType::Node => Element::Node(osmpbf::Node {
+                        id,
+                        info,
+                        lat: parse_coord(required_attribute(e, "lat")?),
+                        lon: parse_coord(required_attribute(e, "lon")?),
+                        ..Default::default()
+                    }),
+                    ElementType::Way => Element::Way(
+                        osmpbf::Way {
+                            id,
+                            info,
+                            ..Default::default()
+                        },
+                        0,
+                    ),
+                    ElementType::Relation => Element::Relation(
+                        osmpbf::Relation {
+                            id,
+                            info,
+                            ..Default::default()
+                        },
+                        0,
+                    ),
+                };
+                if is_empty {
+                    blocks.push(new_element)?;
+                } else {
+                    element = Some(new_element);
+                }
+            }
+            (b"tag", _) => {
+                let key: String = required_attribute(e, "k")?;
+                let value: String = required_attribute(e, "v")?;
+                let key = blocks.string_id(key.as_bytes());
+                let value = blocks.string_id(value.as_bytes());
+                match element {
+                    Some(Element::Node(ref mut node)) => {
+                        node.keys.push(key);
+                        node.vals.push(value);
+                    }
+                    Some(Element::Way(ref mut way, _)) => {
+                        way.keys.push(key);
+                        way.vals.push(value);
+                    }
+                    Some(Element::Relation(ref mut relation, _)) => {
+                        relation.keys.push(key);
+                        relation.vals.push(value);
+                    }
+                    None => return Err(invalid_data("found tag outside of an element")),
+                }
+            }
+            (b"nd", _) => match element {
+                Some(Element::Way(ref mut way, ref mut last_ref)) => {
+                    let node_ref = new_ids.get(ElementType::Node, required_attribute(e, "ref")?)?;
+                    way.refs.push(node_ref - *last_ref);
+                    *last_ref = node_ref;
+                }
+                _ => return Err(invalid_data("found nd outside of a way")),
+            },
+            (b"member", _) => match element {
+                Some(Element::Relation(ref mut relation, ref mut last_memid)) => {
+                    let member_type: String = required_attribute(e, "type")?;
+                    let (member_type, element_type) = match &member_type[..] {
+                        "node" => (osmpbf::relation::MemberType::Node, ElementType::Node),
+                        "way" => (osmpbf::relation::MemberType::Way, ElementType::Way),
+                        "relation" => (
+                            osmpbf::relation::MemberType::Relation,
+                            ElementType::Relation,
+                        ),
+                        _ => return Err(invalid_data("invalid member type")),
+                    };
+                    let memid = new_ids.get(element_type, required_attribute(e, "ref")?)?;
+                    let role: String = attribute(e, "role")?.unwrap_or_default();
+
+                    relation.types.push(member_type as i32);
+                    relation.memids.push(memid - *last_memid);
+                    relation
+                        .roles_sid
+                        .push(blocks.string_id(role.as_bytes()) as i32);
+                    *last_memid = memid;
+                }
+                _ => return Err(invalid_data("found member outside of a relation")),
+            },
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    if element.is_some() {
+        return Err(invalid_data("unexpected end of file"));
+    }
+    if let Some(header) = header {
+        writers[0].write_header(&header)?;
+    }
+    let [blocks, new_blocks] = writers;
+    blocks.finish()?;
+    new_blocks.finish()?;
+    out.write_all(&new_data)?;
+    out.flush()
+}
+
+/// Mapping of the negative ids of new objects, which editors like JOSM assign
+/// to objects not uploaded yet, to new ids following the largest id of their
+/// type in the order of appearance.
+#[derive(Debug, Default)]
+struct NewIds {
+    ids: [AHashMap<i64, i64>; 3],
+}
+
+impl NewIds {
+    /// Scans the ids of all elements in the uncompressed OSM XML `input`.
+    fn scan(input: impl Read) -> io::Result<Self> {
+        let mut reader = Reader::from_reader(BufReader::new(input));
+        let mut max_ids = [0; 3];
+        let mut new_ids = Vec::new();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf).map_err(invalid_data)? {
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    if let Some(element_type) = ElementType::from_name(e.name().as_ref()) {
+                        let id: i64 = required_attribute(e, "id")?;
+                        let max_id = &mut max_ids[element_type as usize];
+                        *max_id = id.max(*max_id);
+                        if id < 0 && !is_deleted(e)? {
+                            new_ids.push((element_type, id));
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+            buf.clear();
+        }
+
+        let mut result = Self::default();
+        for (element_type, id) in new_ids {
+            let ids = &mut result.ids[element_type as usize];
+            let new_id = max_ids[element_type as usize] + ids.len() as i64 + 1;
+            ids.entry(id).or_insert(new_id);
+        }
+        Ok(result)
+    }
+
+    /// Returns the id of an element or a reference to an element.
+    fn get(&self, element_type: ElementType, id: i64) -> io::Result<i64> {
+        if id >= 0 {
+            return Ok(id);
+        }
+        self.ids[element_type as usize]
+            .get(&id)
+            .copied()
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "reference to missing new {:?} {}",
+                    element_type, id
+                ))
+            })
+    }
+}
+
+/// Checks whether an element is marked as deleted by an editor like JOSM.
+fn is_deleted(e: &BytesStart) -> io::Result<bool> {
+    Ok(attribute::<String>(e, "action")?.as_deref() == Some("delete"))
+}
+
+/// Converts (optionally compressed) OSM XML data into in-memory PBF data.
+#[cfg(test)]
+pub fn to_pbf(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut result = Vec::new();
+    write_pbf(|| decompress(data), true, &mut result)?;
+    Ok(result)
+}
+
+/// Decompresses gzip or bzip2 compressed data, other data is passed through.
+pub fn decompress(data: &[u8]) -> Box<dyn Read + '_> {
+    if data.starts_with(GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(data))
+    } else if data.starts_with(BZIP2_MAGIC) {
+        Box::new(BzDecoder::new(data))
+    } else {
+        Box::new(data)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ElementType {
+    Node,
+    Way,
+    Relation,
+}
+
+impl ElementType {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"node" => Some(Self::Node),
+            b"way" => Some(Self::Way),
+            b"relation" => Some(Self::Relation),
+            _ => None,
+        }
+    }
+}
+
+/// Element in construction, ways and relations carry the last delta coded id.
+pub enum Element {
+    Node(osmpbf::Node),
+    Way(osmpbf::Way, i64),
+    Relation(osmpbf::Relation, i64),
+}
+
+impl Element {
+    fn element_type(&self) -> ElementType {
+        match self {
+            Element::Node(_) => ElementType::Node,
+            Element::Way(..) => ElementType::Way,
+            Element::Relation(..) => ElementType::Relation,
+        }
+    }
+}
+
+/// Collects elements into primitive blocks and encodes them as PBF blobs.
+pub struct BlockWriter<W> {
+    out: W,
+    granularity: i32,
+    dense_nodes: bool,
+    compress: bool,
+    block: Option<(ElementType, osmpbf::PrimitiveGroup)>,
+    num_elements: usize,
+    stringtable: Vec<Vec<u8>>,
+    string_ids: AHashMap<Vec<u8>, u32>,
+}
+
+impl<W: Write> BlockWriter<W> {
+    /// Creates a writer of blocks with coordinates in units of `granularity`
+    /// nanodegrees.
+    pub fn new(out: W, granularity: i32) -> Self {
+        Self {
+            out,
+            granularity,
+            dense_nodes: false,
+            compress: false,
+            block: None,
+            num_elements: 0,
+            stringtable: Vec::new(),
+            string_ids: AHashMap::new(),
+        }
+    }
+
+    /// Encodes nodes as dense nodes.
+    pub fn with_dense_nodes(mut self) -> Self {
+        self.dense_nodes = true;
+        self
+    }
+
+    /// Compresses the blobs with zlib.
+    pub fn with_compression(mut self) -> Self {
+        self.compress = true;
+        self
+    }
+
+    /// Writes the header block, which has to precede all elements.
+    pub fn write_header(&mut self, header: &osmpbf::HeaderBlock) -> io::Result<()> {
+        if self.compress {
+            write_zlib_blob(&mut self.out, "OSMHeader", header)
+        } else {
+            write_blob(&mut self.out, "OSMHeader", header)
+        }
+    }
+
+    /// Makes sure that the current block can take an element of the given
+    /// type, otherwise the current block is flushed.
+    pub fn prepare(&mut self, element_type: ElementType) -> io::Result<()> {
+        match self.block {
+            Some((block_type, _))
+                if block_type == element_type && self.num_elements < MAX_BLOCK_ELEMENTS => {}
+            _ => {
+                self.flush()?;
+                self.block = Some((element_type, osmpbf::PrimitiveGroup::default()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn string_id(&mut self, s: &[u8]) -> u32 {
+        if self.stringtable.is_empty() {
+            // index 0 is reserved as delimiter
+            self.stringtable.push(Vec::new());
+        }
+        if let Some(&id) = self.string_ids.get(s) {
+            return id;
+        }
+        let id = self.stringtable.len() as u32;
+        self.stringtable.push(s.to_vec());
+        self.string_ids.insert(s.to_vec(), id);
+        id
+    }
+
+    pub fn push(&mut self, element: Element) -> io::Result<()> {
+        self.prepare(element.element_type())?;
+        let (_, group) = self.block.as_mut().unwrap();
+        match element {
+            Element::Node(node) => group.nodes.push(node),
+            Element::Way(way, _) => group.ways.push(way),
+            Element::Relation(relation, _) => group.relations.push(relation),
+        }
+        self.num_elements += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some((_, mut group)) = self.block.take() {
+            if self.dense_nodes && !group.nodes.is_empty() {
+                group.dense = Some(dense_nodes(std::mem::take(&mut group.nodes)));
+            }
+            if self.stringtable.is_empty() {
+                self.stringtable.push(Vec::new());
+            }
+            let block = osmpbf::PrimitiveBlock {
+                stringtable: osmpbf::StringTable {
+                    s: std::mem::take(&mut self.stringtable),
+                },
+                primitivegroup: vec![group],
+                granularity: Some(self.granularity),
+                ..Default::default()
+            };
+            if self.compress {
+                write_zlib_blob(&mut self.out, "OSMData", &block)?;
+            } else {
+                write_blob(&mut self.out, "OSMData", &block)?;
+            }
+        }
+        self.string_ids.clear();
+        self.num_elements = 0;
+        Ok(())
+    }
+
+    /// Flushes the last block and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Encodes nodes as dense nodes, i.e. with delta coded ids, coordinates and
+/// metadata. The metadata is only encoded if all nodes have metadata.
+fn dense_nodes(nodes: Vec<osmpbf::Node>) -> osmpbf::DenseNodes {
+    let mut dense = osmpbf::DenseNodes::default();
+    let mut dense_info = if nodes.iter().all(|node| node.info.is_some()) {
+        Some(osmpbf::DenseInfo::default())
+    } else {
+        None
+    };
+
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+    let (mut timestamp, mut changeset, mut uid, mut user_sid) = (0, 0, 0, 0);
+    for node in nodes {
+        dense.id.push(node.id - id);
+        dense.lat.push(node.lat - lat);
+        dense.lon.push(node.lon - lon);
+        id = node.id;
+        lat = node.lat;
+        lon = node.lon;
+
+        for (key, val) in node.keys.into_iter().zip(node.vals) {
+            dense.keys_vals.push(key as i32);
+            dense.keys_vals.push(val as i32);
+        }
+        dense.keys_vals.push(0);
+
+        if let (Some(dense_info), Some(info)) = (dense_info.as_mut(), node.info) {
+            let node_timestamp = info.timestamp.unwrap_or_default();
+            let node_changeset = info.changeset.unwrap_or_default();
+            let node_uid = info.uid.unwrap_or_default();
+            let node_user_sid = info.user_sid.unwrap_or_default() as i32;
+            dense_info.version.push(info.version.unwrap_or(-1));
+            dense_info.timestamp.push(node_timestamp - timestamp);
+            dense_info.changeset.push(node_changeset - changeset);
+            dense_info.uid.push(node_uid - uid);
+            dense_info.user_sid.push(node_user_sid - user_sid);
+            timestamp = node_timestamp;
+            changeset = node_changeset;
+            uid = node_uid;
+            user_sid = node_user_sid;
+        }
+    }
+    // tags are omitted if no node has tags
+    if dense.keys_vals.iter().all(|&key| key == 0) {
+        dense.keys_vals.clear();
+    }
+    dense.denseinfo = dense_info;
+    dense
+}
+
+/// Writes a message as an uncompressed blob including its blob header.
+pub fn write_blob(out: &mut impl Write, blob_type: &str, message: &impl Message) -> io::Result<()> {
+    let raw = message.encode_to_vec();
+    let blob = osmpbf::Blob {
+        raw_size: Some(raw.len() as i32),
+        raw: Some(raw),
+        ..Default::default()
+    };
+    write_blob_with_header(out, blob_type, &blob)
+}
+
+/// Writes a message as a zlib compressed blob including its blob header.
+pub fn write_zlib_blob(
+    out: &mut impl Write,
+    blob_type: &str,
+    message: &impl Message,
+) -> io::Result<()> {
+    let raw = message.encode_to_vec();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&raw)?;
+    let blob = osmpbf::Blob {
+        raw_size: Some(raw.len() as i32),
+        zlib_data: Some(encoder.finish()?),
+        ..Default::default()
+    };
+    write_blob_with_header(out, blob_type, &blob)
+}
+
+fn write_blob_with_header(
+    out: &mut impl Write,
+    blob_type: &str,
+    blob: &osmpbf::Blob,
+) -> io::Result<()> {
+    let blob = blob.encode_to_vec();
+    let blob_header = osmpbf::BlobHeader {
+        r#type: blob_type.into(),
+        indexdata: None,
+        datasize: blob.len() as i32,
+    }
+    .encode_to_vec();
+
+    out.write_i32::<NetworkEndian>(blob_header.len() as i32)?;
+    out.write_all(&blob_header)?;
+    out.write_all(&blob)?;
+    Ok(())
+}
+
+fn check_order(
+    (last_type, last_id): (ElementType, Option<i64>),
+    element_type: ElementType,
+    id: i64,
+) -> io::Result<()> {
+    let is_sorted = match last_id {
+        None => true,
+        Some(last_id) => last_type < element_type || (last_type == element_type && last_id < id),
+    };
+    if !is_sorted {
+        return Err(invalid_data(format!(
+            "input is not sorted: {:?} {} follows {:?} {}",
+            element_type,
+            id,
+            last_type,
+            last_id.unwrap()
+        )));
+    }
+    Ok(())
+}
+
+/// Converts a coordinate in degrees into units of the default granularity
+/// (100 nanodegrees).
+fn parse_coord(degrees: f64) -> i64 {
+    (degrees * 1e7).round() as i64
+}
+
+/// Parses the metadata attributes of an element, if there are any.
+fn parse_info<W: Write>(
+    e: &BytesStart,
+    blocks: &mut BlockWriter<W>,
+) -> io::Result<Option<osmpbf::Info>> {
+    let timestamp = match attribute::<String>(e, "timestamp")? {
+        Some(timestamp) => Some(
+            parse_timestamp(&timestamp)
+                .ok_or_else(|| invalid_data(format!("invalid timestamp '{}'", timestamp)))?,
+        ),
+        None => None,
+    };
+    let info = osmpbf::Info {
+        version: attribute(e, "version")?,
+        timestamp,
+        changeset: attribute(e, "changeset")?,
+        uid: attribute(e, "uid")?,
+        user_sid: attribute::<String>(e, "user")?.map(|user| blocks.string_id(user.as_bytes())),
+        ..Default::default()
+    };
+    Ok(Some(info).filter(|info| *info != osmpbf::Info::default()))
+}
+
+/// Parses a timestamp of the form `YYYY-MM-DDThh:mm:ssZ` into seconds since
+/// the epoch (units of the default date granularity).
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let b = s.as_bytes();
+    if b.len() != 20 || [b[4], b[7], b[10], b[13], b[16], b[19]] != *b"--T::Z" {
+        return None;
+    }
+    let num = |start: usize, end: usize| s[start..end].parse::<u32>().ok().map(i64::from);
+    let (year, month, day) = (num(0, 4)?, num(5, 7)?, num(8, 10)?);
+    let (hour, minute, second) = (num(11, 13)?, num(14, 16)?, num(17, 19)?);
+
+    // days since the epoch, see
+    // <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+fn parse_bounds(e: &BytesStart) -> io::Result<osmpbf::HeaderBBox> {
+    // Header bbox is always in nanodegrees
+    let nano = |name| -> io::Result<i64> {
+        let degrees: f64 = required_attribute(e, name)?;
+        Ok((degrees * 1e9).round() as i64)
+    };
+    Ok(osmpbf::HeaderBBox {
+        left: nano("minlon")?,
+        right: nano("maxlon")?,
+        top: nano("maxlat")?,
+        bottom: nano("minlat")?,
+    })
+}
+
+pub fn attribute<T: FromStr>(e: &BytesStart, name: &str) -> io::Result<Option<T>> {
+    let attr = match e.try_get_attribute(name).map_err(invalid_data)? {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let value = attr.unescape_value().map_err(invalid_data)?;
+    value.parse().map(Some).map_err(|_| {
+        invalid_data(format!(
+            "invalid value of attribute '{}': '{}'",
+            name, value
+        ))
+    })
+}
+
+pub fn required_attribute<T: FromStr>(e: &BytesStart, name: &str) -> io::Result<T> {
+    attribute(e, name)?.ok_or_else(|| {
+        invalid_data(format!(
+            "missing attribute '{}' in '{}'",
+            name,
+            String::from_utf8_lossy(e.name().as_ref())
+        ))
+    })
+}
+
+pub fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::osmpbf::{build_block_index, read_block, BlockType};
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="test">
+  <bounds minlat="52.5" minlon="13.3" maxlat="52.6" maxlon="13.4"/>
+  <node id="1" lat="52.51" lon="13.31"/>
+  <node id="2" lat="52.52" lon="13.32">
+    <tag k="amenity" v="pub"/>
+    <tag k="name" v="Zum &amp; Wohl"/>
+  </node>
+  <way id="10">
+    <nd ref="2"/>
+    <nd ref="1"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <relation id="100">
+    <member type="way" ref="10" role="outer"/>
+    <member type="node" ref="1" role=""/>
+    <tag k="type" v="multipolygon"/>
+  </relation>
+</osm>
+"#;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn string(block: &osmpbf::PrimitiveBlock, idx: u32) -> &str {
+        std::str::from_utf8(&block.stringtable.s[idx as usize]).unwrap()
+    }
+
+    #[test]
+    fn test_detection() {
+        assert!(is_osm_xml(Path::new("map.osm"), b""));
+        assert!(is_osm_xml(Path::new("map.osm.bz2"), b""));
+        assert!(is_osm_xml(Path::new("map"), XML.as_bytes()));
+        assert!(is_osm_xml(Path::new("map"), b"\n  <osm version=\"0.6\">"));
+        assert!(is_osm_xml(Path::new("map"), &gzip(XML.as_bytes())));
+        assert!(!is_osm_xml(Path::new("map"), &gzip(b"\0\0\0\x0dOSMHeader")));
+        assert!(!is_osm_xml(Path::new("map"), GZIP_MAGIC));
+        assert!(!is_osm_xml(
+            Path::new("map.osm.pbf"),
+            b"\0\0\0\x0dOSMHeader"
+        ));
+    }
+
+    #[test]
+    fn test_to_pbf() {
+        let data = to_pbf(XML.as_bytes()).unwrap();
+        let (index, _) = build_block_index(&data);
+        let types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                BlockType::Header,
+                BlockType::Nodes,
+                BlockType::Ways,
+                BlockType::Relations
+            ]
+        );
+
+        let header: osmpbf::HeaderBlock = read_block(&data, &index[0]).unwrap();
+        assert_eq!(header.writingprogram.as_deref(), Some("test"));
+        assert_eq!(header.bbox.unwrap().left, 13_300_000_000);
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[1]).unwrap();
+        let nodes = &block.primitivegroup[0].nodes;
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(
+            (nodes[1].id, nodes[1].lat, nodes[1].lon),
+            (2, 525_200_000, 133_200_000)
+        );
+        assert_eq!(string(&block, nodes[1].keys[1]), "name");
+        assert_eq!(string(&block, nodes[1].vals[1]), "Zum & Wohl");
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[2]).unwrap();
+        let way = &block.primitivegroup[0].ways[0];
+        assert_eq!(way.refs, vec![2, -1]);
+        assert_eq!(string(&block, way.vals[0]), "residential");
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[3]).unwrap();
+        let relation = &block.primitivegroup[0].relations[0];
+        assert_eq!(relation.memids, vec![10, -9]);
+        assert_eq!(relation.types, vec![1, 0]);
+        assert_eq!(string(&block, relation.roles_sid[0] as u32), "outer");
+        assert_eq!(string(&block, relation.roles_sid[1] as u32), "");
+    }
+
+    #[test]
+    fn test_info() {
+        let xml = r#"<osm>
+          <node id="1" lat="0" lon="0" version="3" timestamp="2020-01-02T03:04:05Z"
+                changeset="42" uid="7" user="mapper"/>
+          <node id="2" lat="0" lon="0"/>
+        </osm>"#;
+        let data = to_pbf(xml.as_bytes()).unwrap();
+        let (index, _) = build_block_index(&data);
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[1]).unwrap();
+        let nodes = &block.primitivegroup[0].nodes;
+        let info = nodes[0].info.as_ref().unwrap();
+        assert_eq!(
+            (info.version, info.timestamp, info.changeset, info.uid),
+            (Some(3), Some(1_577_934_245), Some(42), Some(7))
+        );
+        assert_eq!(string(&block, info.user_sid.unwrap()), "mapper");
+        assert!(nodes[1].info.is_none());
+
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2000-02-29T23:59:59Z"), Some(951_868_799));
+        assert_eq!(parse_timestamp("2000-02-29"), None);
+    }
+
+    #[test]
+    fn test_dense_nodes() {
+        let mut blocks = BlockWriter::new(Vec::new(), 100)
+            .with_dense_nodes()
+            .with_compression();
+        let amenity = blocks.string_id(b"amenity");
+        let pub_ = blocks.string_id(b"pub");
+        let user = blocks.string_id(b"mapper");
+        let info = |version, timestamp| osmpbf::Info {
+            version: Some(version),
+            timestamp: Some(timestamp),
+            changeset: Some(42),
+            uid: Some(7),
+            user_sid: Some(user),
+            visible: None,
+        };
+        let nodes = vec![
+            osmpbf::Node {
+                id: 1,
+                lat: 10,
+                lon: 20,
+                info: Some(info(1, 100)),
+                ..Default::default()
+            },
+            osmpbf::Node {
+                id: 5,
+                keys: vec![amenity],
+                vals: vec![pub_],
+                lat: 5,
+                lon: 30,
+                info: Some(info(3, 150)),
+            },
+        ];
+        for node in nodes {
+            blocks.push(Element::Node(node)).unwrap();
+        }
+        let data = blocks.finish().unwrap();
+
+        let (index, _) = build_block_index(&data);
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].block_type, BlockType::DenseNodes);
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[0]).unwrap();
+        let dense = block.primitivegroup[0].dense.as_ref().unwrap();
+        assert_eq!(dense.id, vec![1, 4]);
+        assert_eq!(dense.lat, vec![10, -5]);
+        assert_eq!(dense.lon, vec![20, 10]);
+        assert_eq!(dense.keys_vals.len(), 4);
+        assert_eq!(string(&block, dense.keys_vals[1] as u32), "amenity");
+        assert_eq!(string(&block, dense.keys_vals[2] as u32), "pub");
+        assert_eq!(dense.keys_vals[3], 0);
+        let dense_info = dense.denseinfo.as_ref().unwrap();
+        assert_eq!(dense_info.version, vec![1, 3]);
+        assert_eq!(dense_info.timestamp, vec![100, 50]);
+        assert_eq!(dense_info.changeset, vec![42, 0]);
+        assert_eq!(string(&block, dense_info.user_sid[0] as u32), "mapper");
+        assert_eq!(dense_info.user_sid[1], 0);
+    }
+
+    #[test]
+    fn test_unsorted() {
+        let xml = r#"<osm><way id="2"/><node id="1" lat="0" lon="0"/></osm>"#;
+        assert!(to_pbf(xml.as_bytes()).is_err());
+        let xml = r#"<osm><node id="2" lat="0" lon="0"/><node id="1" lat="0" lon="0"/></osm>"#;
+        assert!(to_pbf(xml.as_bytes()).is_err());
+        write_pbf(|| xml.as_bytes(), false, &mut Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn test_josm() {
+        let xml = r#"<osm>
+          <node id="-2" action="modify" lat="0" lon="0"/>
+          <node id="5" lat="0" lon="0"/>
+          <node id="-1" action="modify" lat="0" lon="0"><tag k="amenity" v="pub"/></node>
+          <node id="-3" action="delete" lat="0" lon="0"/>
+          <node id="6" action="delete" lat="0" lon="0"><tag k="amenity" v="bar"/></node>
+          <way id="-1" action="modify"><nd ref="5"/><nd ref="-1"/><nd ref="-2"/></way>
+          <relation id="-1" action="modify">
+            <member type="way" ref="-1" role=""/>
+            <member type="node" ref="-2" role="stop"/>
+          </relation>
+        </osm>"#;
+        let data = to_pbf(xml.as_bytes()).unwrap();
+        let (index, _) = build_block_index(&data);
+        let types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                BlockType::Header,
+                BlockType::Nodes,
+                BlockType::Nodes,
+                BlockType::Ways,
+                BlockType::Relations
+            ]
+        );
+
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[1]).unwrap();
+        let ids: Vec<_> = block.primitivegroup[0]
+            .nodes
+            .iter()
+            .map(|node| node.id)
+            .collect();
+        assert_eq!(ids, vec![5]);
+        // new ids follow the largest id in the order of appearance
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[2]).unwrap();
+        let nodes = &block.primitivegroup[0].nodes;
+        let ids: Vec<_> = nodes.iter().map(|node| node.id).collect();
+        assert_eq!(ids, vec![7, 8]);
+        assert_eq!(string(&block, nodes[1].vals[0]), "pub");
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[3]).unwrap();
+        let way = &block.primitivegroup[0].ways[0];
+        assert_eq!(way.id, 1);
+        assert_eq!(way.refs, vec![5, 3, -1]);
+        let block: osmpbf::PrimitiveBlock = read_block(&data, &index[4]).unwrap();
+        let relation = &block.primitivegroup[0].relations[0];
+        assert_eq!(relation.id, 1);
+        assert_eq!(relation.memids, vec![1, 6]);
+
+        let xml = r#"<osm><way id="1"><nd ref="-1"/></way></osm>"#;
+        let error = to_pbf(xml.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "reference to missing new Node -1");
+    }
+
+    #[test]
+    fn test_streaming() {
+        use std::cell::Cell;
+
+        /// Counts the bytes read from the input.
+        struct CountingReader<'a>(&'a [u8], &'a Cell<usize>);
+
+        impl Read for CountingReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.read(buf)?;
+                self.1.set(self.1.get() + n);
+                Ok(n)
+            }
+        }
+
+        /// Records the output offset and the number of bytes read from the
+        /// input at each write.
+        struct RecordingWriter<'a>(Vec<u8>, Vec<(usize, usize)>, &'a Cell<usize>);
+
+        impl Write for RecordingWriter<'_> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.1.push((self.0.len(), self.2.get()));
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut xml = String::from("<osm>");
+        for id in 1..=2 * MAX_BLOCK_ELEMENTS + 1 {
+            xml.push_str(&format!(r#"<node id="{}" lat="0" lon="0"/>"#, id));
+        }
+        xml.push_str("</osm>");
+
+        let num_read = Cell::new(0);
+        let mut out = RecordingWriter(Vec::new(), Vec::new(), &num_read);
+        write_pbf(|| CountingReader(xml.as_bytes(), &num_read), true, &mut out).unwrap();
+
+        let (index, _) = build_block_index(&out.0);
+        let types: Vec<_> = index.iter().map(|idx| idx.block_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                BlockType::Header,
+                BlockType::Nodes,
+                BlockType::Nodes,
+                BlockType::Nodes
+            ]
+        );
+        // the first block of nodes is written before the input is read
+        // completely the second time
+        let (_, num_read) = out
+            .1
+            .iter()
+            .find(|&&(offset, _)| offset >= index[1].blob_start)
+            .unwrap();
+        assert!(*num_read < 2 * xml.len());
+    }
+
+    #[test]
+    fn test_gzip() {
+        assert_eq!(
+            to_pbf(&gzip(XML.as_bytes())).unwrap(),
+            to_pbf(XML.as_bytes()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Conversion of an osmflat archive back to OSM PBF.
+//!
+//! Nodes, ways and relations are written in the order of their OSM ids, also
+//! if the archive is ordered spatially (cf. `--spatial-order`). Nodes are
+//! encoded as dense nodes, and the blobs are compressed with zlib. The header
+//! contains the bounding box and the replication fields of the archive header.
+//!
+//! The OSM ids are taken from the `ids` sub-archive (compiled with `--ids`).
+//! Without it, ids are synthesized by numbering the elements of each type
+//! from 1 in the order of the archive. The archive does not store the ids of
+//! unresolved references of ways and relations, therefore these references
+//! are dropped.
+//!
+//! See <https://wiki.openstreetmap.org/wiki/PBF_Format>.
+
+use crate::osmchange::{self, delta_encode};
+use crate::osmpbf;
+use crate::osmxml::{self, BlockWriter, ElementType};
+use crate::Error;
+
+use log::info;
+use osmflat::{ElementIndex, FileResourceStorage, Id, Osm, RelationMembersRef};
+
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Writer of an osmflat archive as OSM PBF.
+///
+/// ```no_run
+/// # fn main() -> Result<(), osmflatc::Error> {
+/// let output = std::fs::File::create("output.osm.pbf")?;
+/// osmflatc::PbfWriter::new("input.osm.flatdata").write(std::io::BufWriter::new(output))?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PbfWriter {
+    input: PathBuf,
+    compress: bool,
+}
+
+impl PbfWriter {
+    /// Creates a writer of the archive with zlib compressed blobs.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            compress: true,
+        }
+    }
+
+    /// Sets whether to compress the blobs with zlib. Uncompressed blobs are
+    /// faster to write and to read, but much larger.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Writes the archive as PBF to the output.
+    pub fn write(&self, mut out: impl Write) -> Result<(), Error> {
+        let archive = Osm::open(FileResourceStorage::new(&self.input))?;
+        if archive.ids().is_none() {
+            info!("Archive was compiled without --ids, synthesizing ids");
+        }
+        let ids = ElementIds::new(&archive);
+
+        let mut header = osmchange::header(&archive);
+        header.required_features.push("DenseNodes".into());
+        header.optional_features.push("Sort.Type_then_ID".into());
+        if self.compress {
+            osmxml::write_zlib_blob(&mut out, "OSMHeader", &header)?;
+        } else {
+            osmxml::write_blob(&mut out, "OSMHeader", &header)?;
+        }
+
+        // coordinates are written in the granularity of the archive
+        let granularity = 1_000_000_000 / archive.header().coord_scale();
+        let mut blocks = BlockWriter::new(out, granularity).with_dense_nodes();
+        if self.compress {
+            blocks = blocks.with_compression();
+        }
+        let mut converter = ElementConverter::new(&archive, &ids, 1);
+
+        info!("Writing nodes...");
+        for idx in ids.nodes.by_id() {
+            let node = converter.node(idx, &mut blocks)?;
+            blocks.push(osmxml::Element::Node(node))?;
+        }
+        info!("Writing ways...");
+        for idx in ids.ways.by_id() {
+            let way = converter.way(idx, &mut blocks)?;
+            blocks.push(osmxml::Element::Way(way, 0))?;
+        }
+        info!("Writing relations...");
+        for idx in ids.relations.by_id() {
+            let relation = converter.relation(idx, &mut blocks)?;
+            blocks.push(osmxml::Element::Relation(relation, 0))?;
+        }
+
+        if converter.num_dropped_refs > 0 {
+            info!(
+                "Dropped {} unresolved references of ways and relations",
+                converter.num_dropped_refs
+            );
+        }
+        blocks.finish()?.flush()?;
+        Ok(())
+    }
+}
+
+/// Order of the elements of a type by their ids.
+enum ElementOrder<'a> {
+    /// The elements are ordered by id in the archive
+    Archive,
+    /// Indexes of the elements in the order of their ids from the id index
+    IdIndex(&'a [ElementIndex]),
+    /// Indexes of the elements sorted by their ids
+    Sorted(Vec<u64>),
+}
+
+/// OSM ids of the elements of a type.
+pub struct TypeIds<'a> {
+    /// Ids of the elements, synthesized from their indexes if missing
+    ids: Option<&'a [Id]>,
+    order: ElementOrder<'a>,
+    len: usize,
+}
+
+impl<'a> TypeIds<'a> {
+    fn new(ids: Option<&'a [Id]>, id_index_order: Option<&'a [ElementIndex]>, len: usize) -> Self {
+        let is_sorted = |ids: &[Id]| ids.windows(2).all(|w| w[0].value() < w[1].value());
+        let order = match ids {
+            Some(ids) if !is_sorted(ids) => match id_index_order {
+                Some(order) => ElementOrder::IdIndex(order),
+                None => {
+                    let mut order: Vec<u64> = (0..ids.len() as u64).collect();
+                    order.sort_unstable_by_key(|&idx| ids[idx as usize].value());
+                    ElementOrder::Sorted(order)
+                }
+            },
+            _ => ElementOrder::Archive,
+        };
+        Self { ids, order, len }
+    }
+
+    /// Returns the OSM id of the element at index `idx`.
+    pub fn id(&self, idx: usize) -> i64 {
+        match self.ids {
+            Some(ids) => ids[idx].value() as i64,
+            None => idx as i64 + 1,
+        }
+    }
+
+    /// Returns the indexes of the elements in the order of their ids.
+    pub fn by_id(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).map(move |pos| match self.order {
+            ElementOrder::Archive => pos,
+            ElementOrder::IdIndex(order) => order[pos].value() as usize,
+            ElementOrder::Sorted(ref order) => order[pos] as usize,
+        })
+    }
+}
+
+/// OSM ids of the elements of an archive.
+pub struct ElementIds<'a> {
+    pub nodes: TypeIds<'a>,
+    pub ways: TypeIds<'a>,
+    pub relations: TypeIds<'a>,
+}
+
+impl<'a> ElementIds<'a> {
+    /// Returns the ids of the elements of the archive, which are synthesized
+    /// if it has no `ids` sub-archive.
+    pub fn new(archive: &'a Osm) -> Self {
+        let ids = archive.ids();
+        let id_index = archive.id_index();
+        Self {
+            nodes: TypeIds::new(
+                ids.map(|ids| ids.nodes()),
+                id_index.and_then(|id_index| id_index.node_order()),
+                archive.nodes().len(),
+            ),
+            ways: TypeIds::new(
+                ids.map(|ids| ids.ways()),
+                id_index.and_then(|id_index| id_index.way_order()),
+                archive.ways().len(),
+            ),
+            relations: TypeIds::new(
+                ids.map(|ids| ids.relations()),
+                None,
+                archive.relations().len(),
+            ),
+        }
+    }
+}
+
+/// Converts elements of an archive into PBF elements.
+///
+/// The strings of the elements are added to the current block of the block
+/// writer, which is prepared for the type of the converted element.
+pub struct ElementConverter<'a> {
+    archive: &'a Osm,
+    ids: &'a ElementIds<'a>,
+    /// Factor converting coordinates of the archive into written coordinates
+    coord_factor: i64,
+    /// Number of dropped unresolved references of ways and relations
+    pub num_dropped_refs: usize,
+}
+
+impl<'a> ElementConverter<'a> {
+    pub fn new(archive: &'a Osm, ids: &'a ElementIds<'a>, coord_factor: i64) -> Self {
+        Self {
+            archive,
+            ids,
+            coord_factor,
+            num_dropped_refs: 0,
+        }
+    }
+
+    pub fn node<W: Write>(
+        &mut self,
+        idx: usize,
+        blocks: &mut BlockWriter<W>,
+    ) -> io::Result<osmpbf::Node> {
+        blocks.prepare(ElementType::Node)?;
+        let archive = self.archive;
+        let node = &archive.nodes()[idx];
+        let (keys, vals) = self.tags(node.tags(), blocks);
+        Ok(osmpbf::Node {
+            id: self.ids.nodes.id(idx),
+            keys,
+            vals,
+            info: self.info(archive.info().map(|info| &info.nodes()[idx]), blocks),
+            lat: i64::from(node.lat()) * self.coord_factor,
+            lon: i64::from(node.lon()) * self.coord_factor,
+        })
+    }
+
+    pub fn way<W: Write>(
+        &mut self,
+        idx: usize,
+        blocks: &mut BlockWriter<W>,
+    ) -> io::Result<osmpbf::Way> {
+        blocks.prepare(ElementType::Way)?;
+        let archive = self.archive;
+        let way = &archive.ways()[idx];
+        let (keys, vals) = self.tags(way.tags(), blocks);
+        let nodes_index = archive.nodes_index();
+        let refs: Vec<i64> = way
+            .refs()
+            .filter_map(|i| {
+                let node_idx = nodes_index[i as usize].value();
+                self.num_dropped_refs += node_idx.is_none() as usize;
+                Some(self.ids.nodes.id(node_idx? as usize))
+            })
+            .collect();
+        Ok(osmpbf::Way {
+            id: self.ids.ways.id(idx),
+            keys,
+            vals,
+            info: self.info(archive.info().map(|info| &info.ways()[idx]), blocks),
+            refs: delta_encode(refs),
+        })
+    }
+
+    pub fn relation<W: Write>(
+        &mut self,
+        idx: usize,
+        blocks: &mut BlockWriter<W>,
+    ) -> io::Result<osmpbf::Relation> {
+        blocks.prepare(ElementType::Relation)?;
+        let archive = self.archive;
+        let relation = &archive.relations()[idx];
+        let (keys, vals) = self.tags(relation.tags(), blocks);
+        let mut pbf_relation = osmpbf::Relation {
+            id: self.ids.relations.id(idx),
+            keys,
+            vals,
+            info: self.info(archive.info().map(|info| &info.relations()[idx]), blocks),
+            ..Default::default()
+        };
+
+        let (strings, element_ids) = (archive.stringtable(), self.ids);
+        let mut memids = Vec::new();
+        for member in archive.relation_members().at(idx) {
+            let (member_type, ids, idx, role_idx) = match member {
+                RelationMembersRef::NodeMember(m) => (
+                    osmpbf::relation::MemberType::Node,
+                    &element_ids.nodes,
+                    m.node_idx(),
+                    m.role_idx(),
+                ),
+                RelationMembersRef::WayMember(m) => (
+                    osmpbf::relation::MemberType::Way,
+                    &element_ids.ways,
+                    m.way_idx(),
+                    m.role_idx(),
+                ),
+                RelationMembersRef::RelationMember(m) => (
+                    osmpbf::relation::MemberType::Relation,
+                    &element_ids.relations,
+                    m.relation_idx(),
+                    m.role_idx(),
+                ),
+            };
+            match idx {
+                Some(idx) => {
+                    let role = strings.substring_raw(role_idx as usize);
+                    pbf_relation.types.push(member_type as i32);
+                    pbf_relation.roles_sid.push(blocks.string_id(role) as i32);
+                    memids.push(ids.id(idx as usize));
+                }
+                None => self.num_dropped_refs += 1,
+            }
+        }
+        pbf_relation.memids = delta_encode(memids);
+        Ok(pbf_relation)
+    }
+
+    /// Returns the string ids of keys and values of tags.
+    fn tags<W: Write>(
+        &self,
+        range: Range<u64>,
+        blocks: &mut BlockWriter<W>,
+    ) -> (Vec<u32>, Vec<u32>) {
+        osmflat::iter_tags(self.archive, range)
+            .map(|(key, value)| (blocks.string_id(key), blocks.string_id(value)))
+            .unzip()
+    }
+
+    /// Returns the metadata of an element.
+    fn info<W: Write>(
+        &self,
+        info: Option<&osmflat::Info>,
+        blocks: &mut BlockWriter<W>,
+    ) -> Option<osmpbf::Info> {
+        let info = info?;
+        let strings = self.archive.stringtable();
+        Some(osmpbf::Info {
+            version: Some(info.version()),
+            timestamp: Some(info.timestamp()),
+            changeset: Some(info.changeset()),
+            uid: Some(info.uid()),
+            user_sid: info
+                .user_idx()
+                .map(|idx| blocks.string_id(strings.substring_raw(idx as usize))),
+            visible: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::test::TestArchive;
+
+    fn ids(values: &[u64]) -> Vec<Id> {
+        values
+            .iter()
+            .map(|&value| {
+                let mut id = Id::new();
+                id.set_value(value);
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_type_ids() {
+        let sorted = ids(&[3, 5, 8]);
+        let type_ids = TypeIds::new(Some(&sorted), None, 3);
+        assert_eq!(type_ids.by_id().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(type_ids.id(1), 5);
+
+        let unsorted = ids(&[8, 3, 5]);
+        let type_ids = TypeIds::new(Some(&unsorted), None, 3);
+        assert_eq!(type_ids.by_id().collect::<Vec<_>>(), vec![1, 2, 0]);
+        assert_eq!(type_ids.id(0), 8);
+
+        let synthesized = TypeIds::new(None, None, 3);
+        assert_eq!(synthesized.by_id().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(synthesized.id(0), 1);
+        assert_eq!(synthesized.id(2), 3);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let xml = r#"<osm>
+          <node id="1" lat="0.0" lon="0.0"/>
+          <node id="2" lat="0.0" lon="1.0"/>
+          <node id="9" lat="1.0" lon="1.0"><tag k="amenity" v="pub"/></node>
+          <way id="10"><nd ref="1"/><nd ref="2"/></way>
+          <way id="14"><nd ref="2"/><nd ref="9"/><tag k="highway" v="path"/></way>
+          <relation id="100"><member type="way" ref="10" role=""/></relation>
+          <relation id="101">
+            <member type="node" ref="9" role="stop"/>
+            <member type="relation" ref="100" role=""/>
+            <tag k="type" v="route"/>
+          </relation>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| compiler.with_ids(true)).unwrap();
+        let mut pbf = Vec::new();
+        PbfWriter::new(archive.path()).write(&mut pbf).unwrap();
+        let written =
+            TestArchive::compile("input.osm.pbf", &pbf, |compiler| compiler.with_ids(true))
+                .unwrap();
+
+        assert_eq!(written.nodes().len(), 3);
+        assert_eq!(written.ways().len(), 2);
+        assert_eq!(written.relations().len(), 2);
+        let values = |ids: &[Id]| ids.iter().map(|id| id.value()).collect::<Vec<_>>();
+        let ids = written.ids().unwrap();
+        assert_eq!(values(ids.nodes()), vec![1, 2, 9]);
+        assert_eq!(values(ids.ways()), vec![10, 14]);
+        assert_eq!(values(ids.relations()), vec![100, 101]);
+
+        assert_eq!(written.node(2).unwrap().tag(b"amenity"), Some(&b"pub"[..]));
+        let way = written.way(1).unwrap();
+        assert_eq!(way.tag(b"highway"), Some(&b"path"[..]));
+        let nodes: Vec<_> = way.nodes().map(|node| node.unwrap().idx()).collect();
+        assert_eq!(nodes, vec![1, 2]);
+        let relation = written.relation(1).unwrap();
+        assert_eq!(relation.tag(b"type"), Some(&b"route"[..]));
+        let roles: Vec<_> = relation.members().map(|member| member.role()).collect();
+        assert_eq!(roles, vec![&b"stop"[..], b""]);
+    }
+
+    #[test]
+    fn test_round_trip_with_untagged_block() {
+        // the nodes 8001..=16000 fill a whole block without any tags
+        let tagged = |id: u64| id <= 8000 || id == 16001;
+        let mut xml = String::from("<osm>");
+        for id in 1..=16001 {
+            if tagged(id) {
+                xml +=
+                    &format!(r#"<node id="{id}" lat="0.0" lon="0.0"><tag k="n" v="{id}"/></node>"#);
+            } else {
+                xml += &format!(r#"<node id="{id}" lat="0.0" lon="0.0"/>"#);
+            }
+        }
+        xml += "</osm>";
+        let archive = TestArchive::compile_xml(&xml, |compiler| compiler.with_ids(true)).unwrap();
+        let mut pbf = Vec::new();
+        PbfWriter::new(archive.path()).write(&mut pbf).unwrap();
+        let written =
+            TestArchive::compile("input.osm.pbf", &pbf, |compiler| compiler.with_ids(true))
+                .unwrap();
+
+        assert_eq!(written.nodes().len(), 16001);
+        for node in written.node_refs() {
+            let id = node.id().unwrap();
+            let tags: Vec<_> = node.tags().collect();
+            if tagged(id) {
+                assert_eq!(tags, vec![(&b"n"[..], id.to_string().as_bytes())]);
+            } else {
+                assert_eq!(tags, vec![], "node {}", id);
+            }
+        }
+    }
+}
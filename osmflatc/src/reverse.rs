@@ -0,0 +1,547 @@
+//! Builders of reverse indexes from elements to the elements referencing them.
+//!
+//! The indexes are built by a counting sort in two passes over the
+//! references: the first pass counts the references of each element, the
+//! second one inserts them into the ranges of the elements. The ranges and
+//! the inserted values are stored in temporary files next to the output
+//! archive, which are mapped into memory, so that the indexes do not need to
+//! fit into memory.
+
+use crate::Error;
+
+use log::info;
+use memmap2::MmapMut;
+use osmflat::RelationMembersRef;
+
+use std::fs::OpenOptions;
+use std::io;
+use std::ops::{Deref, DerefMut, Range};
+use std::path::{Path, PathBuf};
+
+/// Vector of a fixed length stored in a temporary file mapped into memory.
+///
+/// The file is removed when the vector is dropped.
+struct TempVec<T> {
+    path: PathBuf,
+    mmap: MmapMut,
+    len: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T> TempVec<T> {
+    /// Creates a vector of `len` zeroed values in a temporary file at `path`.
+    ///
+    /// Safety: `T` has to be valid for any bytes, and aligned to at most a
+    /// page, e.g. an integer or a flatdata struct.
+    unsafe fn new(path: PathBuf, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        file.set_len((len * std::mem::size_of::<T>()) as u64)?;
+        let mmap = MmapMut::map_mut(&file)?;
+        Ok(Self {
+            path,
+            mmap,
+            len,
+            _marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<T> Deref for TempVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // Safety: the mapping is page aligned and holds `len` values (cf. `new`)
+        unsafe { std::slice::from_raw_parts(self.mmap.as_ptr() as *const T, self.len) }
+    }
+}
+
+impl<T> DerefMut for TempVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // Safety: the mapping is page aligned and holds `len` values (cf. `new`)
+        unsafe { std::slice::from_raw_parts_mut(self.mmap.as_mut_ptr() as *mut T, self.len) }
+    }
+}
+
+impl<T> Drop for TempVec<T> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Index from elements to the values referencing them.
+struct ReverseIndex<T> {
+    path: PathBuf,
+    // Counts, then starts of the ranges, shifted by one element, such that
+    // inserting moves the start of an element to the start of the next one.
+    first_idx: TempVec<u64>,
+    values: Option<TempVec<T>>,
+}
+
+impl<T: flatdata::Struct> ReverseIndex<T> {
+    /// Creates an index of `len` elements, which is stored in temporary files
+    /// starting with `path`.
+    fn new(len: usize, path: &Path) -> io::Result<Self> {
+        assert_eq!(std::mem::size_of::<T>(), T::SIZE_IN_BYTES);
+        let path = path.to_owned();
+        // Safety: `u64` is valid for any bytes
+        let first_idx = unsafe { TempVec::new(with_suffix(&path, ".ranges"), len + 2)? };
+        Ok(Self {
+            path,
+            first_idx,
+            values: None,
+        })
+    }
+
+    /// Counts a reference of the element `idx` (first pass).
+    fn count(&mut self, idx: u64) {
+        self.first_idx[idx as usize + 2] += 1;
+    }
+
+    /// Finishes counting and accumulates the counts to the starts of ranges.
+    fn start_inserting(&mut self) -> io::Result<()> {
+        let mut sum = 0;
+        for idx in self.first_idx.iter_mut() {
+            sum += *idx;
+            *idx = sum;
+        }
+        // Safety: flatdata structs are byte arrays
+        self.values =
+            Some(unsafe { TempVec::new(with_suffix(&self.path, ".values"), sum as usize)? });
+        Ok(())
+    }
+
+    /// Inserts a reference of the element `idx` (second pass).
+    fn insert(&mut self, idx: u64) -> &mut T {
+        let next_idx = &mut self.first_idx[idx as usize + 1];
+        let value = &mut self.values.as_mut().expect("not inserting")[*next_idx as usize];
+        *next_idx += 1;
+        value
+    }
+
+    /// Returns the starts of the ranges of all elements including the
+    /// sentinel (after inserting).
+    fn first_idx(&self) -> &[u64] {
+        &self.first_idx[..self.first_idx.len() - 1]
+    }
+
+    /// Returns the inserted values (after inserting).
+    fn values(&self) -> &[T] {
+        self.values.as_deref().expect("not inserting")
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Builds the index from nodes to the ways referencing them.
+///
+/// A way referencing a node multiple times (e.g. a closed way) is stored only
+/// once for this node.
+pub fn build_node_ways(
+    archive: &osmflat::Osm,
+    builder: &osmflat::NodeWaysBuilder,
+    temp_path: &Path,
+) -> Result<(), Error> {
+    info!("Building index of ways of nodes...");
+
+    let ways = archive.ways();
+    let nodes_index = archive.nodes_index();
+
+    let mut index = ReverseIndex::<osmflat::WayIndex>::new(archive.nodes().len(), temp_path)?;
+    let mut way_nodes = Vec::new();
+    for &inserting in &[false, true] {
+        for (way_idx, way) in ways.iter().enumerate() {
+            way_nodes.clear();
+            way_nodes.extend(
+                way.refs()
+                    .filter_map(|idx| nodes_index[idx as usize].value()),
+            );
+            way_nodes.sort_unstable();
+            way_nodes.dedup();
+            for &node_idx in &way_nodes {
+                if inserting {
+                    index.insert(node_idx).set_value(way_idx as u64);
+                } else {
+                    index.count(node_idx);
+                }
+            }
+        }
+        if !inserting {
+            index.start_inserting()?;
+        }
+    }
+
+    let mut nodes = builder.start_nodes()?;
+    for &idx in index.first_idx() {
+        nodes.grow()?.set_way_first_idx(idx);
+    }
+    nodes.close()?;
+    builder.set_ways_index(index.values())?;
+
+    info!("Index of ways of nodes built.");
+    Ok(())
+}
+
+/// Builds the index from nodes, ways and relations to the relations having
+/// them as members.
+pub fn build_parent_relations(
+    archive: &osmflat::Osm,
+    builder: &osmflat::ParentRelationsBuilder,
+    temp_path: &Path,
+) -> Result<(), Error> {
+    info!("Building index of parent relations...");
+
+    let new_index = |len, suffix| ReverseIndex::new(len, &with_suffix(temp_path, suffix));
+    let mut nodes: ReverseIndex<osmflat::ParentRelation> =
+        new_index(archive.nodes().len(), ".nodes")?;
+    let mut ways = new_index(archive.ways().len(), ".ways")?;
+    let mut relations = new_index(archive.relations().len(), ".relations")?;
+
+    let relation_members = archive.relation_members();
+    for &inserting in &[false, true] {
+        for relation_idx in 0..relation_members.len() {
+            for member in relation_members.at(relation_idx) {
+                let (index, idx, role_idx) = match member {
+                    RelationMembersRef::NodeMember(m) => (&mut nodes, m.node_idx(), m.role_idx()),
+                    RelationMembersRef::WayMember(m) => (&mut ways, m.way_idx(), m.role_idx()),
+                    RelationMembersRef::RelationMember(m) => {
+                        (&mut relations, m.relation_idx(), m.role_idx())
+                    }
+                };
+                let idx = match idx {
+                    Some(idx) => idx,
+                    None => continue, // unresolved member
+                };
+                if inserting {
+                    let parent = index.insert(idx);
+                    parent.set_relation_idx(relation_idx as u64);
+                    parent.set_role_idx(role_idx);
+                } else {
+                    index.count(idx);
+                }
+            }
+        }
+        if !inserting {
+            nodes.start_inserting()?;
+            ways.start_inserting()?;
+            relations.start_inserting()?;
+        }
+    }
+
+    write_parent_ranges(nodes.first_idx(), builder.start_nodes()?)?;
+    builder.set_node_parents(nodes.values())?;
+    write_parent_ranges(ways.first_idx(), builder.start_ways()?)?;
+    builder.set_way_parents(ways.values())?;
+    write_parent_ranges(relations.first_idx(), builder.start_relations()?)?;
+    builder.set_relation_parents(relations.values())?;
+
+    info!("Index of parent relations built.");
+    Ok(())
+}
+
+/// Builds the inverted index from tags to the elements having them.
+///
+/// Tags with the same key and value are stored multiple times in the archive
+/// (e.g. by spilling strings or reordering). Elements are indexed under the
+/// first of these tags, which is the only one of them in the sorted tags.
+pub fn build_tag_elements(
+    archive: &osmflat::Osm,
+    builder: &osmflat::TagElementsBuilder,
+    temp_path: &Path,
+) -> Result<(), Error> {
+    info!("Building index of elements of tags...");
+
+    let nodes = archive.nodes();
+    let ways = archive.ways();
+    let relations = archive.relations();
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+
+    let strings = archive.stringtable();
+    let tag = |idx: u64| {
+        let tag = &tags[idx as usize];
+        (
+            strings.substring_raw(tag.key_idx() as usize),
+            strings.substring_raw(tag.value_idx() as usize),
+        )
+    };
+    let mut sorted_tags: Vec<u64> = (0..tags.len() as u64).collect();
+    sorted_tags.sort_by(|&a, &b| tag(a).cmp(&tag(b)).then(a.cmp(&b)));
+    // maps each tag to the first tag with the same key and value
+    let mut canonical_tags: Vec<u64> = (0..tags.len() as u64).collect();
+    sorted_tags.dedup_by(|&mut idx, &mut first_idx| {
+        let duplicate = tag(idx) == tag(first_idx);
+        if duplicate {
+            canonical_tags[idx as usize] = first_idx;
+        }
+        duplicate
+    });
+
+    let new_index = |suffix| ReverseIndex::new(tags.len(), &with_suffix(temp_path, suffix));
+    let mut node_index: ReverseIndex<osmflat::ElementIndex> = new_index(".nodes")?;
+    let mut way_index = new_index(".ways")?;
+    let mut relation_index = new_index(".relations")?;
+    let mut element_tags = Vec::new();
+    for &inserting in &[false, true] {
+        let mut add = |index: &mut ReverseIndex<_>, element_idx: usize, range: Range<u64>| {
+            element_tags.clear();
+            element_tags
+                .extend(range.map(|idx| canonical_tags[tags_index[idx as usize].value() as usize]));
+            element_tags.sort_unstable();
+            element_tags.dedup();
+            for &tag_idx in &element_tags {
+                if inserting {
+                    let element: &mut osmflat::ElementIndex = index.insert(tag_idx);
+                    element.set_value(element_idx as u64);
+                } else {
+                    index.count(tag_idx);
+                }
+            }
+        };
+        for (idx, node) in nodes.iter().enumerate() {
+            add(&mut node_index, idx, node.tags());
+        }
+        for (idx, way) in ways.iter().enumerate() {
+            add(&mut way_index, idx, way.tags());
+        }
+        for (idx, relation) in relations.iter().enumerate() {
+            add(&mut relation_index, idx, relation.tags());
+        }
+        if !inserting {
+            node_index.start_inserting()?;
+            way_index.start_inserting()?;
+            relation_index.start_inserting()?;
+        }
+    }
+
+    let mut ranges = builder.start_tags()?;
+    for idx in 0..=tags.len() {
+        let range = ranges.grow()?;
+        range.set_node_first_idx(node_index.first_idx()[idx]);
+        range.set_way_first_idx(way_index.first_idx()[idx]);
+        range.set_relation_first_idx(relation_index.first_idx()[idx]);
+    }
+    ranges.close()?;
+    builder.set_nodes(node_index.values())?;
+    builder.set_ways(way_index.values())?;
+    builder.set_relations(relation_index.values())?;
+
+    let sorted_tags: Vec<osmflat::TagIndex> = sorted_tags
+        .into_iter()
+        .map(|idx| {
+            let mut tag_index = osmflat::TagIndex::new();
+            tag_index.set_value(idx);
+            tag_index
+        })
+        .collect();
+    builder.set_sorted_tags(&sorted_tags)?;
+
+    info!("Index of elements of tags built.");
+    Ok(())
+}
+
+fn write_parent_ranges(
+    first_idx: &[u64],
+    mut ranges: flatdata::ExternalVector<osmflat::ParentRange>,
+) -> Result<(), Error> {
+    for &idx in first_idx {
+        ranges.grow()?.set_parent_first_idx(idx);
+    }
+    ranges.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::test::TestArchive;
+    use flatdata::MemoryResourceStorage;
+
+    const XML: &str = r#"<osm>
+      <node id="1" lat="0.0" lon="0.0"><tag k="amenity" v="pub"/></node>
+      <node id="2" lat="1.0" lon="1.0"/>
+      <node id="3" lat="2.0" lon="2.0"><tag k="amenity" v="pub"/></node>
+      <way id="10"><nd ref="1"/><nd ref="2"/><tag k="highway" v="path"/></way>
+      <way id="11">
+        <nd ref="3"/><nd ref="2"/><nd ref="3"/>
+        <tag k="highway" v="track"/>
+      </way>
+      <relation id="100">
+        <member type="node" ref="3" role="stop"/>
+        <member type="way" ref="11" role="outer"/>
+        <member type="relation" ref="101" role="sub"/>
+        <tag k="type" v="multipolygon"/>
+      </relation>
+      <relation id="101">
+        <member type="node" ref="3" role="platform"/>
+        <tag k="type" v="route"/>
+      </relation>
+    </osm>"#;
+
+    #[test]
+    fn test_build_node_ways() {
+        let archive =
+            TestArchive::compile_xml(XML, |compiler| compiler.with_node_ways(true)).unwrap();
+        let ways_of_node = |idx| {
+            osmflat::ways_of_node(&archive, idx)
+                .unwrap()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ways_of_node(0), vec![0]);
+        assert_eq!(ways_of_node(1), vec![0, 1]);
+        assert_eq!(ways_of_node(2), vec![1]);
+        // the temporary files of the index are removed
+        assert_eq!(archive.files(), vec!["archive.osm.flatdata", "input.osm"]);
+    }
+
+    #[test]
+    fn test_build_node_ways_without_nodes() {
+        let xml = r#"<osm><way id="10"><nd ref="1"/></way></osm>"#;
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_node_ways(true)).unwrap();
+        assert_eq!(archive.node_ways().unwrap().nodes().len(), 0);
+        assert!(archive.node_ways().unwrap().ways_index().is_empty());
+    }
+
+    fn parents<'a>(
+        archive: &'a osmflat::Osm,
+        parents: Option<impl Iterator<Item = &'a osmflat::ParentRelation>>,
+    ) -> Vec<(u64, &'a [u8])> {
+        let strings = archive.stringtable();
+        parents
+            .unwrap()
+            .map(|parent| {
+                let role = strings.substring_raw(parent.role_idx() as usize);
+                (parent.relation_idx(), role)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_parent_relations() {
+        let archive =
+            TestArchive::compile_xml(XML, |compiler| compiler.with_parent_relations(true)).unwrap();
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_node(&archive, 0)),
+            vec![]
+        );
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_node(&archive, 2)),
+            vec![(0, &b"stop"[..]), (1, b"platform")]
+        );
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_way(&archive, 0)),
+            vec![]
+        );
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_way(&archive, 1)),
+            vec![(0, &b"outer"[..])]
+        );
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_relation(&archive, 0)),
+            vec![]
+        );
+        assert_eq!(
+            parents(&archive, osmflat::parents_of_relation(&archive, 1)),
+            vec![(0, &b"sub"[..])]
+        );
+    }
+
+    #[test]
+    fn test_build_tag_elements() {
+        let archive =
+            TestArchive::compile_xml(XML, |compiler| compiler.with_tag_elements(true)).unwrap();
+        let values = |elements: &[osmflat::ElementIndex]| -> Vec<u64> {
+            elements.iter().map(|idx| idx.value()).collect()
+        };
+
+        let pubs = osmflat::find_elements_by_tag(&archive, b"amenity", b"pub").unwrap();
+        assert_eq!(values(pubs.nodes), vec![0, 2]);
+        assert!(pubs.ways.is_empty() && pubs.relations.is_empty());
+
+        let tracks = osmflat::find_elements_by_tag(&archive, b"highway", b"track").unwrap();
+        assert!(tracks.nodes.is_empty());
+        assert_eq!(values(tracks.ways), vec![1]);
+
+        let routes = osmflat::find_elements_by_tag(&archive, b"type", b"route").unwrap();
+        assert_eq!(values(routes.relations), vec![1]);
+
+        let highways: Vec<_> = osmflat::find_elements_by_key(&archive, b"highway")
+            .unwrap()
+            .map(|(value, elements)| (value, values(elements.ways)))
+            .collect();
+        assert_eq!(
+            highways,
+            vec![(&b"path"[..], vec![0]), (&b"track"[..], vec![1])]
+        );
+    }
+
+    #[test]
+    fn test_build_tag_elements_with_duplicate_tags() {
+        let storage = MemoryResourceStorage::new("/root/osm");
+        let builder = osmflat::OsmBuilder::new(storage.clone()).unwrap();
+        let mut header = osmflat::Header::new();
+        header.set_tag_policy_idx(None);
+        builder.set_header(&header).unwrap();
+        // the tags 0 and 2 are both amenity=pub, the third node has both
+        let strings = b"\0amenity\0pub\0bar\0";
+        let mut tags = Vec::new();
+        for value_idx in [9, 13, 9] {
+            let mut tag = osmflat::Tag::new();
+            tag.set_key_idx(1);
+            tag.set_value_idx(value_idx);
+            tags.push(tag);
+        }
+        let tags_index: Vec<_> = [0, 2, 1, 2, 0]
+            .iter()
+            .map(|&idx| {
+                let mut tag_index = osmflat::TagIndex::new();
+                tag_index.set_value(idx);
+                tag_index
+            })
+            .collect();
+        let mut nodes = builder.start_nodes().unwrap();
+        for tag_first_idx in [0, 1, 3, 5] {
+            nodes.grow().unwrap().set_tag_first_idx(tag_first_idx);
+        }
+        nodes.close().unwrap();
+        let mut ways = builder.start_ways().unwrap();
+        ways.grow().unwrap().set_tag_first_idx(5);
+        ways.close().unwrap();
+        let mut relations = builder.start_relations().unwrap();
+        relations.grow().unwrap().set_tag_first_idx(5);
+        relations.close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+        builder.set_nodes_index(&[]).unwrap();
+        builder.set_tags(&tags).unwrap();
+        builder.set_tags_index(&tags_index).unwrap();
+        builder.set_stringtable(strings).unwrap();
+        let archive = osmflat::Osm::open(storage.clone()).unwrap();
+        let temp_path = std::env::temp_dir().join(format!("osmflatc-tags-{}", std::process::id()));
+        build_tag_elements(&archive, &builder.tag_elements().unwrap(), &temp_path).unwrap();
+        std::mem::drop(builder);
+        let archive = osmflat::Osm::open(storage).unwrap();
+
+        let values = |elements: &[osmflat::ElementIndex]| -> Vec<u64> {
+            elements.iter().map(|idx| idx.value()).collect()
+        };
+        let pubs = osmflat::find_elements_by_tag(&archive, b"amenity", b"pub").unwrap();
+        assert_eq!(values(pubs.nodes), vec![0, 1, 2]);
+        let amenities: Vec<_> = osmflat::find_elements_by_key(&archive, b"amenity")
+            .unwrap()
+            .map(|(value, elements)| (value, values(elements.nodes)))
+            .collect();
+        assert_eq!(
+            amenities,
+            vec![(&b"bar"[..], vec![1]), (&b"pub"[..], vec![0, 1, 2])]
+        );
+    }
+}
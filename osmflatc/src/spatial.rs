@@ -0,0 +1,665 @@
+//! Builder of the spatial index sub-archive.
+//!
+//! Nodes and ways are indexed by packed Hilbert R-trees: elements are sorted
+//! by the Hilbert value of the center of their bounding box, and grouped
+//! bottom-up into inner entries of at most `NODE_SIZE` children. The entries
+//! are stored level by level starting with the root, such that the children
+//! of consecutive entries are consecutive, and can be expressed as a range.
+//!
+//! The same Hilbert order is also used to reorder the nodes and ways of an
+//! archive, such that spatially close elements are close in memory.
+
+use crate::Error;
+
+use log::info;
+use osmflat::RelationMembersRef;
+
+/// Maximum number of children of an inner entry
+const NODE_SIZE: usize = 16;
+
+/// Bounding box in coordinates scaled with `header.coord_scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BBox {
+    left: i32,
+    right: i32,
+    top: i32,
+    bottom: i32,
+}
+
+impl BBox {
+    fn from_point(lon: i32, lat: i32) -> Self {
+        Self {
+            left: lon,
+            right: lon,
+            top: lat,
+            bottom: lat,
+        }
+    }
+
+    fn extend(&mut self, other: &Self) {
+        self.left = self.left.min(other.left);
+        self.right = self.right.max(other.right);
+        self.top = self.top.max(other.top);
+        self.bottom = self.bottom.min(other.bottom);
+    }
+
+    fn center(&self) -> (i64, i64) {
+        (
+            (i64::from(self.left) + i64::from(self.right)) / 2,
+            (i64::from(self.bottom) + i64::from(self.top)) / 2,
+        )
+    }
+}
+
+/// Builds the spatial index of nodes and ways of the given archive.
+pub fn build_spatial_index(
+    archive: &osmflat::Osm,
+    builder: &osmflat::SpatialIndexBuilder,
+) -> Result<(), Error> {
+    let nodes = archive.nodes();
+    let ways = archive.ways();
+    let nodes_index = archive.nodes_index();
+
+    info!("Building spatial index of nodes...");
+    let node_items = nodes
+        .iter()
+        .enumerate()
+        .map(|(idx, node)| (BBox::from_point(node.lon(), node.lat()), idx as u64))
+        .collect();
+    pack(node_items, builder.start_nodes()?)?;
+
+    info!("Building spatial index of ways...");
+    let way_items = ways
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, way)| {
+            let bbox = way
+                .refs()
+                .filter_map(|i| nodes_index[i as usize].value())
+                .map(|i| &nodes[i as usize])
+                .map(|node| BBox::from_point(node.lon(), node.lat()))
+                .reduce(|mut bbox, other| {
+                    bbox.extend(&other);
+                    bbox
+                })?;
+            Some((bbox, idx as u64))
+        })
+        .collect();
+    pack(way_items, builder.start_ways()?)?;
+
+    info!("Spatial index built.");
+    Ok(())
+}
+
+/// Reorders the nodes and the ways (by their centroids) of the archive along
+/// the Hilbert curve.
+///
+/// The reordered nodes and ways, and all resources depending on their order,
+/// i.e. the tags and nodes indexes, the relation members, and the optional
+/// ids, infos and orders of the id index, are written to `builder`.
+pub fn reorder(archive: &osmflat::Osm, builder: &osmflat::OsmBuilder) -> Result<(), Error> {
+    let nodes = archive.nodes();
+    let ways = archive.ways();
+    let nodes_index = archive.nodes_index();
+    let tags_index = archive.tags_index();
+
+    info!("Ordering nodes and ways along the Hilbert curve...");
+    let node_bboxes: Vec<_> = nodes
+        .iter()
+        .map(|node| Some(BBox::from_point(node.lon(), node.lat())))
+        .collect();
+    let way_bboxes: Vec<_> = ways
+        .iter()
+        .map(|way| {
+            let (mut lon, mut lat, mut n) = (0, 0, 0);
+            for node in way
+                .refs()
+                .filter_map(|i| nodes_index[i as usize].value())
+                .map(|i| &nodes[i as usize])
+            {
+                lon += i64::from(node.lon());
+                lat += i64::from(node.lat());
+                n += 1;
+            }
+            if n == 0 {
+                return None;
+            }
+            Some(BBox::from_point((lon / n) as i32, (lat / n) as i32))
+        })
+        .collect();
+    let node_order = hilbert_order(&node_bboxes);
+    let way_order = hilbert_order(&way_bboxes);
+    std::mem::drop(node_bboxes);
+    std::mem::drop(way_bboxes);
+    let node_idx = inverse(&node_order);
+    let way_idx = inverse(&way_order);
+
+    info!("Writing reordered nodes and ways...");
+    let mut new_nodes = builder.start_nodes()?;
+    let mut new_tags_index = builder.start_tags_index()?;
+    let mut num_tags = 0;
+    let mut copy_tags = |tags: std::ops::Range<u64>| -> Result<u64, Error> {
+        if tags.start > tags.end || tags.end > tags_index.len() as u64 {
+            return Err(format!(
+                "invalid tag range {}..{} of {} tags",
+                tags.start,
+                tags.end,
+                tags_index.len()
+            )
+            .into());
+        }
+        let first_idx = num_tags;
+        for tag in &tags_index[tags.start as usize..tags.end as usize] {
+            new_tags_index.grow()?.fill_from(tag);
+            num_tags += 1;
+        }
+        Ok(first_idx)
+    };
+    for &idx in &node_order {
+        let node = &nodes[idx as usize];
+        let new_node = new_nodes.grow()?;
+        new_node.fill_from(node);
+        new_node.set_tag_first_idx(copy_tags(node.tags())?);
+    }
+    new_nodes.grow()?.set_tag_first_idx(copy_tags(0..0)?);
+    new_nodes.close()?;
+
+    let mut new_ways = builder.start_ways()?;
+    let mut new_nodes_index = builder.start_nodes_index()?;
+    let mut num_refs = 0;
+    for &idx in &way_order {
+        let way = &ways[idx as usize];
+        let new_way = new_ways.grow()?;
+        new_way.set_tag_first_idx(copy_tags(way.tags())?);
+        new_way.set_ref_first_idx(num_refs);
+        for i in way.refs() {
+            let node_ref = nodes_index[i as usize].value();
+            new_nodes_index
+                .grow()?
+                .set_value(node_ref.map(|idx| node_idx[idx as usize]));
+            num_refs += 1;
+        }
+    }
+    {
+        let sentinel = new_ways.grow()?;
+        sentinel.set_tag_first_idx(copy_tags(0..0)?);
+        sentinel.set_ref_first_idx(num_refs);
+    }
+    new_ways.close()?;
+    new_nodes_index.close()?;
+
+    // the tags of relations follow the tags of nodes and ways, whose number
+    // did not change, so the relations are kept as they are
+    let relation_tags = archive
+        .relations()
+        .first()
+        .map_or(tags_index.len() as u64, |relation| relation.tags().start)
+        ..tags_index.len() as u64;
+    copy_tags(relation_tags)?;
+    new_tags_index.close()?;
+
+    let relation_members = archive.relation_members();
+    let mut new_relation_members = builder.start_relation_members()?;
+    for relation_idx in 0..relation_members.len() {
+        let mut new_members = new_relation_members.grow()?;
+        for member in relation_members.at(relation_idx) {
+            match member {
+                RelationMembersRef::NodeMember(m) => {
+                    let new_member = new_members.add_node_member();
+                    new_member.fill_from(m);
+                    new_member.set_node_idx(m.node_idx().map(|idx| node_idx[idx as usize]));
+                }
+                RelationMembersRef::WayMember(m) => {
+                    let new_member = new_members.add_way_member();
+                    new_member.fill_from(m);
+                    new_member.set_way_idx(m.way_idx().map(|idx| way_idx[idx as usize]));
+                }
+                RelationMembersRef::RelationMember(m) => {
+                    new_members.add_relation_member().fill_from(m);
+                }
+            }
+        }
+    }
+    new_relation_members.close()?;
+
+    if let Some(ids) = archive.ids() {
+        let new_ids = builder.ids()?;
+        reorder_vector(ids.nodes(), &node_order, new_ids.start_nodes()?)?;
+        reorder_vector(ids.ways(), &way_order, new_ids.start_ways()?)?;
+    }
+    if let Some(infos) = archive.info() {
+        let new_infos = builder.info()?;
+        reorder_vector(infos.nodes(), &node_order, new_infos.start_nodes()?)?;
+        reorder_vector(infos.ways(), &way_order, new_infos.start_ways()?)?;
+    }
+    if archive.id_index().is_some() {
+        // the positions of the sorted ids are the previous indexes
+        let new_id_index = builder.id_index()?;
+        write_order(&node_idx, new_id_index.start_node_order()?)?;
+        write_order(&way_idx, new_id_index.start_way_order()?)?;
+    }
+
+    info!("Nodes and ways reordered.");
+    Ok(())
+}
+
+/// Returns the indexes of the elements given by their bounding boxes ordered
+/// by the Hilbert values of their centers. Elements without a bounding box
+/// are ordered last, and ties keep their order.
+fn hilbert_order(bboxes: &[Option<BBox>]) -> Vec<u64> {
+    let extent = bboxes.iter().flatten().copied().reduce(|mut extent, bbox| {
+        extent.extend(&bbox);
+        extent
+    });
+    let keys: Vec<_> = bboxes
+        .iter()
+        .map(|bbox| match (bbox, &extent) {
+            (Some(bbox), Some(extent)) => hilbert_value(extent, bbox),
+            _ => u64::MAX,
+        })
+        .collect();
+    let mut order: Vec<u64> = (0..bboxes.len() as u64).collect();
+    order.sort_by_key(|&idx| keys[idx as usize]);
+    order
+}
+
+/// Inverts a permutation.
+fn inverse(order: &[u64]) -> Vec<u64> {
+    let mut result = vec![0; order.len()];
+    for (new_idx, &idx) in order.iter().enumerate() {
+        result[idx as usize] = new_idx as u64;
+    }
+    result
+}
+
+/// Writes the elements of a vector in the given order.
+///
+/// The elements must not have ranges, which depend on the next element.
+fn reorder_vector<T>(
+    elements: &[T],
+    order: &[u64],
+    mut vector: flatdata::ExternalVector<T>,
+) -> Result<(), Error>
+where
+    T: flatdata::Struct + Clone,
+{
+    for &idx in order {
+        *vector.grow()? = elements[idx as usize].clone();
+    }
+    vector.close()?;
+    Ok(())
+}
+
+/// Writes the indexes of the elements in the order of their ids.
+fn write_order(
+    indexes: &[u64],
+    mut vector: flatdata::ExternalVector<osmflat::ElementIndex>,
+) -> Result<(), Error> {
+    for &idx in indexes {
+        vector.grow()?.set_value(idx);
+    }
+    vector.close()?;
+    Ok(())
+}
+
+/// Packs elements given by their bounding boxes and indexes into an R-tree.
+fn pack(
+    mut items: Vec<(BBox, u64)>,
+    mut entries: flatdata::ExternalVector<osmflat::RTreeEntry>,
+) -> Result<(), Error> {
+    if let Some(extent) = items
+        .iter()
+        .map(|(bbox, _)| *bbox)
+        .reduce(|mut extent, bbox| {
+            extent.extend(&bbox);
+            extent
+        })
+    {
+        items.sort_by_cached_key(|(bbox, _)| hilbert_value(&extent, bbox));
+    }
+
+    // levels of inner entries, bottom-up
+    let mut levels: Vec<Vec<BBox>> = Vec::new();
+    let mut level_len = items.len();
+    while level_len > 1 {
+        let level: Vec<BBox> = match levels.last() {
+            None => group(items.iter().map(|(bbox, _)| bbox)),
+            Some(level) => group(level.iter()),
+        };
+        level_len = level.len();
+        levels.push(level);
+    }
+
+    let num_entries = levels.iter().map(Vec::len).sum::<usize>() + items.len();
+    let mut level_start = 0;
+    for (i, level) in levels.iter().enumerate().rev() {
+        let children_start = level_start + level.len();
+        for (j, bbox) in level.iter().enumerate() {
+            let entry = entries.grow()?;
+            set_bbox(entry, bbox);
+            entry.set_child_first_idx((children_start + j * NODE_SIZE) as u64);
+            entry.set_element_idx(None);
+        }
+        level_start = children_start;
+        debug_assert!(i > 0 || level_start + items.len() == num_entries);
+    }
+    for (bbox, idx) in &items {
+        let entry = entries.grow()?;
+        set_bbox(entry, bbox);
+        entry.set_child_first_idx(num_entries as u64);
+        entry.set_element_idx(Some(*idx));
+    }
+
+    // fill child_first_idx of the sentry, since it contains the end of the
+    // children range of the last entry
+    let sentinel = entries.grow()?;
+    sentinel.set_child_first_idx(num_entries as u64);
+    sentinel.set_element_idx(None);
+    entries.close()?;
+    Ok(())
+}
+
+/// Groups consecutive bounding boxes into their union bounding boxes.
+fn group<'a>(bboxes: impl Iterator<Item = &'a BBox>) -> Vec<BBox> {
+    let mut result: Vec<BBox> = Vec::new();
+    for (i, bbox) in bboxes.enumerate() {
+        if i % NODE_SIZE == 0 {
+            result.push(*bbox);
+        } else {
+            result.last_mut().unwrap().extend(bbox);
+        }
+    }
+    result
+}
+
+fn set_bbox(entry: &mut osmflat::RTreeEntry, bbox: &BBox) {
+    entry.set_bbox_left(bbox.left);
+    entry.set_bbox_right(bbox.right);
+    entry.set_bbox_top(bbox.top);
+    entry.set_bbox_bottom(bbox.bottom);
+}
+
+/// Computes the Hilbert value of the center of `bbox` on a 2^16 x 2^16 grid
+/// covering `extent`.
+fn hilbert_value(extent: &BBox, bbox: &BBox) -> u64 {
+    const N: i64 = 1 << 16;
+    let (x, y) = bbox.center();
+    let scale = |v: i64, min: i32, max: i32| {
+        let width = (i64::from(max) - i64::from(min)).max(1);
+        ((v - i64::from(min)) * (N - 1) / width) as u32
+    };
+    hilbert(
+        scale(x, extent.left, extent.right),
+        scale(y, extent.bottom, extent.top),
+    )
+}
+
+/// Converts `(x, y)` on a 2^16 x 2^16 grid to its distance on the Hilbert curve.
+///
+/// See <https://en.wikipedia.org/wiki/Hilbert_curve>.
+fn hilbert(mut x: u32, mut y: u32) -> u64 {
+    const N: u32 = 1 << 16;
+    let mut d = 0;
+    let mut s = N / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = N - 1 - x;
+                y = N - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::test::TestArchive;
+    use flatdata::MemoryResourceStorage;
+    use osmflat::{BoundingBox, RTreeQuery, SpatialIndex, SpatialIndexBuilder};
+
+    #[test]
+    fn test_hilbert() {
+        assert_eq!(hilbert(0, 0), 0);
+        assert_eq!(hilbert(1, 0), 1);
+        assert_eq!(hilbert(1, 1), 2);
+        assert_eq!(hilbert(0, 1), 3);
+        assert_eq!(hilbert((1 << 16) - 1, 0), (1 << 32) - 1);
+    }
+
+    #[test]
+    fn test_hilbert_order() {
+        let bboxes = [
+            Some(BBox::from_point(0, 1)),
+            None,
+            Some(BBox::from_point(1, 1)),
+            Some(BBox::from_point(0, 0)),
+            Some(BBox::from_point(1, 0)),
+            Some(BBox::from_point(0, 0)),
+        ];
+        let order = hilbert_order(&bboxes);
+        assert_eq!(order, vec![3, 5, 0, 2, 4, 1]);
+        assert_eq!(inverse(&order), vec![2, 5, 3, 0, 4, 1]);
+    }
+
+    #[test]
+    fn test_pack_and_query() {
+        let storage = MemoryResourceStorage::new("/root/spatial_index");
+        let builder = SpatialIndexBuilder::new(storage.clone()).unwrap();
+        // grid of 100 x 100 points
+        let items: Vec<_> = (0..10_000)
+            .map(|i| (BBox::from_point(i % 100, i / 100), i as u64))
+            .collect();
+        pack(items, builder.start_nodes().unwrap()).unwrap();
+        pack(Vec::new(), builder.start_ways().unwrap()).unwrap();
+
+        let index = SpatialIndex::open(storage).unwrap();
+        let mut result: Vec<_> = RTreeQuery::new(index.nodes(), 10, 12, 51, 50).collect();
+        result.sort_unstable();
+        assert_eq!(result, vec![5010, 5011, 5012, 5110, 5111, 5112]);
+        assert_eq!(RTreeQuery::new(index.nodes(), 200, 300, 10, 0).count(), 0);
+        assert_eq!(RTreeQuery::new(index.nodes(), 0, 99, 99, 0).count(), 10_000);
+        assert_eq!(RTreeQuery::new(index.ways(), 0, 99, 99, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_pack_single_item() {
+        let storage = MemoryResourceStorage::new("/root/spatial_index");
+        let builder = SpatialIndexBuilder::new(storage.clone()).unwrap();
+        let items = vec![(BBox::from_point(5, 5), 7)];
+        pack(items, builder.start_nodes().unwrap()).unwrap();
+        pack(Vec::new(), builder.start_ways().unwrap()).unwrap();
+
+        let index = SpatialIndex::open(storage).unwrap();
+        let result: Vec<_> = RTreeQuery::new(index.nodes(), 0, 10, 10, 0).collect();
+        assert_eq!(result, vec![7]);
+        assert_eq!(RTreeQuery::new(index.nodes(), 6, 10, 10, 6).count(), 0);
+    }
+
+    const XML: &str = r#"<osm>
+      <node id="1" lat="0.0" lon="0.0"/>
+      <node id="2" lat="1.0" lon="1.0"/>
+      <node id="3" lat="2.0" lon="2.0"/>
+      <way id="10"><nd ref="1"/><nd ref="2"/></way>
+      <way id="11"><nd ref="2"/><nd ref="3"/></way>
+    </osm>"#;
+
+    fn bbox(left: f64, bottom: f64, right: f64, top: f64) -> BoundingBox {
+        BoundingBox {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    fn sorted(query: Option<RTreeQuery>) -> Vec<usize> {
+        let mut result: Vec<_> = query.unwrap().collect();
+        result.sort_unstable();
+        result
+    }
+
+    #[test]
+    fn test_build_spatial_index() {
+        let archive =
+            TestArchive::compile_xml(XML, |compiler| compiler.with_spatial_index(true)).unwrap();
+        let last = bbox(1.5, 1.5, 2.5, 2.5);
+        let all = bbox(-1., -1., 3., 3.);
+        assert_eq!(sorted(osmflat::nodes_in_bbox(&archive, &last)), vec![2]);
+        assert_eq!(
+            sorted(osmflat::nodes_in_bbox(&archive, &all)),
+            vec![0, 1, 2]
+        );
+        assert_eq!(sorted(osmflat::ways_in_bbox(&archive, &last)), vec![1]);
+        assert_eq!(sorted(osmflat::ways_in_bbox(&archive, &all)), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_build_spatial_index_without_nodes() {
+        let xml = r#"<osm><way id="1"/></osm>"#;
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_spatial_index(true)).unwrap();
+        let all = bbox(-180., -90., 180., 90.);
+        assert!(sorted(osmflat::nodes_in_bbox(&archive, &all)).is_empty());
+        assert!(sorted(osmflat::ways_in_bbox(&archive, &all)).is_empty());
+    }
+
+    fn tag<'a>(archive: &'a osmflat::Osm, range: std::ops::Range<u64>, key: &str) -> &'a [u8] {
+        osmflat::find_tag(archive, range, key.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_reorder() {
+        let xml = r#"<osm>
+          <node id="1" lat="2.0" lon="2.0"><tag k="name" v="first"/></node>
+          <node id="2" lat="0.0" lon="0.0"/>
+          <node id="3" lat="1.0" lon="1.0"><tag k="name" v="last"/></node>
+          <way id="10"><nd ref="3"/><nd ref="1"/><tag k="highway" v="path"/></way>
+          <way id="11"><nd ref="2"/><tag k="highway" v="track"/></way>
+          <relation id="100">
+            <member type="node" ref="3" role=""/>
+            <member type="way" ref="11" role=""/>
+            <tag k="type" v="route"/>
+          </relation>
+        </osm>"#;
+        let archive = TestArchive::compile_xml(xml, |compiler| {
+            compiler
+                .with_spatial_order(true)
+                .with_ids(true)
+                .with_id_index(true)
+        })
+        .unwrap();
+        assert_eq!(archive.nodes().len(), 3);
+        assert_eq!(archive.ways().len(), 2);
+
+        let ids = archive.ids().unwrap();
+        let scale = archive.header().coord_scale();
+        let node_idx = |id| archive.node_index_by_id(id).unwrap();
+        for (id, coord) in [(1, 2), (2, 0), (3, 1)] {
+            let node = &archive.nodes()[node_idx(id)];
+            assert_eq!(ids.nodes()[node_idx(id)].value(), id);
+            assert_eq!(node.lat(), coord * scale);
+            assert_eq!(node.lon(), coord * scale);
+        }
+        assert_eq!(
+            tag(&archive, archive.nodes()[node_idx(1)].tags(), "name"),
+            b"first"
+        );
+        assert_eq!(
+            tag(&archive, archive.nodes()[node_idx(3)].tags(), "name"),
+            b"last"
+        );
+        assert!(archive.nodes()[node_idx(2)].tags().is_empty());
+
+        let nodes_index = archive.nodes_index();
+        let way_idx = |id| archive.way_index_by_id(id).unwrap();
+        for (id, refs, highway) in [(10, vec![3, 1], "path"), (11, vec![2], "track")] {
+            let way = &archive.ways()[way_idx(id)];
+            assert_eq!(ids.ways()[way_idx(id)].value(), id);
+            let way_refs: Vec<_> = way
+                .refs()
+                .map(|idx| nodes_index[idx as usize].value().unwrap() as usize)
+                .collect();
+            let expected: Vec<_> = refs.into_iter().map(node_idx).collect();
+            assert_eq!(way_refs, expected);
+            assert_eq!(tag(&archive, way.tags(), "highway"), highway.as_bytes());
+        }
+
+        let relation = &archive.relations()[0];
+        assert_eq!(tag(&archive, relation.tags(), "type"), b"route");
+        let members: Vec<_> = archive
+            .relation_members()
+            .at(0)
+            .map(|member| match member {
+                RelationMembersRef::NodeMember(m) => m.node_idx(),
+                RelationMembersRef::WayMember(m) => m.way_idx(),
+                RelationMembersRef::RelationMember(m) => m.relation_idx(),
+            })
+            .collect();
+        assert_eq!(
+            members,
+            vec![Some(node_idx(3) as u64), Some(way_idx(11) as u64)]
+        );
+    }
+
+    #[test]
+    fn test_reorder_with_invalid_tag_ranges() {
+        let archive_storage = MemoryResourceStorage::new("/root/osm");
+        let archive_builder = osmflat::OsmBuilder::new(archive_storage.clone()).unwrap();
+        let mut header = osmflat::Header::new();
+        header.set_tag_policy_idx(None);
+        archive_builder.set_header(&header).unwrap();
+        let mut nodes = archive_builder.start_nodes().unwrap();
+        // the tags of the second node end before they start
+        for tag_first_idx in [2, 0, 2] {
+            nodes.grow().unwrap().set_tag_first_idx(tag_first_idx);
+        }
+        nodes.close().unwrap();
+        let mut ways = archive_builder.start_ways().unwrap();
+        ways.grow().unwrap().set_tag_first_idx(2);
+        ways.close().unwrap();
+        let mut relations = archive_builder.start_relations().unwrap();
+        relations.grow().unwrap().set_tag_first_idx(2);
+        relations.close().unwrap();
+        archive_builder
+            .start_relation_members()
+            .unwrap()
+            .close()
+            .unwrap();
+        archive_builder.set_nodes_index(&[]).unwrap();
+        archive_builder.set_tags(&[]).unwrap();
+        archive_builder
+            .set_tags_index(&[osmflat::TagIndex::new(), osmflat::TagIndex::new()])
+            .unwrap();
+        archive_builder.set_stringtable(b"\0").unwrap();
+        std::mem::drop(archive_builder);
+        let archive = osmflat::Osm::open(archive_storage).unwrap();
+
+        let builder = osmflat::OsmBuilder::new(MemoryResourceStorage::new("/root/osm")).unwrap();
+        let error = reorder(&archive, &builder).unwrap_err();
+        assert_eq!(error.to_string(), "invalid tag range 2..0 of 2 tags");
+    }
+
+    #[test]
+    fn test_reorder_without_nodes() {
+        let xml = r#"<osm>
+          <way id="10"><tag k="highway" v="path"/></way>
+          <relation id="100"><tag k="type" v="route"/></relation>
+        </osm>"#;
+        let archive =
+            TestArchive::compile_xml(xml, |compiler| compiler.with_spatial_order(true)).unwrap();
+        assert_eq!(archive.nodes().len(), 0);
+        assert_eq!(archive.ways().len(), 1);
+        assert_eq!(tag(&archive, archive.ways()[0].tags(), "highway"), b"path");
+        assert_eq!(
+            tag(&archive, archive.relations()[0].tags(), "type"),
+            b"route"
+        );
+    }
+}
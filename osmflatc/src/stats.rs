@@ -1,6 +1,7 @@
 use std::fmt;
 use std::ops::AddAssign;
 
+/// Statistics of a compilation.
 #[derive(Debug, Default)]
 pub struct Stats {
     pub num_nodes: usize,
@@ -9,6 +10,8 @@ pub struct Stats {
     pub num_unresolved_node_ids: usize,
     pub num_unresolved_way_ids: usize,
     pub num_unresolved_rel_ids: usize,
+    /// Number of corrupt blocks skipped in lenient mode
+    pub num_skipped_blocks: usize,
 }
 
 impl AddAssign for Stats {
@@ -20,6 +23,7 @@ impl AddAssign for Stats {
         self.num_unresolved_node_ids += other.num_unresolved_node_ids;
         self.num_unresolved_way_ids += other.num_unresolved_way_ids;
         self.num_unresolved_rel_ids += other.num_unresolved_rel_ids;
+        self.num_skipped_blocks += other.num_skipped_blocks;
     }
 }
 
@@ -34,13 +38,15 @@ impl fmt::Display for Stats {
 Unresolved ids:
   nodes:        {}
   ways:         {}
-  relations:    {}"#,
+  relations:    {}
+Skipped corrupt blocks: {}"#,
             self.num_nodes,
             self.num_ways,
             self.num_relations,
             self.num_unresolved_node_ids,
             self.num_unresolved_way_ids,
-            self.num_unresolved_rel_ids
+            self.num_unresolved_rel_ids,
+            self.num_skipped_blocks
         )
     }
 }
@@ -1,4 +1,28 @@
+use crate::Error;
+
 use ahash::AHashMap;
+use log::info;
+use memmap2::Mmap;
+use osmflat::RelationMembersRef;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::PathBuf;
+
+/// Size of the buffers storing the strings in memory
+const BUFFER_SIZE: usize = 1024 * 1024 * 4;
+
+/// Minimum memory limit of a string table spilling to a file, such that some
+/// buffers fit next to the strings kept in memory across spills
+pub const MIN_MAX_MEMORY: usize = 4 * BUFFER_SIZE;
+
+/// Size of an entry of the index of strings, including its control byte
+const INDEX_ENTRY_SIZE: usize = std::mem::size_of::<(TerminatedStringPtr, u64)>() + 1;
+
+/// Flag of indexes in the index of strings, set when a string is inserted
+/// again since the last spill
+const REUSED: u64 = 1 << 63;
 
 #[derive(Debug, Clone, Copy)]
 struct TerminatedStringPtr {
@@ -48,6 +72,14 @@ impl std::borrow::Borrow<[u8]> for TerminatedStringPtr {
     }
 }
 
+/// File to which the strings are spilled when they exceed the memory limit.
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    file: Option<io::BufWriter<File>>,
+    max_memory: usize,
+}
+
 #[derive(Debug, Default)]
 pub struct StringTable {
     // Append only, we will never reallocate any data inside
@@ -59,6 +91,31 @@ pub struct StringTable {
     indexed_data: AHashMap<TerminatedStringPtr, u64>,
 
     size_in_bytes: u64,
+
+    // Sum of the capacities of the buffers in data
+    data_capacity: usize,
+
+    // Copies of the spilled strings, which are still indexed; never reallocated
+    cache: Vec<u8>,
+
+    spill: Option<SpillFile>,
+}
+
+/// Data of a finished string table.
+pub enum StringData {
+    Memory(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for StringData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            StringData::Memory(data) => data,
+            StringData::Mapped(data) => data,
+        }
+    }
 }
 
 impl StringTable {
@@ -66,15 +123,35 @@ impl StringTable {
         Default::default()
     }
 
+    /// Creates a string table, which spills its strings to the file at `path`
+    /// when the strings and their index in memory exceed `max_memory` bytes.
+    /// Limits below `MIN_MAX_MEMORY` are raised to it.
+    ///
+    /// When spilling, the strings inserted again since the previous spill
+    /// stay indexed, using at most a quarter of `max_memory`. All other
+    /// strings inserted before are not deduplicated anymore. The file is
+    /// created on first spill.
+    pub fn with_spill_file(path: PathBuf, max_memory: usize) -> Self {
+        Self {
+            spill: Some(SpillFile {
+                path,
+                file: None,
+                max_memory: max_memory.max(MIN_MAX_MEMORY),
+            }),
+            ..Default::default()
+        }
+    }
+
     /// Inserts a string into string table and returns its index.
     ///
     /// If the string was already inserted before, the string is deduplicated
     /// and the index to the previous string is returned.
-    pub fn insert(&mut self, s: &str) -> u64 {
+    pub fn insert(&mut self, s: &str) -> io::Result<u64> {
         // Horrible news, we cannot use entry API since it does not support Borrow
         // See: https://github.com/rust-lang/rust/issues/56167
-        if let Some(&idx) = self.indexed_data.get(s.as_bytes()) {
-            return idx;
+        if let Some(idx) = self.indexed_data.get_mut(s.as_bytes()) {
+            *idx |= REUSED;
+            return Ok(*idx & !REUSED);
         }
 
         let idx = self.size_in_bytes;
@@ -84,8 +161,9 @@ impl StringTable {
             .filter(|x| x.len() + s.len() < x.capacity()) // str-len + \0
             .is_none()
         {
-            self.data
-                .push(Vec::with_capacity((1024 * 1024 * 4).max(s.len() + 1)));
+            let buffer = Vec::with_capacity(BUFFER_SIZE.max(s.len() + 1));
+            self.data_capacity += buffer.capacity();
+            self.data.push(buffer);
         }
         // unwrap is ok here, since we just ensured that there is always one entry
         let buffer = self.data.last_mut().unwrap();
@@ -105,19 +183,107 @@ impl StringTable {
         self.indexed_data.insert(key, idx);
 
         self.size_in_bytes += s.len() as u64 + 1;
-        idx
+
+        if let Some(spill) = &self.spill {
+            if self.memory_usage() > spill.max_memory {
+                self.spill()?;
+            }
+        }
+        Ok(idx)
+    }
+
+    /// Returns the approximate number of bytes used by the strings and their
+    /// index in memory.
+    fn memory_usage(&self) -> usize {
+        self.data_capacity + self.cache.capacity() + self.indexed_data.capacity() * INDEX_ENTRY_SIZE
+    }
+
+    /// Writes the strings in memory to the spill file and frees them.
+    ///
+    /// The strings inserted again since the previous spill are copied to the
+    /// cache and stay indexed, ordered by their indexes until the cache is
+    /// full.
+    fn spill(&mut self) -> io::Result<()> {
+        let spill = self.spill.as_mut().expect("no spill file");
+        if spill.file.is_none() {
+            // the file is mapped for reading when finished
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&spill.path)?;
+            spill.file = Some(io::BufWriter::new(file));
+        }
+        let file = spill.file.as_mut().unwrap();
+
+        let mut reused: Vec<_> = self
+            .indexed_data
+            .iter()
+            .filter(|(_, &idx)| idx & REUSED != 0)
+            .map(|(&s, &idx)| (idx & !REUSED, s))
+            .collect();
+        reused.sort_unstable_by_key(|&(idx, _)| idx);
+        // an index entry is counted twice for the spare capacity of the index
+        let max_cache_size = spill.max_memory / 4;
+        let mut cache_size = 0;
+        let num_cached = reused
+            .iter()
+            .take_while(|(_, s)| {
+                cache_size += s.as_bytes().len() + 1 + 2 * INDEX_ENTRY_SIZE;
+                cache_size <= max_cache_size
+            })
+            .count();
+        reused.truncate(num_cached);
+
+        let mut cache =
+            Vec::with_capacity(reused.iter().map(|(_, s)| s.as_bytes().len() + 1).sum());
+        let mut indexed_data = AHashMap::with_capacity(reused.len());
+        for (idx, s) in reused {
+            let pos = cache.len();
+            cache.extend(s.as_bytes());
+            cache.push(0);
+            // Safety: the cache is never reallocated
+            let key = unsafe { TerminatedStringPtr::from_ptr(cache[pos..].as_ptr()) };
+            indexed_data.insert(key, idx);
+        }
+
+        // drop the index before the strings it references
+        self.indexed_data = indexed_data;
+        for buffer in self.data.drain(..) {
+            file.write_all(&buffer)?;
+        }
+        self.data_capacity = 0;
+        self.cache = cache;
+        Ok(())
+    }
+
+    /// Finishes the string table and returns its data, which is mapped from
+    /// the spill file if any strings were spilled.
+    pub fn finish(mut self) -> io::Result<StringData> {
+        if !matches!(self.spill, Some(SpillFile { file: Some(_), .. })) {
+            return Ok(StringData::Memory(self.into_bytes()));
+        }
+        let mut file = self.spill.take().unwrap().file.unwrap();
+        // drop the index before the strings it references
+        self.indexed_data = AHashMap::new();
+        for buffer in self.data.drain(..) {
+            file.write_all(&buffer)?;
+        }
+        let file = file.into_inner().map_err(io::IntoInnerError::into_error)?;
+        Ok(StringData::Mapped(unsafe { Mmap::map(&file)? }))
     }
 
-    pub fn into_bytes(self) -> Vec<u8> {
+    fn into_bytes(self) -> Vec<u8> {
         let Self {
             data,
             indexed_data,
             size_in_bytes,
+            ..
         } = self;
         std::mem::drop(indexed_data);
 
-        let mut result = Vec::new();
-        result.reserve(size_in_bytes as usize);
+        let mut result = Vec::with_capacity(size_in_bytes as usize);
         for buffer in data {
             result.extend(buffer); // also drops buffer
         }
@@ -125,21 +291,179 @@ impl StringTable {
     }
 }
 
+/// Rewrites the stringtable of the archive with its strings ordered by their
+/// number of uses, so that the most used strings are in the first pages of
+/// the stringtable.
+///
+/// The header, the tags, the relation members and the optional infos, i.e.
+/// all resources referencing strings, are written with the new indexes of
+/// their strings to `builder`.
+pub fn sort_by_frequency(
+    archive: &osmflat::Osm,
+    builder: &osmflat::OsmBuilder,
+) -> Result<(), Error> {
+    info!("Sorting stringtable by frequency of strings...");
+
+    let header = archive.header();
+    let tags = archive.tags();
+    let relation_members = archive.relation_members();
+    let infos = archive.info();
+
+    let mut tag_uses = vec![0u64; tags.len()];
+    for tag_idx in archive.tags_index() {
+        tag_uses[tag_idx.value() as usize] += 1;
+    }
+
+    let mut uses = AHashMap::new();
+    let mut count = |idx: u64, n: u64| *uses.entry(idx).or_insert(0) += n;
+    count(header.writingprogram_idx(), 1);
+    count(header.source_idx(), 1);
+    count(header.replication_base_url_idx(), 1);
+    if let Some(idx) = header.tag_policy_idx() {
+        count(idx, 1);
+    }
+    for (tag, &n) in tags.iter().zip(&tag_uses) {
+        count(tag.key_idx(), n);
+        count(tag.value_idx(), n);
+    }
+    for relation_idx in 0..relation_members.len() {
+        for member in relation_members.at(relation_idx) {
+            let role_idx = match member {
+                RelationMembersRef::NodeMember(m) => m.role_idx(),
+                RelationMembersRef::WayMember(m) => m.role_idx(),
+                RelationMembersRef::RelationMember(m) => m.role_idx(),
+            };
+            count(role_idx, 1);
+        }
+    }
+    if let Some(infos) = infos {
+        for info in infos
+            .nodes()
+            .iter()
+            .chain(infos.ways())
+            .chain(infos.relations())
+        {
+            if let Some(idx) = info.user_idx() {
+                count(idx, 1);
+            }
+        }
+    }
+    std::mem::drop(tag_uses);
+
+    let (data, new_idx) = order_by_frequency(archive.stringtable().as_bytes(), &uses);
+    std::mem::drop(uses);
+
+    let mut new_header = osmflat::Header::new();
+    new_header.fill_from(header);
+    new_header.set_writingprogram_idx(new_idx[&header.writingprogram_idx()]);
+    new_header.set_source_idx(new_idx[&header.source_idx()]);
+    new_header.set_replication_base_url_idx(new_idx[&header.replication_base_url_idx()]);
+    new_header.set_tag_policy_idx(header.tag_policy_idx().map(|idx| new_idx[&idx]));
+    builder.set_header(&new_header)?;
+
+    let mut new_tags = builder.start_tags()?;
+    for tag in tags {
+        let new_tag = new_tags.grow()?;
+        new_tag.set_key_idx(new_idx[&tag.key_idx()]);
+        new_tag.set_value_idx(new_idx[&tag.value_idx()]);
+    }
+    new_tags.close()?;
+
+    let mut new_relation_members = builder.start_relation_members()?;
+    for relation_idx in 0..relation_members.len() {
+        let mut new_members = new_relation_members.grow()?;
+        for member in relation_members.at(relation_idx) {
+            match member {
+                RelationMembersRef::NodeMember(m) => {
+                    let new_member = new_members.add_node_member();
+                    new_member.fill_from(m);
+                    new_member.set_role_idx(new_idx[&m.role_idx()]);
+                }
+                RelationMembersRef::WayMember(m) => {
+                    let new_member = new_members.add_way_member();
+                    new_member.fill_from(m);
+                    new_member.set_role_idx(new_idx[&m.role_idx()]);
+                }
+                RelationMembersRef::RelationMember(m) => {
+                    let new_member = new_members.add_relation_member();
+                    new_member.fill_from(m);
+                    new_member.set_role_idx(new_idx[&m.role_idx()]);
+                }
+            }
+        }
+    }
+    new_relation_members.close()?;
+
+    if let Some(infos) = infos {
+        let new_infos_builder = builder.info()?;
+        for (infos, mut new_infos) in [
+            (infos.nodes(), new_infos_builder.start_nodes()?),
+            (infos.ways(), new_infos_builder.start_ways()?),
+            (infos.relations(), new_infos_builder.start_relations()?),
+        ] {
+            for info in infos {
+                let new_info = new_infos.grow()?;
+                new_info.fill_from(info);
+                new_info.set_user_idx(info.user_idx().map(|idx| new_idx[&idx]));
+            }
+            new_infos.close()?;
+        }
+    }
+
+    builder.set_stringtable(&data)?;
+    info!("Stringtable sorted.");
+    Ok(())
+}
+
+/// Orders the used strings of the stringtable `data` by their number of uses,
+/// given by their indexes in `uses`.
+///
+/// Strings stored multiple times, e.g. after spilling, are merged. Returns the
+/// new stringtable and the mapping from the old to the new indexes.
+fn order_by_frequency(data: &[u8], uses: &AHashMap<u64, u64>) -> (Vec<u8>, AHashMap<u64, u64>) {
+    let string_at = |idx: u64| {
+        let s = &data[idx as usize..];
+        &s[..s.iter().position(|&c| c == 0).unwrap_or(s.len())]
+    };
+
+    let mut strings: AHashMap<&[u8], u64> = AHashMap::new();
+    for (&idx, &n) in uses {
+        *strings.entry(string_at(idx)).or_insert(0) += n;
+    }
+    let mut strings: Vec<_> = strings.into_iter().collect();
+    // strings with the same number of uses are ordered by their contents to
+    // get a deterministic result
+    strings.sort_unstable_by(|(s1, n1), (s2, n2)| n2.cmp(n1).then_with(|| s1.cmp(s2)));
+
+    let mut sorted = Vec::new();
+    let mut sorted_idx = AHashMap::with_capacity(strings.len());
+    for (s, _) in strings {
+        sorted_idx.insert(s, sorted.len() as u64);
+        sorted.extend(s);
+        sorted.push(0);
+    }
+    let new_idx = uses
+        .keys()
+        .map(|&idx| (idx, sorted_idx[string_at(idx)]))
+        .collect();
+    (sorted, new_idx)
+}
+
 #[cfg(test)]
 mod test {
-    use super::StringTable;
+    use super::{order_by_frequency, StringTable, MIN_MAX_MEMORY};
     use proptest::prelude::*;
     use std::collections::HashSet;
 
     #[test]
     fn test_simple_insert() {
         let mut st = StringTable::new();
-        assert_eq!(st.insert("hello"), 0);
-        assert_eq!(st.insert("world"), 6);
-        assert_eq!(st.insert("world"), 6);
-        assert_eq!(st.insert("!"), 6 + 6);
-        assert_eq!(st.insert("!"), 6 + 6);
-        assert_eq!(st.insert("!"), 6 + 6);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert("world").unwrap(), 6);
+        assert_eq!(st.insert("world").unwrap(), 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
+        assert_eq!(st.insert("!").unwrap(), 6 + 6);
 
         let bytes = st.into_bytes();
         println!("{}", ::std::str::from_utf8(&bytes).unwrap());
@@ -149,11 +473,11 @@ mod test {
     #[test]
     fn test_large_insert() {
         let mut st = StringTable::new();
-        assert_eq!(st.insert("hello"), 0);
-        assert_eq!(st.insert(&str::repeat("x", 1024 * 1024 * 5)), 6);
-        assert_eq!(st.insert("huh"), 1024 * 1024 * 5 + 1 + 6);
-        assert_eq!(st.insert(&str::repeat("x", 1024 * 1024 * 5)), 6);
-        assert_eq!(st.insert("hello"), 0);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert(&str::repeat("x", 1024 * 1024 * 5)).unwrap(), 6);
+        assert_eq!(st.insert("huh").unwrap(), 1024 * 1024 * 5 + 1 + 6);
+        assert_eq!(st.insert(&str::repeat("x", 1024 * 1024 * 5)).unwrap(), 6);
+        assert_eq!(st.insert("hello").unwrap(), 0);
 
         let bytes = st.into_bytes();
         assert_eq!(
@@ -162,6 +486,74 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_spill() {
+        let path = std::env::temp_dir().join(format!("osmflatc-spill-{}", std::process::id()));
+        let mut st = StringTable::with_spill_file(path.clone(), MIN_MAX_MEMORY);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert("world").unwrap(), 6);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        // a string exceeding the limit is spilled right away
+        let large = str::repeat("x", MIN_MAX_MEMORY);
+        assert_eq!(st.insert(&large).unwrap(), 12);
+        // the reused string is still indexed, but not the other ones
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert("world").unwrap(), MIN_MAX_MEMORY as u64 + 13);
+        assert_eq!(
+            &*st.finish().unwrap(),
+            ("hello\0world\0".to_string() + &large + "\0world\0").as_bytes()
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let mut st = StringTable::with_spill_file(path.clone(), 1 << 30);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(st.insert("hello").unwrap(), 0);
+        assert_eq!(&*st.finish().unwrap(), b"hello\0");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_spill_with_tiny_limit() {
+        let path = std::env::temp_dir().join(format!("osmflatc-tiny-{}", std::process::id()));
+        // the limit is raised to the minimum
+        let mut st = StringTable::with_spill_file(path.clone(), 1);
+        let mut inserted = Vec::new();
+        for i in 0..100_000 {
+            for s in [format!("{:01000}", i), "highway".into(), "yes".into()] {
+                let idx = st.insert(&s).unwrap();
+                inserted.push((idx, s));
+            }
+        }
+        assert!(path.exists());
+        let data = st.finish().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for (idx, s) in &inserted {
+            let string = &data[*idx as usize..];
+            let len = string.iter().position(|&c| c == 0).unwrap();
+            assert_eq!(&string[..len], s.as_bytes());
+        }
+        // the frequent strings are stored once
+        let count = |s: &[u8]| data.split(|&c| c == 0).filter(|&x| x == s).count();
+        assert_eq!(count(b"highway"), 1);
+        assert_eq!(count(b"yes"), 1);
+        assert_eq!(data.len(), 100_000 * 1001 + 8 + 4);
+    }
+
+    #[test]
+    fn test_order_by_frequency() {
+        let data = b"name\0highway\0yes\0highway\0unused\0";
+        let uses = [(0, 1), (5, 2), (13, 3), (17, 2)].iter().cloned().collect();
+        let (sorted, new_idx) = order_by_frequency(data, &uses);
+        // the two copies of highway are merged
+        assert_eq!(sorted, b"highway\0yes\0name\0");
+        assert_eq!(new_idx.len(), 4);
+        assert_eq!(new_idx[&0], 12);
+        assert_eq!(new_idx[&5], 0);
+        assert_eq!(new_idx[&13], 8);
+        assert_eq!(new_idx[&17], 0);
+    }
+
     #[derive(Debug, Default)]
     struct ReferenceStringTable {
         words: HashSet<String>,
@@ -185,7 +577,7 @@ mod test {
             let mut st = StringTable::new();
             let mut reference_st = ReferenceStringTable::default();
             for input in seq {
-                st.insert(input);
+                st.insert(input).unwrap();
                 reference_st.insert(input.into());
             }
             assert_eq!(st.into_bytes(), reference_st.data);
@@ -0,0 +1,89 @@
+#!/usr/bin/env python3
+"""Generates the PBF fixtures with compressed blobs.
+
+Each fixture consists of a header block and a block with a single node
+`id=1, lat=52.5, lon=13.3, name=test`, both compressed with the same method.
+
+Requires the `zstd` command line tool.
+"""
+
+import lzma
+import subprocess
+import struct
+
+
+def varint(value):
+    result = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            result.append(byte | 0x80)
+        else:
+            result.append(byte)
+            return bytes(result)
+
+
+def zigzag(value):
+    return (value << 1) ^ (value >> 63)
+
+
+def field(number, wire_type, payload):
+    key = varint(number << 3 | wire_type)
+    if wire_type == 0:
+        return key + varint(payload)
+    return key + varint(len(payload)) + payload
+
+
+def header_block():
+    return field(4, 2, b"OsmSchema-V0.6") + field(16, 2, b"fixture")
+
+
+def primitive_block():
+    stringtable = b"".join(field(1, 2, s) for s in [b"", b"name", b"test"])
+    node = (
+        field(1, 0, zigzag(1))
+        + field(2, 2, varint(1))
+        + field(3, 2, varint(2))
+        + field(8, 0, zigzag(525_000_000))
+        + field(9, 0, zigzag(133_000_000))
+    )
+    group = field(1, 2, node)
+    return field(1, 2, stringtable) + field(2, 2, group)
+
+
+def lz4_block(data):
+    """LZ4 block consisting of a single literal run."""
+    length = len(data)
+    result = bytearray([min(length, 15) << 4])
+    if length >= 15:
+        rest = length - 15
+        while rest >= 255:
+            result.append(255)
+            rest -= 255
+        result.append(rest)
+    return bytes(result) + data
+
+
+def zstd(data):
+    return subprocess.run(["zstd", "-c", "-q"], input=data, capture_output=True, check=True).stdout
+
+
+COMPRESSIONS = {
+    "lzma": (4, lambda data: lzma.compress(data, format=lzma.FORMAT_ALONE)),
+    "lz4": (6, lz4_block),
+    "zstd": (7, zstd),
+}
+
+
+def blob(blob_type, data, compression):
+    number, compress = COMPRESSIONS[compression]
+    blob = field(2, 0, len(data)) + field(number, 2, compress(data))
+    header = field(1, 2, blob_type) + field(3, 0, len(blob))
+    return struct.pack(">i", len(header)) + header + blob
+
+
+for compression in COMPRESSIONS:
+    with open(f"{compression}.osm.pbf", "wb") as f:
+        f.write(blob(b"OSMHeader", header_block(), compression))
+        f.write(blob(b"OSMData", primitive_block(), compression))